@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::SolverError;
+use crate::nodes::chance_node::build_next;
+use crate::nodes::node::{ActionType, NodeResult, NodeResultType};
+use crate::ranges::combination::Board;
+use crate::ranges::utility::number_to_card;
+
+// mirrors OverallNodeResult in common.proto, one per runout the line can be played on. the solver only deals one
+// card of every isomorphic suit group, weight is how many real runouts this one stands for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OverallNodeResult {
+    pub runout: Vec<u8>,
+    pub action_sequence: String,
+    pub action_list: Vec<ActionType>,
+    pub bet_sizings: Vec<f32>,
+    pub overall_frequencies: Vec<f32>,
+    pub weight: u32,
+}
+
+// follows the line, given as the index of the action taken at every action node, fanning out over every card at
+// each chance node on the way. the node the line ends at gets its strategy averaged over the acting player's range.
+// board is the board the game was solved from
+pub fn frequencies_across_runouts(
+    root: &NodeResult,
+    board: &Board,
+    line: &[usize],
+) -> Result<Vec<OverallNodeResult>, SolverError> {
+    let mut results = vec![];
+    let mut sequence = vec![];
+    let mut board = *board;
+    collect_frequencies(
+        root,
+        line,
+        &mut board,
+        1,
+        &mut vec![],
+        &mut sequence,
+        &mut results,
+    )?;
+    Ok(results)
+}
+
+// the line's frequencies over every runout, with each runout counted once for every card it stands for
+pub fn weighted_frequencies(results: &[OverallNodeResult]) -> Vec<f32> {
+    let num_actions = results.first().map_or(0, |r| r.overall_frequencies.len());
+    let mut frequencies = vec![0.0; num_actions];
+    let mut total = 0.0;
+
+    for result in results.iter() {
+        let weight = result.weight as f32;
+        total += weight;
+        frequencies
+            .iter_mut()
+            .zip(result.overall_frequencies.iter())
+            .for_each(|(overall, frequency)| *overall += weight * frequency);
+    }
+
+    if total > 0.0 {
+        frequencies.iter_mut().for_each(|f| *f /= total);
+    }
+    frequencies
+}
+
+fn collect_frequencies(
+    node: &NodeResult,
+    line: &[usize],
+    board: &mut Board,
+    weight: u32,
+    runout: &mut Vec<u8>,
+    sequence: &mut Vec<String>,
+    results: &mut Vec<OverallNodeResult>,
+) -> Result<(), SolverError> {
+    match node.node_type {
+        NodeResultType::Terminal => Ok(()),
+        NodeResultType::Chance => {
+            let cards = node.next_cards.as_deref().unwrap_or_default();
+            if node.next_nodes.is_empty() {
+                return Err(SolverError::InvalidMessage(
+                    "line runs past the streets that were exported".to_string(),
+                ));
+            }
+            let slot = board.iter().position(|&card| card == 52).ok_or_else(|| {
+                SolverError::InvalidBoard("chance node below a complete board".to_string())
+            })?;
+
+            let mut dealt = vec![];
+            let mut weights = vec![];
+            build_next(board, &mut dealt, &mut weights);
+
+            for (next, card) in node.next_nodes.iter().zip(cards.iter()) {
+                let card_weight = dealt
+                    .iter()
+                    .position(|dealt| dealt == card)
+                    .map_or(1, |index| weights[index] as u32);
+
+                board[slot] = *card;
+                runout.push(*card);
+                sequence.push(number_to_card(*card));
+                collect_frequencies(
+                    next,
+                    line,
+                    board,
+                    weight * card_weight,
+                    runout,
+                    sequence,
+                    results,
+                )?;
+                sequence.pop();
+                runout.pop();
+                board[slot] = 52;
+            }
+            Ok(())
+        }
+        NodeResultType::Action => {
+            let action_list = node.action_list.clone().unwrap_or_default();
+            let bet_sizings = node.bet_sizings.clone().unwrap_or_default();
+
+            match line.split_first() {
+                None => {
+                    results.push(OverallNodeResult {
+                        runout: runout.clone(),
+                        action_sequence: sequence.join("-"),
+                        overall_frequencies: overall_frequencies(node, action_list.len()),
+                        action_list,
+                        bet_sizings,
+                        weight,
+                    });
+                    Ok(())
+                }
+                Some((&action, rest)) => {
+                    let child = node
+                        .next_nodes
+                        .get(action)
+                        .filter(|child| !matches!(child.node_type, NodeResultType::Terminal))
+                        .ok_or_else(|| {
+                            SolverError::InvalidMessage(format!(
+                                "action {} at '{}' doesn't lead to another decision",
+                                action,
+                                sequence.join("-")
+                            ))
+                        })?;
+
+                    sequence.push(action_label(action_list[action], bet_sizings[action]));
+                    collect_frequencies(child, rest, board, weight, runout, sequence, results)?;
+                    sequence.pop();
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+pub fn overall_frequencies(node: &NodeResult, num_actions: usize) -> Vec<f32> {
+    let mut frequencies = vec![0.0; num_actions];
+    let mut total = 0.0;
+
+    for hand in node.hand_actions.iter().flatten() {
+        total += hand.frequency;
+        frequencies
+            .iter_mut()
+            .zip(hand.action_frequency.iter())
+            .for_each(|(overall, action)| *overall += hand.frequency * action);
+    }
+
+    if total > 0.0 {
+        frequencies.iter_mut().for_each(|f| *f /= total);
+    }
+    frequencies
+}
+
+pub fn action_label(action: ActionType, sizing: f32) -> String {
+    match action {
+        ActionType::Fold => "F".to_string(),
+        ActionType::Check => "X".to_string(),
+        ActionType::Call => "C".to_string(),
+        ActionType::Bet => format!("B{}", sizing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::trained_game;
+    use crate::nodes::node::{CombinationActions, ResultFilter};
+    use crate::ranges::utility::{card_to_number, parse_board};
+
+    fn turn_results() -> NodeResult {
+        trained_game("as,kh,7c,2d", "QQ,JJ,AK", "QQ,TT,AK")
+            .results(&ResultFilter::default())
+            .unwrap()
+            .node_results
+    }
+
+    #[test]
+    fn test_oop_river_frequencies_after_turn_checks_through() {
+        let root = turn_results();
+        let board = parse_board("as,kh,7c,2d").unwrap();
+        let results = frequencies_across_runouts(&root, &board, &[0, 0]).unwrap();
+
+        assert_eq!(results.len(), 48);
+        for result in results.iter() {
+            assert_eq!(result.runout.len(), 1);
+            assert_eq!(result.weight, 1);
+            assert_eq!(result.action_list, vec![ActionType::Check, ActionType::Bet]);
+            assert_eq!(result.bet_sizings, vec![0.0, 5.0]);
+            assert!((result.overall_frequencies.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            assert!(result.action_sequence.starts_with("X-X-"));
+        }
+    }
+
+    #[test]
+    fn test_line_into_a_terminal_is_rejected() {
+        let root = turn_results();
+        let board = parse_board("as,kh,7c,2d").unwrap();
+        assert!(frequencies_across_runouts(&root, &board, &[0, 0, 0, 0]).is_err());
+        assert!(frequencies_across_runouts(&root, &board, &[9]).is_err());
+    }
+
+    fn river_decision(action_frequency: Vec<f32>) -> NodeResult {
+        let mut node = NodeResult::terminal(3);
+        node.node_type = NodeResultType::Action;
+        node.player = Some(0);
+        node.action_list = Some(vec![ActionType::Check, ActionType::Bet]);
+        node.bet_sizings = Some(vec![0.0, 5.0]);
+        node.hand_actions = Some(vec![CombinationActions {
+            combination: "QdQc".to_string(),
+            frequency: 1.0,
+            action_frequency,
+        }]);
+        node
+    }
+
+    #[test]
+    fn test_runouts_are_weighted_by_their_isomorphic_cards() {
+        // on a four spade turn the heart river stands in for the club and diamond ones too
+        let board = parse_board("as,ks,7s,2s").unwrap();
+        let mut root = NodeResult::terminal(2);
+        root.node_type = NodeResultType::Chance;
+        root.next_cards = Some(vec![
+            card_to_number("qh".to_string()).unwrap(),
+            card_to_number("qs".to_string()).unwrap(),
+        ]);
+        root.next_nodes = vec![
+            river_decision(vec![1.0, 0.0]),
+            river_decision(vec![0.0, 1.0]),
+        ];
+
+        let results = frequencies_across_runouts(&root, &board, &[]).unwrap();
+        let weights: Vec<u32> = results.iter().map(|r| r.weight).collect();
+        assert_eq!(weights, vec![3, 1]);
+        assert_eq!(weighted_frequencies(&results), vec![0.75, 0.25]);
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::aggregation::{action_label, overall_frequencies};
+use super::cancellation::CancellationToken;
+use super::game::Game;
+use super::game_params::GameParams;
+use super::solution_file::encode_solution;
+use super::traversal::build_traversal_from_ranges;
+use crate::error::SolverError;
+use crate::nodes::node::{ActionType, NodeResult, NodeResultType, ResultFilter};
+use crate::ranges::combination::Board;
+use crate::ranges::flops::{canonical_flops, representative_flops, WeightedFlop};
+use crate::ranges::texture::{classify_board, BoardTexture};
+use crate::ranges::utility::{number_to_card, parse_board};
+
+const PROGRESS_FILE: &str = "progress.jsonl";
+const REPORT_FILE: &str = "report.json";
+const CONFIG_FILE: &str = "batch.json";
+
+// flops default to all 1755 canonical flops, weighted by how many raw flops each stands for, or to a representative
+// subset of that many flops when representative is set
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchConfig {
+    pub oop_range: String,
+    pub ip_range: String,
+    pub game_params: GameParams,
+    pub target_exploitability: Option<f32>,
+    pub flops: Option<Vec<String>>,
+    pub representative: Option<usize>,
+}
+
+// the strategy of a player's first decision on the flop averaged over their range, for ip that's after oop checks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionSummary {
+    pub actions: Vec<String>,
+    pub frequencies: Vec<f32>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlopSummary {
+    pub board: Board,
+    pub weight: f32,
+    pub oop_ev: Option<f32>,
+    pub ip_ev: Option<f32>,
+    pub oop_actions: ActionSummary,
+    pub ip_actions: Option<ActionSummary>,
+    pub texture: Option<BoardTexture>,
+}
+
+// weighted averages over the batch, an action only counts for the flops that offered it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub total_weight: f32,
+    pub oop_ev: f32,
+    pub ip_ev: f32,
+    pub oop_actions: ActionSummary,
+    pub ip_actions: ActionSummary,
+    pub textures: Vec<GroupReport>,
+    pub flops: Vec<FlopSummary>,
+}
+
+// the same averages over just the flops with one texture label, e.g. monotone or aceHigh
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupReport {
+    pub group: String,
+    pub total_weight: f32,
+    pub oop_ev: f32,
+    pub ip_ev: f32,
+    pub oop_actions: ActionSummary,
+    pub ip_actions: ActionSummary,
+}
+
+impl BatchConfig {
+    pub fn weighted_flops(&self) -> Result<Vec<WeightedFlop>, SolverError> {
+        match &self.flops {
+            None => Ok(match self.representative {
+                Some(n) => representative_flops(n),
+                None => canonical_flops(),
+            }),
+            Some(flops) => flops
+                .iter()
+                .map(|flop| {
+                    Ok(WeightedFlop {
+                        board: parse_board(flop)?,
+                        weight: 1.0,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+// solves every flop in turn, writing each solution to the output directory and appending its summary to a progress
+// file as soon as it's done. running it again on the same directory skips the flops already in the progress file,
+// so a batch that was stopped picks up where it left off. the directory remembers the ranges, params and target it was
+// solved with and refuses to resume with others. the report only covers the flops asked for
+pub fn run_batch(
+    config: &BatchConfig,
+    flops: &[WeightedFlop],
+    output_dir: &Path,
+    cancel: &CancellationToken,
+) -> Result<BatchReport, SolverError> {
+    fs::create_dir_all(output_dir)?;
+    check_fingerprint(config, output_dir)?;
+    let progress_path = output_dir.join(PROGRESS_FILE);
+
+    let mut summaries = read_progress(&progress_path)?;
+    let done: HashSet<Board> = summaries.iter().map(|summary| summary.board).collect();
+    let mut progress = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&progress_path)?;
+
+    for (i, flop) in flops.iter().enumerate() {
+        if done.contains(&flop.board) {
+            continue;
+        }
+        cancel.check()?;
+        info!(
+            "Solving flop {} of {}: {}",
+            i + 1,
+            flops.len(),
+            board_name(&flop.board)
+        );
+
+        let traversal =
+            build_traversal_from_ranges(flop.board, &config.oop_range, &config.ip_range)?;
+        let mut game = Game::new(traversal, config.game_params.clone(), flop.board);
+        game.train_with_progress(config.target_exploitability.unwrap_or(0.35), cancel, |_| {})?;
+
+        let solution = File::create(output_dir.join(format!("{}.sol", board_name(&flop.board))))?;
+        encode_solution(&game, &ResultFilter::default(), BufWriter::new(solution))?;
+
+        // the summary only looks at the flop decisions
+        let root = game.node_results(&ResultFilter {
+            max_street: Some(1),
+            runout: vec![],
+        })?;
+        let summary = summarize_flop(&root, flop)?;
+        writeln!(progress, "{}", serde_json::to_string(&summary)?)?;
+        progress.flush()?;
+        summaries.push(summary);
+    }
+
+    let requested: HashSet<Board> = flops.iter().map(|flop| flop.board).collect();
+    summaries.retain(|summary| requested.contains(&summary.board));
+    let report = aggregate(summaries);
+    fs::write(
+        output_dir.join(REPORT_FILE),
+        serde_json::to_vec_pretty(&report)?,
+    )?;
+    Ok(report)
+}
+
+pub fn summarize_flop(root: &NodeResult, flop: &WeightedFlop) -> Result<FlopSummary, SolverError> {
+    let oop_actions = action_summary(root).ok_or_else(|| {
+        SolverError::Runtime("flop solution has no strategy at the root".to_string())
+    })?;
+
+    // ip's first decision comes after oop checks
+    let ip_actions = root
+        .action_list
+        .iter()
+        .flatten()
+        .position(|action| *action == ActionType::Check)
+        .and_then(|check| root.next_nodes.get(check))
+        .and_then(action_summary);
+
+    Ok(FlopSummary {
+        board: flop.board,
+        weight: flop.weight,
+        oop_ev: root.oop_values.as_ref().and_then(|values| values.range_ev),
+        ip_ev: root.ip_values.as_ref().and_then(|values| values.range_ev),
+        oop_actions,
+        ip_actions,
+        texture: classify_board(&flop.board).ok(),
+    })
+}
+
+fn action_summary(node: &NodeResult) -> Option<ActionSummary> {
+    if !matches!(node.node_type, NodeResultType::Action) {
+        return None;
+    }
+    let actions = node.action_list.as_ref()?;
+    let sizings = node.bet_sizings.as_ref()?;
+
+    Some(ActionSummary {
+        actions: actions
+            .iter()
+            .zip(sizings.iter())
+            .map(|(action, sizing)| action_label(*action, *sizing))
+            .collect(),
+        frequencies: overall_frequencies(node, actions.len()),
+    })
+}
+
+pub fn aggregate(flops: Vec<FlopSummary>) -> BatchReport {
+    let all = summarize_group("all", flops.iter());
+
+    let mut labels: Vec<String> = flops.iter().flat_map(texture_labels).collect();
+    labels.sort();
+    labels.dedup();
+    let textures = labels
+        .iter()
+        .map(|label| {
+            summarize_group(
+                label,
+                flops
+                    .iter()
+                    .filter(|flop| texture_labels(flop).contains(label)),
+            )
+        })
+        .collect();
+
+    BatchReport {
+        total_weight: all.total_weight,
+        oop_ev: all.oop_ev,
+        ip_ev: all.ip_ev,
+        oop_actions: all.oop_actions,
+        ip_actions: all.ip_actions,
+        textures,
+        flops,
+    }
+}
+
+fn texture_labels(flop: &FlopSummary) -> Vec<String> {
+    flop.texture
+        .map(|texture| texture.labels())
+        .unwrap_or_default()
+}
+
+fn summarize_group<'a>(
+    group: &str,
+    flops: impl Iterator<Item = &'a FlopSummary> + Clone,
+) -> GroupReport {
+    let weighted_ev = |ev: fn(&FlopSummary) -> Option<f32>| {
+        let (sum, weight) = flops
+            .clone()
+            .fold((0.0, 0.0), |(sum, weight), flop| match ev(flop) {
+                Some(ev) => (sum + ev * flop.weight, weight + flop.weight),
+                None => (sum, weight),
+            });
+        if weight > 0.0 {
+            sum / weight
+        } else {
+            0.0
+        }
+    };
+
+    GroupReport {
+        group: group.to_string(),
+        total_weight: flops.clone().map(|flop| flop.weight).sum(),
+        oop_ev: weighted_ev(|flop| flop.oop_ev),
+        ip_ev: weighted_ev(|flop| flop.ip_ev),
+        oop_actions: average_actions(flops.clone().map(|flop| (&flop.oop_actions, flop.weight))),
+        ip_actions: average_actions(flops.filter_map(|flop| {
+            flop.ip_actions
+                .as_ref()
+                .map(|actions| (actions, flop.weight))
+        })),
+    }
+}
+
+fn average_actions<'a>(summaries: impl Iterator<Item = (&'a ActionSummary, f32)>) -> ActionSummary {
+    let mut actions: Vec<String> = vec![];
+    let mut totals: Vec<(f32, f32)> = vec![];
+
+    for (summary, weight) in summaries {
+        for (action, frequency) in summary.actions.iter().zip(summary.frequencies.iter()) {
+            let i = match actions.iter().position(|a| a == action) {
+                Some(i) => i,
+                None => {
+                    actions.push(action.clone());
+                    totals.push((0.0, 0.0));
+                    actions.len() - 1
+                }
+            };
+            totals[i].0 += frequency * weight;
+            totals[i].1 += weight;
+        }
+    }
+
+    ActionSummary {
+        actions,
+        frequencies: totals
+            .iter()
+            .map(|(sum, weight)| if *weight > 0.0 { sum / weight } else { 0.0 })
+            .collect(),
+    }
+}
+
+fn read_progress(path: &Path) -> Result<Vec<FlopSummary>, SolverError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut summaries = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        // a line cut short by a crash is solved again rather than failing the whole batch
+        match serde_json::from_str(&line) {
+            Ok(summary) => summaries.push(summary),
+            Err(_) if !line.trim().is_empty() => {
+                info!("Ignoring unreadable progress line {}", line)
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(summaries)
+}
+
+// what decides the solutions, the flops themselves are left out so a directory can be filled in over several runs
+fn fingerprint(config: &BatchConfig) -> Result<serde_json::Value, SolverError> {
+    Ok(serde_json::json!({
+        "oopRange": config.oop_range,
+        "ipRange": config.ip_range,
+        "gameParams": serde_json::to_value(&config.game_params)?,
+        "targetExploitability": config.target_exploitability,
+    }))
+}
+
+fn check_fingerprint(config: &BatchConfig, output_dir: &Path) -> Result<(), SolverError> {
+    let path = output_dir.join(CONFIG_FILE);
+    let current = fingerprint(config)?;
+    if path.exists() {
+        let stored: serde_json::Value = serde_json::from_slice(&fs::read(&path)?)?;
+        if stored != current {
+            return Err(SolverError::InvalidMessage(format!(
+                "{} was batch solved with other ranges, params or target, use a new output directory",
+                output_dir.display()
+            )));
+        }
+        return Ok(());
+    }
+    fs::write(path, serde_json::to_vec_pretty(&current)?)?;
+    Ok(())
+}
+
+fn board_name(board: &Board) -> String {
+    board
+        .iter()
+        .filter(|card| **card != 52)
+        .map(|card| number_to_card(*card))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::test_params;
+
+    fn config(boards: &[&str]) -> BatchConfig {
+        BatchConfig {
+            oop_range: "QQ,JJ,AK".to_string(),
+            ip_range: "QQ,TT,AK".to_string(),
+            game_params: GameParams {
+                oop_flop_bets: vec![vec![0.5]],
+                oop_turn_bets: vec![vec![0.5]],
+                ip_flop_bets: vec![vec![0.5]],
+                ip_turn_bets: vec![vec![0.5]],
+                ..test_params(vec![vec![0.5]])
+            },
+            target_exploitability: Some(1.0),
+            flops: Some(boards.iter().map(|b| b.to_string()).collect()),
+            representative: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_resumes_from_progress() {
+        let dir = std::env::temp_dir().join(format!("batch-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // turn boards keep the test quick, the batch doesn't care which street it starts on
+        let config = config(&["as,kh,7c,2d", "qs,jh,5c,3d"]);
+        let flops = config.weighted_flops().unwrap();
+
+        let first = run_batch(&config, &flops[..1], &dir, &CancellationToken::new()).unwrap();
+        assert_eq!(first.flops.len(), 1);
+
+        // a stopped token would fail any flop that still needs solving, the finished one comes from the progress file
+        let stopped = CancellationToken::new();
+        stopped.cancel();
+        assert!(run_batch(&config, &flops[..1], &dir, &stopped).is_ok());
+        assert!(run_batch(&config, &flops, &dir, &stopped).is_err());
+
+        let report = run_batch(&config, &flops, &dir, &CancellationToken::new()).unwrap();
+        assert_eq!(report.flops.len(), 2);
+        assert_eq!(report.total_weight, 2.0);
+        assert_eq!(report.oop_actions.actions, vec!["X", "B5"]);
+        assert!((report.oop_actions.frequencies.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!((report.oop_ev + report.ip_ev - 10.0).abs() < 0.1);
+        assert!(dir.join("QsJh5c3d.sol").exists());
+
+        // both boards are ace or queen high, rainbow and unpaired
+        let rainbow = report
+            .textures
+            .iter()
+            .find(|t| t.group == "rainbow")
+            .unwrap();
+        assert_eq!(rainbow.total_weight, 2.0);
+        assert_eq!(rainbow.oop_actions, report.oop_actions);
+        assert!(report
+            .textures
+            .iter()
+            .any(|t| t.group == "aceHigh" && t.total_weight == 1.0));
+
+        // the report sticks to the flops asked for, even with more of them in the progress file
+        let first_only = run_batch(&config, &flops[..1], &dir, &CancellationToken::new()).unwrap();
+        assert_eq!(first_only.flops.len(), 1);
+        assert_eq!(first_only.total_weight, 1.0);
+
+        // solutions from other ranges aren't reused
+        let mut changed = config.clone();
+        changed.ip_range = "QQ,TT".to_string();
+        assert!(matches!(
+            run_batch(&changed, &flops, &dir, &CancellationToken::new()),
+            Err(SolverError::InvalidMessage(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
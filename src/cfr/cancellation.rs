@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use crate::error::SolverError;
+
+const RUNNING: u8 = 0;
+const CANCELLED: u8 = 1;
+const INTERRUPTED: u8 = 2;
+
+// shared between whoever can stop a solve and the training loop, which checks it between iterations. a cancelled
+// job is abandoned for good, an interrupted one is expected to run again somewhere else
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    state: Arc<watch::Sender<u8>>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        let (state, _) = watch::channel(RUNNING);
+        Self {
+            state: Arc::new(state),
+        }
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.stop(CANCELLED);
+    }
+
+    pub fn interrupt(&self) {
+        self.stop(INTERRUPTED);
+    }
+
+    fn stop(&self, reason: u8) {
+        self.state.send_if_modified(|state| {
+            let running = *state == RUNNING;
+            if running {
+                *state = reason;
+            }
+            running
+        });
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.state.borrow() != RUNNING
+    }
+
+    pub fn check(&self) -> Result<(), SolverError> {
+        match *self.state.borrow() {
+            CANCELLED => Err(SolverError::Cancelled),
+            INTERRUPTED => Err(SolverError::Interrupted),
+            _ => Ok(()),
+        }
+    }
+
+    // resolves once the job is stopped, with the error check gives from then on
+    pub async fn stopped(&self) -> SolverError {
+        let mut state = self.state.subscribe();
+        loop {
+            if let Err(e) = self.check() {
+                return e;
+            }
+            // self keeps the sender alive, so this can't fail while we wait
+            let _ = state.changed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_stop_wins() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+
+        let shared = token.clone();
+        shared.cancel();
+        token.interrupt();
+
+        assert!(token.is_stopped());
+        assert!(matches!(token.check(), Err(SolverError::Cancelled)));
+    }
+}
@@ -0,0 +1,77 @@
+// the discounted cfr updates and strategy helpers of the preflop and multiway action nodes. regrets, strategies and
+// strategy sums are laid out action by action with one entry per hand
+
+pub fn update_regrets(
+    regrets: &mut [f32],
+    action_values: &[Vec<f32>],
+    values: &[f32],
+    iteration: u32,
+) {
+    let alpha = f64::from(iteration).powf(1.45);
+    let positive_multiplier = (alpha / (alpha + 1.0)) as f32;
+    let negative_multiplier = 0.5;
+    let hands = values.len();
+
+    for (action, action_value) in action_values.iter().enumerate() {
+        for hand in 0..hands {
+            let regret = &mut regrets[hand + action * hands];
+            *regret += action_value[hand] - values[hand];
+            *regret *= if *regret > 0.0 {
+                positive_multiplier
+            } else {
+                negative_multiplier
+            };
+        }
+    }
+}
+
+// reach is the acting player's own reach, so this runs in a pass where they aren't the traverser and their strategy
+// has already been applied above the node
+pub fn update_strategy_sum(
+    strategy_sum: &mut [f32],
+    strategy: &[f32],
+    reach: &[f32],
+    iteration: u32,
+) {
+    let strategy_multiplier = (f64::from(iteration) / f64::from(iteration + 1)).powi(2) as f32;
+    let hands = reach.len();
+
+    for (index, sum) in strategy_sum.iter_mut().enumerate() {
+        *sum = *sum * strategy_multiplier + reach[index % hands] * strategy[index];
+    }
+}
+
+// per hand shares of the positive weights across actions, uniform when a hand has none
+pub fn normalize(weights: &[f32], num_actions: usize, weight: impl Fn(f32) -> f32) -> Vec<f32> {
+    let hands = weights.len() / num_actions;
+    let mut strategy = vec![0.0; weights.len()];
+    for hand in 0..hands {
+        let total: f32 = (0..num_actions)
+            .map(|action| weight(weights[hand + action * hands]))
+            .sum();
+        for action in 0..num_actions {
+            let index = hand + action * hands;
+            strategy[index] = if total > 0.0 {
+                weight(weights[index]) / total
+            } else {
+                1.0 / num_actions as f32
+            };
+        }
+    }
+    strategy
+}
+
+// the reaches below an action, with the acting player's scaled by how often they take it
+pub fn next_reach(
+    reach: &[Vec<f32>],
+    player: usize,
+    strategy: &[f32],
+    action: usize,
+) -> Vec<Vec<f32>> {
+    let hands = reach[player].len();
+    let mut next = reach.to_vec();
+    for (hand, reach) in next[player].iter_mut().enumerate() {
+        *reach *= strategy[hand + action * hands];
+    }
+    next
+}
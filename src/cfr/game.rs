@@ -1,9 +1,15 @@
-use super::{game_params::GameParams, traversal::Traversal};
+use super::{cancellation::CancellationToken, game_params::GameParams, traversal::Traversal};
+use super::payoff::PayoffModel;
+use super::solution_file::{encode_solution, SolutionMetadata};
+use crate::error::SolverError;
 use crate::nodes::all_in_showdown_node::AllInShowdownNode;
+use crate::nodes::blueprint_value_node::BlueprintValueNode;
 use crate::nodes::chance_node::ChanceNode;
-use crate::nodes::node::{CfrNode, NodeResult};
-use crate::ranges::combination::Combination;
+use crate::nodes::node::{CfrNode, NodeResult, ResultFilter, ResultSink};
+use crate::ranges::combination::{Combination, Hand};
+use crate::ranges::parser::ordered;
 use crate::ranges::range_manager::RangeManager;
+use crate::ranges::texture::{classify_board, BoardTexture};
 use crate::ranges::utility::{number_to_card, range_relative_probabilities};
 use crate::{nodes::{
     action_node::ActionNode, node::Node, showdown_node::ShowdownNode,
@@ -11,7 +17,13 @@ use crate::{nodes::{
 }, ranges::{combination::Board, utility::unblocked_hands}};
 use cloud_storage::Client;
 
+use futures_lite::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
 use tracing::info;
 use crate::cfr::traversal::build_traversal_from_ranges;
 use crate::nodes::node::Node::{
@@ -22,26 +34,60 @@ use crate::nodes::node::Node::{
     TerminalNode as OtherTerminalNode
 };
 
+// solutions are uploaded in chunks of about this size, with a few queued while the upload catches up
+const UPLOAD_CHUNK_BYTES: usize = 1 << 20;
+const UPLOAD_CHUNKS_IN_FLIGHT: usize = 4;
+
+// the solution is uploaded to bucket_name when there is one, its file name is returned either way
 pub async fn run_trainer(
     board: Board,
     oop_range: &str,
     ip_range: &str,
     params: GameParams,
-    bucket_name: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let traversal = build_traversal_from_ranges(board, oop_range, ip_range);
-
-    let mut game = Game::new(traversal, params, board);
+    bucket_name: Option<&str>,
+    num_threads: usize,
+    filter: ResultFilter,
+    cancel: CancellationToken,
+    on_progress: impl FnMut(TrainingProgress) + Send + 'static,
+) -> Result<String, SolverError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()?;
+    let oop_range = oop_range.to_string();
+    let ip_range = ip_range.to_string();
+
+    // training is cpu bound, keep it off the async workers and inside this job's own pool so concurrent solves
+    // don't compete for the global rayon threads
+    let game = tokio::task::spawn_blocking(move || {
+        pool.install(|| -> Result<Game, SolverError> {
+            let traversal = build_traversal_from_ranges(board, &oop_range, &ip_range)?;
+            let mut game = Game::new(traversal, params, board);
+            game.train_with_progress(0.35, &cancel, on_progress)?;
+            Ok(game)
+        })
+    })
+    .await??;
 
-    game.train(0.35);
     let file_name = format!(
-        "{}{}{}.json",
+        "{}{}{}.sol",
         number_to_card(board[0]),
         number_to_card(board[1]),
         number_to_card(board[2])
     );
-    // game.output_results(bucket_name, file_name.as_ref()).await?;
-    Ok(())
+    // only jobs from the queue upload, local runs just train
+    if let Some(bucket_name) = bucket_name {
+        game.output_results(bucket_name, file_name.as_ref(), filter)
+            .await?;
+    }
+    Ok(file_name)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingProgress {
+    pub iteration: u32,
+    pub exploitability: f32,
+    pub target_exploitability: f32,
 }
 
 #[serde_with::skip_serializing_none]
@@ -52,6 +98,7 @@ pub struct GameResult {
     pub ip_range: Vec<Combination>,
     pub game_params: GameParams,
     pub starting_board: Board,
+    pub texture: BoardTexture,
     pub node_results: NodeResult,
 }
 
@@ -60,6 +107,8 @@ pub struct Game {
     pub root: Node,
     game_params: GameParams,
     starting_board: Board,
+    // the player who may take their blueprint values instead of playing the subgame, see with_resolve_gadget
+    gadget: Option<(u8, Vec<(Hand, f32)>)>,
 }
 
 impl Game {
@@ -69,11 +118,34 @@ impl Game {
             game_params,
             root: OtherShowdownNode(ShowdownNode::new(0.0)),
             starting_board,
+            gadget: None,
         }
     }
 
-    pub fn train(&mut self, target_nash_distance: f32) {
-        self.construct_tree();
+    // safe re-solving with the resolve gadget. before the subgame starts the player gets to choose, hand by hand,
+    // between playing it and ending the hand with the counterfactual value they had in the blueprint, given per unit
+    // of unblocked reach and relative to half the pot. the other player's new strategy then can't give them more than
+    // the blueprint did, however the finer tree changes it. the gadget only shapes training, the persisted values and
+    // results are the subgame's own
+    pub fn with_resolve_gadget(mut self, player: u8, blueprint_values: Vec<(Hand, f32)>) -> Self {
+        self.gadget = Some((player, blueprint_values));
+        self
+    }
+
+    pub fn train(&mut self, target_nash_distance: f32) -> Result<(), SolverError> {
+        self.train_with_progress(target_nash_distance, &CancellationToken::new(), |_| {})
+    }
+
+    // on_progress is called every time exploitability is measured, before deciding whether to stop. the token is
+    // checked before every iteration, so a stop request takes effect within one pass over the tree
+    pub fn train_with_progress(
+        &mut self,
+        target_nash_distance: f32,
+        cancel: &CancellationToken,
+        mut on_progress: impl FnMut(TrainingProgress),
+    ) -> Result<(), SolverError> {
+        self.game_params.validate()?;
+        self.construct_tree()?;
 
         self.traversal.traverser = 0;
 
@@ -90,16 +162,33 @@ impl Game {
 
         let mut iterations = 0;
         loop {
+            cancel.check()?;
             if iterations % 25 == 0 {
                 self.traversal.traverser = 0;
                 let oop_br = self.overall_best_response(&oop_relative_probs, &ip);
                 self.traversal.traverser = 1;
                 let ip_br = self.overall_best_response(&ip_relative_probs, &oop);
-                let exploitability = (ip_br + oop_br) / 2.0 / self.game_params.starting_pot * 100.0;
+                let mut nash_distance = ip_br + oop_br;
+                // outside chip ev the payoffs aren't zero sum, so what each player's strategy already gets comes off
+                // their best response
+                if self.game_params.payoff_model != PayoffModel::ChipEv {
+                    self.traversal.follow_strategy = true;
+                    self.traversal.traverser = 0;
+                    nash_distance -= self.overall_best_response(&oop_relative_probs, &ip);
+                    self.traversal.traverser = 1;
+                    nash_distance -= self.overall_best_response(&ip_relative_probs, &oop);
+                    self.traversal.follow_strategy = false;
+                }
+                let exploitability = nash_distance / 2.0 / self.game_params.starting_pot * 100.0;
                 info!(
                     "Iteration {} OOP BR {} IP BR {} exploitability = {} percent of the pot",
                     iterations, oop_br, ip_br, exploitability
                 );
+                on_progress(TrainingProgress {
+                    iteration: iterations,
+                    exploitability,
+                    target_exploitability: target_nash_distance,
+                });
                 if exploitability < target_nash_distance {
                     break;
                 }
@@ -115,13 +204,16 @@ impl Game {
             iterations += 1;
         }
 
+        self.remove_resolve_gadget();
         info!("Reached target exploitability, persisting node EVs");
         self.traversal.persist_evs = true;
+        self.traversal.add_pot_share = self.game_params.payoff_model == PayoffModel::ChipEv;
         self.traversal.traverser = 0;
         self.overall_best_response(&oop_relative_probs, &ip);
         self.traversal.traverser = 1;
         self.overall_best_response(&ip_relative_probs, &oop);
         info!("Done persisting node EVs");
+        Ok(())
     }
 
     fn overall_best_response(
@@ -147,24 +239,86 @@ impl Game {
         sum
     }
 
-    fn construct_tree(&mut self) {
+    fn construct_tree(&mut self) -> Result<(), SolverError> {
         let mut root = ActionNode::new(
             0,
             self.traversal
-                .get_num_hands_for_traverser(&self.starting_board),
+                .get_num_hands_for_player(0, &self.starting_board)?,
             self.game_params.starting_pot,
-            self.game_params.starting_stack,
-            self.game_params.starting_stack,
+            self.game_params.effective_stack(),
+            self.game_params.effective_stack(),
         );
 
         let board = self.starting_board;
 
-        self.add_successor_nodes(&mut root, 0, &board);
+        self.add_successor_nodes(&mut root, 0, &board)?;
 
-        self.root = OtherActionNode(root);
+        self.root = match &self.gadget {
+            Some((player, values)) => OtherActionNode(self.resolve_gadget(*player, values, root)?),
+            None => OtherActionNode(root),
+        };
+        Ok(())
     }
 
-    fn add_successor_nodes(&mut self, root: &mut ActionNode, bet_number: u8, board: &Board) {
+    // the gadget player acts first with two options, the first ends the hand at their blueprint values and the
+    // second plays the subgame
+    fn resolve_gadget(
+        &self,
+        player: u8,
+        blueprint_values: &[(Hand, f32)],
+        subgame: ActionNode,
+    ) -> Result<ActionNode, SolverError> {
+        let by_hand: HashMap<Hand, f32> = blueprint_values
+            .iter()
+            .map(|(hand, value)| (ordered(hand), *value))
+            .collect();
+        let hands = if player == 1 {
+            self.traversal
+                .ip_rm
+                .get_range_for_board(&self.starting_board)
+        } else {
+            self.traversal
+                .oop_rm
+                .get_range_for_board(&self.starting_board)
+        };
+        // hands the blueprint has no value for never reached the node, whatever they choose doesn't count
+        let values: Vec<f32> = hands
+            .iter()
+            .map(|combo| by_hand.get(&ordered(&combo.hand)).copied().unwrap_or(0.0))
+            .collect();
+
+        let mut gadget = ActionNode::new(
+            player,
+            self.traversal
+                .get_num_hands_for_player(player, &self.starting_board)?,
+            self.game_params.starting_pot,
+            self.game_params.effective_stack(),
+            self.game_params.effective_stack(),
+        );
+        gadget.add_child(Node::BlueprintValueNode(BlueprintValueNode::new(
+            player, values,
+        )));
+        gadget.add_child(OtherActionNode(subgame));
+        gadget.init_vectors();
+        Ok(gadget)
+    }
+
+    fn remove_resolve_gadget(&mut self) {
+        if self.gadget.is_none() {
+            return;
+        }
+        let root = std::mem::replace(&mut self.root, OtherShowdownNode(ShowdownNode::new(0.0)));
+        if let OtherActionNode(gadget) = root {
+            self.root = gadget.into_child(1);
+        }
+    }
+
+    fn add_successor_nodes(
+        &mut self,
+        root: &mut ActionNode,
+        bet_number: u8,
+        board: &Board,
+    ) -> Result<(), SolverError> {
         let mut street = 3;
         if board[3] == 52 {
             street = 1;
@@ -173,16 +327,17 @@ impl Game {
         }
 
         if root.player_node == 1 || bet_number > 0 {
-            self.create_next_call_check_and_fold_nodes(root, bet_number, street, board);
+            self.create_next_call_check_and_fold_nodes(root, bet_number, street, board)?;
         } else {
-            self.create_check_to_ip_node(root, bet_number, street, board);
+            self.create_check_to_ip_node(root, bet_number, street, board)?;
         }
 
         if root.oop_stack > 0.0 && root.ip_stack > 0.0 {
-            self.create_next_bet_nodes(root, bet_number, street, board)
+            self.create_next_bet_nodes(root, bet_number, street, board)?;
         }
 
         root.init_vectors();
+        Ok(())
     }
 
     fn create_next_call_check_and_fold_nodes(
@@ -191,15 +346,22 @@ impl Game {
         bet_number: u8,
         street: u8,
         board: &Board,
-    ) {
+    ) -> Result<(), SolverError> {
         let last_bet_size = (root.ip_stack - root.oop_stack).abs();
         let call_stacks = root.ip_stack.min(root.oop_stack);
 
         if street == 3 {
-            let next = ShowdownNode::new(root.pot_size + last_bet_size);
+            let next = ShowdownNode::with_payoffs(
+                self.game_params
+                    .terminal_payoffs(root.pot_size + last_bet_size),
+            );
             root.add_child(OtherShowdownNode(next));
         } else if call_stacks == 0.0 {
-            let next = AllInShowdownNode::new(root.pot_size + last_bet_size, street);
+            let next = AllInShowdownNode::with_payoffs(
+                self.game_params
+                    .terminal_payoffs(root.pot_size + last_bet_size),
+                street,
+            );
             root.add_child(OtherAllInShowdownNode(next));
         } else {
             let mut next = if self.game_params.parallel_street == street {
@@ -220,13 +382,13 @@ impl Game {
 
                 let mut next_game_node = ActionNode::new(
                     0,
-                    self.traversal.get_num_hands_for_player(0, &new_board),
+                    self.traversal.get_num_hands_for_player(0, &new_board)?,
                     root.pot_size + last_bet_size,
                     call_stacks,
                     call_stacks,
                 );
 
-                self.add_successor_nodes(&mut next_game_node, 0, &new_board);
+                self.add_successor_nodes(&mut next_game_node, 0, &new_board)?;
                 next.add_next_node(OtherActionNode(next_game_node));
             }
 
@@ -234,9 +396,14 @@ impl Game {
         }
 
         if bet_number > 0 {
-            let fold = TerminalNode::new(root.pot_size - last_bet_size, root.player_node ^ 1);
+            let fold = TerminalNode::with_payoffs(
+                self.game_params
+                    .terminal_payoffs(root.pot_size - last_bet_size),
+                root.player_node ^ 1,
+            );
             root.add_child(OtherTerminalNode(fold));
         }
+        Ok(())
     }
 
     fn create_check_to_ip_node(
@@ -245,18 +412,19 @@ impl Game {
         _bet_number: u8,
         _street: u8,
         board: &Board,
-    ) {
+    ) -> Result<(), SolverError> {
         let mut next = ActionNode::new(
             1,
-            self.traversal.get_num_hands_for_player(1, board),
+            self.traversal.get_num_hands_for_player(1, board)?,
             root.pot_size,
             root.ip_stack,
             root.oop_stack,
         );
 
-        self.add_successor_nodes(&mut next, 0, board);
+        self.add_successor_nodes(&mut next, 0, board)?;
 
         root.add_child(OtherActionNode(next));
+        Ok(())
     }
 
     fn create_next_bet_nodes(
@@ -265,17 +433,19 @@ impl Game {
         bet_number: u8,
         street: u8,
         board: &Board,
-    ) {
+    ) -> Result<(), SolverError> {
         let current_bets = if root.pot_size * self.game_params.all_in_cut_off
             >= root.ip_stack.max(root.oop_stack)
         {
             let mut v = self
+                .game_params
                 .get_current_bets(street, root.player_node, bet_number)
                 .to_vec();
             v.push(self.game_params.all_in_cut_off);
             v
         } else {
-            self.get_current_bets(street, root.player_node, bet_number)
+            self.game_params
+                .get_current_bets(street, root.player_node, bet_number)
                 .to_vec()
         };
 
@@ -287,13 +457,13 @@ impl Game {
                 let final_bet_size = (root.ip_stack.min(sizing)).min(root.oop_stack + last_bet);
                 let mut next = ActionNode::new(
                     0,
-                    self.traversal.get_num_hands_for_player(0, board),
+                    self.traversal.get_num_hands_for_player(0, board)?,
                     root.pot_size + final_bet_size,
                     root.ip_stack - final_bet_size,
                     root.oop_stack,
                 );
 
-                self.add_successor_nodes(&mut next, bet_number + 1, board);
+                self.add_successor_nodes(&mut next, bet_number + 1, board)?;
                 root.add_child(OtherActionNode(next));
                 if final_bet_size < sizing {
                     break;
@@ -302,66 +472,328 @@ impl Game {
                 let final_bet_size = (root.oop_stack.min(sizing)).min(root.ip_stack + last_bet);
                 let mut next = ActionNode::new(
                     1,
-                    self.traversal.get_num_hands_for_player(1, board),
+                    self.traversal.get_num_hands_for_player(1, board)?,
                     root.pot_size + final_bet_size,
                     root.ip_stack,
                     root.oop_stack - final_bet_size,
                 );
 
-                self.add_successor_nodes(&mut next, bet_number + 1, board);
+                self.add_successor_nodes(&mut next, bet_number + 1, board)?;
                 root.add_child(OtherActionNode(next));
                 if final_bet_size < sizing {
                     break;
                 }
             }
         }
+        Ok(())
     }
 
-    fn get_current_bets(&self, street: u8, player: u8, bet_number: u8) -> &Vec<f32> {
-        let bet = usize::from(bet_number);
-        if street == 1 {
-            if player == 0 && bet < self.game_params.oop_flop_bets.len() {
-                return &self.game_params.oop_flop_bets[bet];
-            } else if bet < self.game_params.ip_flop_bets.len() {
-                return &self.game_params.ip_flop_bets[bet];
-            }
-        } else if street == 2 {
-            if player == 0 && bet < self.game_params.oop_turn_bets.len() {
-                return &self.game_params.oop_turn_bets[bet];
-            } else if bet < self.game_params.ip_turn_bets.len() {
-                return &self.game_params.ip_turn_bets[bet];
-            }
-        } else if street == 3 {
-            if player == 0 && bet < self.game_params.oop_river_bets.len() {
-                return &self.game_params.oop_river_bets[bet];
-            } else if bet < self.game_params.ip_river_bets.len() {
-                return &self.game_params.ip_river_bets[bet];
-            }
-        }
-        &self.game_params.default_bets[0]
+    pub fn results(&self, filter: &ResultFilter) -> Result<GameResult, SolverError> {
+        Ok(GameResult {
+            oop_range: self.traversal.oop_rm.get_starting_combinations(),
+            ip_range: self.traversal.ip_rm.get_starting_combinations(),
+            game_params: self.game_params.clone(),
+            starting_board: self.starting_board,
+            texture: classify_board(&self.starting_board)?,
+            node_results: self.node_results(filter)?,
+        })
     }
 
-    pub async fn output_results(
+    // just the tree
+    pub fn node_results(&self, filter: &ResultFilter) -> Result<NodeResult, SolverError> {
+        filter.validate(&self.starting_board)?;
+        self.root
+            .output_results(&self.traversal, &self.starting_board, filter)
+            .ok_or_else(|| {
+                SolverError::Runtime("game tree has no results, was it trained?".to_string())
+            })
+    }
+
+    // the tree a node at a time, returns the root's id
+    pub fn export_results(
         &self,
-        bucket_name: &str,
-        file_name: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Uploading file {} to bucket {}", file_name, bucket_name);
-        let result = GameResult {
+        filter: &ResultFilter,
+        sink: &mut dyn ResultSink,
+    ) -> Result<u32, SolverError> {
+        filter.validate(&self.starting_board)?;
+        self.root
+            .export_results(&self.traversal, &self.starting_board, filter, sink)?
+            .ok_or_else(|| {
+                SolverError::Runtime("game tree has no results, was it trained?".to_string())
+            })
+    }
+
+    // everything in GameResult except the tree
+    pub fn metadata(&self) -> Result<SolutionMetadata, SolverError> {
+        Ok(SolutionMetadata {
             oop_range: self.traversal.oop_rm.get_starting_combinations(),
             ip_range: self.traversal.ip_rm.get_starting_combinations(),
             game_params: self.game_params.clone(),
             starting_board: self.starting_board,
-            node_results: self.root.output_results().unwrap(),
-        };
+            texture: Some(classify_board(&self.starting_board)?),
+        })
+    }
 
-        let bytes = serde_json::to_string(&result).unwrap().as_bytes().to_vec();
+    // the file is encoded on a blocking thread and uploaded as it's written, a failed export fails the upload rather
+    // than leaving a partial solution in the bucket
+    pub async fn output_results(
+        self,
+        bucket_name: &str,
+        file_name: &str,
+        filter: ResultFilter,
+    ) -> Result<(), SolverError> {
+        info!("Uploading file {} to bucket {}", file_name, bucket_name);
+        let (sender, receiver) = mpsc::channel(UPLOAD_CHUNKS_IN_FLIGHT);
+        let export = tokio::task::spawn_blocking(move || {
+            let out = BufWriter::with_capacity(UPLOAD_CHUNK_BYTES, UploadWriter(sender.clone()));
+            let result = encode_solution(&self, &filter, out);
+            if let Err(e) = &result {
+                let _ = sender.blocking_send(Err(io::Error::other(e.to_string())));
+            }
+            result
+        });
 
         let client = Client::default();
-        client
+        let upload = client
             .object()
-            .create(bucket_name, bytes, file_name, "application/json")
-            .await?;
+            .create_streamed(
+                bucket_name,
+                UploadChunks(receiver),
+                None,
+                file_name,
+                "application/octet-stream",
+            )
+            .await;
+        // an export error is what made the upload fail, report that one first
+        export.await??;
+        upload?;
         Ok(())
     }
 }
+
+// hands what the encoder writes to the upload stream, the upload going away fails the write
+struct UploadWriter(mpsc::Sender<io::Result<Vec<u8>>>);
+
+impl Write for UploadWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the upload stopped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct UploadChunks(mpsc::Receiver<io::Result<Vec<u8>>>);
+
+impl Stream for UploadChunks {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+// the spot most tests solve, a 10 chip pot with 100 behind and only the given river sizings for both players
+#[cfg(test)]
+pub fn test_params(river_bets: Vec<Vec<f32>>) -> GameParams {
+    GameParams::new(
+        1,
+        10.0,
+        100.0,
+        1.0,
+        0.75,
+        vec![vec![]],
+        vec![vec![]],
+        river_bets.clone(),
+        vec![vec![]],
+        vec![vec![]],
+        river_bets,
+    )
+}
+
+// half pot river bets, trained until it's within a chip of the pot
+#[cfg(test)]
+pub fn trained_game(board: &str, oop_range: &str, ip_range: &str) -> Game {
+    trained_game_with(board, oop_range, ip_range, test_params(vec![vec![0.5]]), 1.0)
+}
+
+#[cfg(test)]
+pub fn trained_game_with(
+    board: &str,
+    oop_range: &str,
+    ip_range: &str,
+    params: GameParams,
+    target_exploitability: f32,
+) -> Game {
+    let board = crate::ranges::utility::parse_board(board).unwrap();
+    let traversal = build_traversal_from_ranges(board, oop_range, ip_range).unwrap();
+    let mut game = Game::new(traversal, params, board);
+    game.train(target_exploitability).unwrap();
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::node::ActionType;
+    use crate::ranges::utility::{card_to_number, parse_board};
+
+    #[test]
+    fn test_river_game_exports_values_for_both_players() {
+        let game = trained_game("as,ah,ac,kd,2s", "QQ,KK", "QQ,KK,JJ");
+        let root = game.results(&ResultFilter::default()).unwrap().node_results;
+        let oop = root.oop_values.unwrap();
+        let ip = root.ip_values.unwrap();
+
+        // oop acts first with check or bet, every combo is labelled and its frequencies sum to one
+        let hand_actions = root.hand_actions.unwrap();
+        assert_eq!(root.player, Some(0));
+        assert_eq!(hand_actions.len(), 3 + 6);
+        assert!(hand_actions.iter().any(|h| h.combination == "KhKs"));
+        for hand in hand_actions.iter() {
+            assert_eq!(hand.action_frequency.len(), 2);
+            assert!((hand.action_frequency.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            assert!(hand.frequency > 0.0);
+        }
+
+        assert!(oop.hands.iter().all(|h| h.action_ev.is_some()));
+        assert!(ip.hands.iter().all(|h| h.action_ev.is_none()));
+        for values in [&oop, &ip].iter() {
+            let range_equity = values.range_equity.unwrap();
+            assert!(range_equity > 0.0 && range_equity < 1.0);
+            assert!(values.hands.iter().all(|h| (0.0..=1.0).contains(&h.equity)));
+        }
+        // the two shares of the pot add up to the whole pot
+        let total = oop.range_ev.unwrap() + ip.range_ev.unwrap();
+        assert!((total - 10.0).abs() < 0.5, "range evs sum to {}", total);
+    }
+
+    #[test]
+    fn test_covering_stack_plays_as_the_effective_stack() {
+        let solve = |oop_stack: Option<f32>, ip_stack: Option<f32>| {
+            let mut params = test_params(vec![vec![0.5, 3.0]]);
+            params.starting_stack = 20.0;
+            params.oop_stack = oop_stack;
+            params.ip_stack = ip_stack;
+            let game = trained_game_with("as,ah,ac,kd,2s", "QQ,KK", "QQ,KK,JJ", params, 1.0);
+            game.results(&ResultFilter::default()).unwrap().node_results
+        };
+
+        // the ip player covers, the 3 pot bet is capped at the 20 oop can call and the rest is never in play
+        let covered = solve(Some(20.0), Some(1000.0));
+        let symmetric = solve(None, None);
+        assert_eq!(covered.bet_sizings, symmetric.bet_sizings);
+        assert!(covered
+            .bet_sizings
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|size| *size <= 20.0));
+        assert_eq!(
+            covered.oop_values.unwrap().range_ev,
+            symmetric.oop_values.unwrap().range_ev
+        );
+
+        let mut params = GameParams::default();
+        params.starting_stack = 50.0;
+        params.ip_stack = Some(30.0);
+        assert_eq!(params.stacks(), (50.0, 30.0));
+        assert_eq!(params.effective_stack(), 30.0);
+    }
+
+    #[test]
+    fn test_icm_bubble_tightens_calls() {
+        let call_frequency = |payoff_model: PayoffModel| {
+            let mut params = test_params(vec![vec![1.0]]);
+            params.starting_stack = 10.0;
+            params.ip_river_bets = vec![vec![]];
+            params.payoff_model = payoff_model;
+            let game = trained_game_with("as,ah,ac,kd,2s", "KK,JJ", "QQ", params, 0.1);
+
+            // the queens only ever face the all in after the oop bet
+            let root = game.results(&ResultFilter::default()).unwrap().node_results;
+            let facing_bet = root
+                .next_nodes
+                .iter()
+                .find(|node| {
+                    node.action_list
+                        .as_ref()
+                        .map_or(false, |actions| actions.contains(&ActionType::Fold))
+                })
+                .unwrap();
+            let hands = facing_bet.hand_actions.as_ref().unwrap();
+            hands.iter().map(|h| h.action_frequency[0]).sum::<f32>() / hands.len() as f32
+        };
+
+        // pot sized all in, the bluff catcher calls half the time at chip ev
+        let chips = call_frequency(PayoffModel::ChipEv);
+        assert!((chips - 0.5).abs() < 0.1, "chip ev call {}", chips);
+
+        // a short stack still in makes busting expensive for both players
+        let icm = call_frequency(PayoffModel::Icm {
+            payouts: vec![50.0, 30.0, 20.0],
+            other_stacks: vec![30.0, 2.0],
+        });
+        assert!(icm < chips - 0.1, "icm call {} vs chip ev {}", icm, chips);
+    }
+
+    #[test]
+    fn test_turn_game_exports_every_river() {
+        let board = parse_board("as,kh,7c,2d").unwrap();
+        let game = trained_game_with(
+            "as,kh,7c,2d",
+            "QQ,JJ",
+            "QQ,TT",
+            test_params(vec![vec![]]),
+            1.0,
+        );
+
+        // check check on the turn, then check check on each of the 48 rivers
+        let all = game.results(&ResultFilter::default()).unwrap().node_results;
+        assert_eq!(all.street_nodes(2).len(), 2);
+        assert_eq!(all.street_nodes(3).len(), 48 * 2);
+
+        let turn_only = ResultFilter {
+            max_street: Some(2),
+            runout: vec![],
+        };
+        let turn = game.results(&turn_only).unwrap().node_results;
+        assert_eq!(turn.street_nodes(2).len(), 2);
+        assert!(turn.street_nodes(3).is_empty());
+
+        let one_river = ResultFilter {
+            max_street: None,
+            runout: vec![board[3], parse_board("2c,3c,4c").unwrap()[0]],
+        };
+        let river = game.results(&one_river).unwrap().node_results;
+        assert_eq!(river.street_nodes(3).len(), 2);
+    }
+
+    #[test]
+    fn test_isomorphic_runout_cards_are_rejected() {
+        // spades and hearts are interchangeable on this flop until the turn tells them apart
+        let board = parse_board("ac,kc,7d").unwrap();
+        let runout = |cards: &str| ResultFilter {
+            max_street: None,
+            runout: cards
+                .split(',')
+                .map(|card| card_to_number(card.to_string()).unwrap())
+                .collect(),
+        };
+
+        assert!(runout("2s").validate(&board).is_ok());
+        match runout("2h").validate(&board) {
+            Err(SolverError::InvalidBoard(reason)) => {
+                assert!(reason.contains("solved as 2s"), "{}", reason)
+            }
+            other => panic!("expected the isomorph to be named, got {:?}", other),
+        }
+        // once the turn is a spade the two suits differ on the river
+        assert!(runout("2s,3h").validate(&board).is_ok());
+        assert!(runout("kc").validate(&board).is_err());
+    }
+}
@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+use super::payoff::{PayoffModel, TerminalPayoffs};
+use crate::error::SolverError;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GameParams {
     pub parallel_street: u8,
     pub starting_pot: f32,
     pub starting_stack: f32,
+    pub oop_stack: Option<f32>,
+    pub ip_stack: Option<f32>,
     pub all_in_cut_off: f32,
     pub default_bet: f32,
     pub default_bets: Vec<Vec<f32>>,
@@ -15,6 +20,8 @@ pub struct GameParams {
     pub oop_turn_bets: Vec<Vec<f32>>,
     pub ip_river_bets: Vec<Vec<f32>>,
     pub oop_river_bets: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub payoff_model: PayoffModel,
 }
 
 impl GameParams {
@@ -35,6 +42,8 @@ impl GameParams {
             parallel_street,
             starting_pot,
             starting_stack,
+            oop_stack: None,
+            ip_stack: None,
             all_in_cut_off,
             default_bet,
             default_bets: vec![vec![default_bet; 1]],
@@ -44,6 +53,113 @@ impl GameParams {
             oop_turn_bets,
             ip_river_bets,
             oop_river_bets,
+            payoff_model: PayoffModel::ChipEv,
+        }
+    }
+
+    // (oop, ip), a player without a stack of their own has starting_stack
+    pub fn stacks(&self) -> (f32, f32) {
+        (
+            self.oop_stack.unwrap_or(self.starting_stack),
+            self.ip_stack.unwrap_or(self.starting_stack),
+        )
+    }
+
+    // only the shorter stack can ever be won or lost, anything the covering player bets beyond it would come back as
+    // uncalled, so the tree is built with both players on this
+    pub fn effective_stack(&self) -> f32 {
+        let (oop, ip) = self.stacks();
+        oop.min(ip)
+    }
+
+    pub fn terminal_payoffs(&self, pot_size: f32) -> TerminalPayoffs {
+        self.payoff_model
+            .terminal_payoffs(self.stacks(), self.starting_pot, pot_size)
+    }
+
+    pub fn get_current_bets(&self, street: u8, player: u8, bet_number: u8) -> &Vec<f32> {
+        let bet = usize::from(bet_number);
+        if street == 1 {
+            if player == 0 && bet < self.oop_flop_bets.len() {
+                return &self.oop_flop_bets[bet];
+            } else if bet < self.ip_flop_bets.len() {
+                return &self.ip_flop_bets[bet];
+            }
+        } else if street == 2 {
+            if player == 0 && bet < self.oop_turn_bets.len() {
+                return &self.oop_turn_bets[bet];
+            } else if bet < self.ip_turn_bets.len() {
+                return &self.ip_turn_bets[bet];
+            }
+        } else if street == 3 {
+            if player == 0 && bet < self.oop_river_bets.len() {
+                return &self.oop_river_bets[bet];
+            } else if bet < self.ip_river_bets.len() {
+                return &self.ip_river_bets[bet];
+            }
+        }
+        &self.default_bets[0]
+    }
+
+    pub fn validate(&self) -> Result<(), SolverError> {
+        if !(self.starting_pot > 0.0 && self.starting_pot.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "starting pot must be positive, got {}",
+                self.starting_pot
+            )));
+        }
+        if !(self.starting_stack >= 0.0 && self.starting_stack.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "starting stack can't be negative, got {}",
+                self.starting_stack
+            )));
+        }
+        for (name, stack) in [("oop", self.oop_stack), ("ip", self.ip_stack)].iter() {
+            if let Some(stack) = stack.filter(|s| !(*s >= 0.0 && s.is_finite())) {
+                return Err(SolverError::InconsistentBetConfig(format!(
+                    "{} stack can't be negative, got {}",
+                    name, stack
+                )));
+            }
+        }
+        if !(self.all_in_cut_off > 0.0 && self.all_in_cut_off.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "all in cut off must be positive, got {}",
+                self.all_in_cut_off
+            )));
+        }
+        if self.parallel_street > 3 {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "parallel street must be 1 (flop), 2 (turn) or 3 (river), got {}",
+                self.parallel_street
+            )));
+        }
+        self.payoff_model.validate()?;
+        if self.default_bets.is_empty() {
+            return Err(SolverError::InconsistentBetConfig(
+                "default bets can't be empty".to_string(),
+            ));
+        }
+
+        let streets = [
+            ("default", &self.default_bets),
+            ("oop flop", &self.oop_flop_bets),
+            ("ip flop", &self.ip_flop_bets),
+            ("oop turn", &self.oop_turn_bets),
+            ("ip turn", &self.ip_turn_bets),
+            ("oop river", &self.oop_river_bets),
+            ("ip river", &self.ip_river_bets),
+        ];
+        for (name, bets) in streets.iter() {
+            for (bet_number, sizes) in bets.iter().enumerate() {
+                if let Some(size) = sizes.iter().find(|s| !(**s > 0.0 && s.is_finite())) {
+                    return Err(SolverError::InconsistentBetConfig(format!(
+                        "{} bet sizes for bet {} must be positive pot fractions, got {}",
+                        name, bet_number, size
+                    )));
+                }
+            }
         }
+        Ok(())
     }
 }
@@ -1,3 +1,15 @@
+pub mod aggregation;
+pub mod batch;
+pub mod cancellation;
+pub mod discounting;
 pub mod game;
 pub mod game_params;
+pub mod multiway;
+pub mod payoff;
+pub mod preflop;
+pub mod preflop_params;
+pub mod propagation;
+pub mod solution_file;
+pub mod tabular;
 pub mod traversal;
+pub mod tree_size;
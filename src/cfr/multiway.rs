@@ -0,0 +1,858 @@
+use rust_poker::hand_evaluator::{evaluate, Hand as EvalHand, CARDS};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::cancellation::CancellationToken;
+use super::discounting::{next_reach, normalize, update_regrets, update_strategy_sum};
+use crate::error::SolverError;
+use crate::nodes::node::{ActionType, CombinationActions, NodeResultType};
+use crate::ranges::combination::{Board, Hand};
+use crate::ranges::parser::parse_range;
+use crate::ranges::utility::{
+    board_has_river, board_has_turn, board_street, check_hand_overlap, combination_label,
+    parse_board,
+};
+use crate::ranges::validation::validate_range;
+
+// experimental, three player cfr doesn't converge to a nash equilibrium, it settles on strategies that no player can
+// improve much on their own, best_response_gains says how much. players are listed in the order they act on every
+// street. everyone starts with the same stack so callers always match and there are never side pots
+const PLAYERS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiwayParams {
+    pub starting_pot: f32,
+    pub starting_stack: f32,
+    pub bet_sizes: Vec<f32>,
+    pub raise_sizes: Vec<f32>,
+    pub max_raises: u8,
+}
+
+impl MultiwayParams {
+    pub fn validate(&self) -> Result<(), SolverError> {
+        if !(self.starting_pot > 0.0 && self.starting_pot.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "starting pot must be positive, got {}",
+                self.starting_pot
+            )));
+        }
+        if !(self.starting_stack >= 0.0 && self.starting_stack.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "starting stack can't be negative, got {}",
+                self.starting_stack
+            )));
+        }
+        for (name, sizes) in [("bet", &self.bet_sizes), ("raise", &self.raise_sizes)].iter() {
+            if let Some(size) = sizes.iter().find(|s| !(**s > 0.0 && s.is_finite())) {
+                return Err(SolverError::InconsistentBetConfig(format!(
+                    "{} sizes must be positive pot fractions, got {}",
+                    name, size
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// what the multiway command reads, ranges in the order the players act
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiwayConfig {
+    pub board: String,
+    pub ranges: Vec<String>,
+    pub params: MultiwayParams,
+    pub iterations: u32,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiwayNodeResult {
+    pub node_type: NodeResultType,
+    pub street: u8,
+    pub pot: f32,
+    pub player: Option<u8>,
+    pub action_list: Option<Vec<ActionType>>,
+    pub bet_sizings: Option<Vec<f32>>,
+    pub hand_actions: Option<Vec<CombinationActions>>,
+    pub next_cards: Option<Vec<u8>>,
+    pub next_nodes: Vec<MultiwayNodeResult>,
+}
+
+// evs and gains are in chips won or lost against what each player put in
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiwayResult {
+    pub starting_board: Board,
+    pub params: MultiwayParams,
+    pub player_evs: Vec<f32>,
+    pub best_response_gains: Vec<f32>,
+    pub node_results: MultiwayNodeResult,
+}
+
+struct PlayerRange {
+    hands: Vec<Hand>,
+    masks: Vec<u64>,
+    weights: Vec<f32>,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    board: Board,
+    to_act: usize,
+    street_bets: [f32; PLAYERS],
+    totals: [f32; PLAYERS],
+    folded: [bool; PLAYERS],
+    acted: [bool; PLAYERS],
+    bets: u8,
+}
+
+struct MultiwayActionNode {
+    player: usize,
+    street: u8,
+    pot: f32,
+    actions: Vec<(ActionType, f32)>,
+    children: Vec<MultiwayNode>,
+    regrets: Vec<f32>,
+    strategy_sum: Vec<f32>,
+}
+
+// showdown terminals keep every player's hand ranks on their board, fold outs don't need them
+enum MultiwayNode {
+    Action(MultiwayActionNode),
+    Chance {
+        street: u8,
+        pot: f32,
+        cards: Vec<u8>,
+        children: Vec<MultiwayNode>,
+    },
+    Terminal {
+        board: Board,
+        totals: [f32; PLAYERS],
+        folded: [bool; PLAYERS],
+        pot: f32,
+        ranks: Option<[Vec<u16>; PLAYERS]>,
+    },
+}
+
+struct Context<'a> {
+    ranges: &'a [PlayerRange],
+    traverser: usize,
+    iteration: u32,
+    best_response: bool,
+}
+
+pub struct MultiwayGame {
+    starting_board: Board,
+    params: MultiwayParams,
+    ranges: Vec<PlayerRange>,
+    root: MultiwayNode,
+    iteration: u32,
+}
+
+impl MultiwayGame {
+    pub fn new(board: &str, ranges: &[&str], params: MultiwayParams) -> Result<Self, SolverError> {
+        params.validate()?;
+        let starting_board = parse_board(board)?;
+        // flops have no suit isomorphism here, every showdown is a three way loop over the ranges and the tree never
+        // goes through a size estimate, so they're out of reach
+        if !board_has_turn(&starting_board) {
+            return Err(SolverError::InvalidBoard(
+                "multiway games start on the turn or river".to_string(),
+            ));
+        }
+        if ranges.len() != PLAYERS {
+            return Err(SolverError::InvalidRange(format!(
+                "multiway games need {} ranges, got {}",
+                PLAYERS,
+                ranges.len()
+            )));
+        }
+
+        let ranges = ranges
+            .iter()
+            .enumerate()
+            .map(|(player, range)| player_range(player, range, &starting_board))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut game = Self {
+            starting_board,
+            params,
+            ranges,
+            root: MultiwayNode::Chance {
+                street: 0,
+                pot: 0.0,
+                cards: vec![],
+                children: vec![],
+            },
+            iteration: 0,
+        };
+        let start = State {
+            board: starting_board,
+            to_act: PLAYERS - 1,
+            street_bets: [0.0; PLAYERS],
+            totals: [0.0; PLAYERS],
+            folded: [false; PLAYERS],
+            acted: [false; PLAYERS],
+            bets: 0,
+        };
+        game.root = game.advance(start);
+        Ok(game)
+    }
+
+    // there's no exploitability target to stop at, so training runs for a fixed number of iterations
+    pub fn train(
+        &mut self,
+        iterations: u32,
+        cancel: &CancellationToken,
+    ) -> Result<(), SolverError> {
+        for _ in 0..iterations {
+            cancel.check()?;
+            for traverser in 0..PLAYERS {
+                let context = Context {
+                    ranges: &self.ranges,
+                    traverser,
+                    iteration: self.iteration,
+                    best_response: false,
+                };
+                let reach = self.starting_reach();
+                self.root.cfr(&context, &reach);
+            }
+            self.iteration += 1;
+            if self.iteration % 25 == 0 {
+                info!(
+                    "Multiway iteration {} best response gains {:?}",
+                    self.iteration,
+                    self.best_response_gains()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn player_evs(&self) -> Vec<f32> {
+        (0..PLAYERS)
+            .map(|player| self.value(player, false))
+            .collect()
+    }
+
+    // how much each player could win on top of their ev by switching to a best response while the others keep their
+    // average strategies
+    pub fn best_response_gains(&self) -> Vec<f32> {
+        (0..PLAYERS)
+            .map(|player| self.value(player, true) - self.value(player, false))
+            .collect()
+    }
+
+    pub fn results(&self) -> MultiwayResult {
+        MultiwayResult {
+            starting_board: self.starting_board,
+            params: self.params.clone(),
+            player_evs: self.player_evs(),
+            best_response_gains: self.best_response_gains(),
+            node_results: self
+                .root
+                .output_results(&self.ranges, &self.starting_reach()),
+        }
+    }
+
+    fn value(&self, player: usize, best_response: bool) -> f32 {
+        let context = Context {
+            ranges: &self.ranges,
+            traverser: player,
+            iteration: self.iteration,
+            best_response,
+        };
+        let reach = self.starting_reach();
+        let values = self.root.evaluate(&context, &reach);
+
+        // every weighted deal of three non overlapping hands, the same for every player
+        let matchups = showdown_values(&context, &reach, &self.starting_board, |_, _, _| 1.0);
+        let total: f32 = matchups
+            .iter()
+            .zip(reach[player].iter())
+            .map(|(count, reach)| count * reach)
+            .sum();
+
+        values
+            .iter()
+            .zip(reach[player].iter())
+            .map(|(value, reach)| value * reach)
+            .sum::<f32>()
+            / total
+    }
+
+    fn starting_reach(&self) -> Vec<Vec<f32>> {
+        self.ranges
+            .iter()
+            .map(|range| range.weights.clone())
+            .collect()
+    }
+
+    fn advance(&self, state: State) -> MultiwayNode {
+        let live = state.folded.iter().filter(|folded| !**folded).count();
+        if live == 1 {
+            return self.terminal(&state);
+        }
+
+        let stack = |player: usize| self.params.starting_stack - state.totals[player];
+        let can_act = |player: usize| !state.folded[player] && stack(player) > 0.0;
+        let acting = (0..PLAYERS).filter(|player| can_act(*player)).count();
+        let max_bet = state.street_bets.iter().cloned().fold(0.0, f32::max);
+
+        // a lone player who can still act only has to answer a bet, there's nobody left to bet into
+        let next = (1..=PLAYERS)
+            .map(|offset| (state.to_act + offset) % PLAYERS)
+            .find(|player| {
+                can_act(*player)
+                    && (state.street_bets[*player] < max_bet
+                        || (!state.acted[*player] && acting >= 2))
+            });
+        if let Some(player) = next {
+            return self.action_node(State {
+                to_act: player,
+                ..state
+            });
+        }
+
+        if board_has_river(&state.board) {
+            return self.terminal(&state);
+        }
+
+        let street = board_street(&state.board);
+        let pot = self.pot(&state);
+        let cards: Vec<u8> = (0u8..52)
+            .filter(|card| !state.board.contains(card))
+            .collect();
+        let children = cards
+            .iter()
+            .map(|card| {
+                let mut board = state.board;
+                board[usize::from(street) + 2] = *card;
+                self.advance(State {
+                    board,
+                    to_act: PLAYERS - 1,
+                    street_bets: [0.0; PLAYERS],
+                    acted: [false; PLAYERS],
+                    bets: 0,
+                    ..state
+                })
+            })
+            .collect();
+        MultiwayNode::Chance {
+            street,
+            pot,
+            cards,
+            children,
+        }
+    }
+
+    fn action_node(&self, state: State) -> MultiwayNode {
+        let player = state.to_act;
+        let pot = self.pot(&state);
+        let stack = self.params.starting_stack - state.totals[player];
+        let max_bet = state.street_bets.iter().cloned().fold(0.0, f32::max);
+        let facing = max_bet - state.street_bets[player];
+        let others_can_call = (0..PLAYERS).any(|other| {
+            other != player
+                && !state.folded[other]
+                && self.params.starting_stack - state.totals[other] > 0.0
+        });
+
+        let mut actions = vec![];
+        if facing > 0.0 {
+            actions.push((ActionType::Fold, 0.0));
+            actions.push((ActionType::Call, facing.min(stack)));
+        } else {
+            actions.push((ActionType::Check, 0.0));
+        }
+
+        let sizes = if state.bets == 0 {
+            self.params.bet_sizes.clone()
+        } else if state.bets <= self.params.max_raises {
+            self.params.raise_sizes.clone()
+        } else {
+            vec![]
+        };
+        if others_can_call && stack > facing {
+            for size in sizes {
+                // the same sizing as heads up, a fraction of the pot after calling on top of the call
+                let amount = (size * (pot + facing) + facing).min(stack);
+                if !actions.contains(&(ActionType::Bet, amount)) {
+                    actions.push((ActionType::Bet, amount));
+                }
+                if amount >= stack {
+                    break;
+                }
+            }
+        }
+
+        let children = actions
+            .iter()
+            .map(|(action, amount)| {
+                let mut next = state;
+                next.acted[player] = true;
+                match action {
+                    ActionType::Fold => next.folded[player] = true,
+                    ActionType::Check => {}
+                    ActionType::Call | ActionType::Bet => {
+                        next.street_bets[player] += amount;
+                        next.totals[player] += amount;
+                    }
+                }
+                if *action == ActionType::Bet {
+                    next.bets += 1;
+                    next.acted = [false; PLAYERS];
+                    next.acted[player] = true;
+                }
+                self.advance(next)
+            })
+            .collect();
+
+        let size = actions.len() * self.ranges[player].hands.len();
+        MultiwayNode::Action(MultiwayActionNode {
+            player,
+            street: board_street(&state.board),
+            pot,
+            actions,
+            children,
+            regrets: vec![0.0; size],
+            strategy_sum: vec![0.0; size],
+        })
+    }
+
+    fn terminal(&self, state: &State) -> MultiwayNode {
+        let live = state.folded.iter().filter(|folded| !**folded).count();
+        let ranks = if live > 1 {
+            let board = state.board.iter().fold(EvalHand::default(), |hand, card| {
+                hand + CARDS[usize::from(*card)]
+            });
+            let rank = |range: &PlayerRange| -> Vec<u16> {
+                range
+                    .hands
+                    .iter()
+                    .map(|hand| {
+                        if check_hand_overlap(*hand, &state.board) {
+                            0
+                        } else {
+                            evaluate(
+                                &(board
+                                    + CARDS[usize::from(hand[0])]
+                                    + CARDS[usize::from(hand[1])]),
+                            )
+                        }
+                    })
+                    .collect()
+            };
+            Some([
+                rank(&self.ranges[0]),
+                rank(&self.ranges[1]),
+                rank(&self.ranges[2]),
+            ])
+        } else {
+            None
+        };
+
+        MultiwayNode::Terminal {
+            board: state.board,
+            totals: state.totals,
+            folded: state.folded,
+            pot: self.pot(state),
+            ranks,
+        }
+    }
+
+    fn pot(&self, state: &State) -> f32 {
+        self.params.starting_pot + state.totals.iter().sum::<f32>()
+    }
+}
+
+fn player_range(player: usize, range: &str, board: &Board) -> Result<PlayerRange, SolverError> {
+    validate_range(&format!("player {}", player + 1), range, board)?;
+    let parsed = parse_range(range)?;
+
+    let mut result = PlayerRange {
+        hands: vec![],
+        masks: vec![],
+        weights: vec![],
+    };
+    for (cards, weight) in parsed.hands() {
+        if !check_hand_overlap(cards, board) {
+            result.hands.push(cards);
+            result.masks.push(hand_mask(&cards));
+            result.weights.push(weight);
+        }
+    }
+    Ok(result)
+}
+
+fn hand_mask(cards: &[u8]) -> u64 {
+    cards
+        .iter()
+        .filter(|card| **card != 52)
+        .fold(0, |mask, card| mask | (1 << card))
+}
+
+// for every traverser hand, the sum over opponent hands that don't share a card with it, each other or the board of
+// both opponents' reach times the payoff
+fn showdown_values(
+    context: &Context,
+    reach: &[Vec<f32>],
+    board: &Board,
+    payoff: impl Fn(usize, usize, usize) -> f32,
+) -> Vec<f32> {
+    let traverser = context.traverser;
+    let first = (traverser + 1) % PLAYERS;
+    let second = (traverser + 2) % PLAYERS;
+    let ranges = context.ranges;
+    let board_mask = hand_mask(board);
+
+    ranges[traverser]
+        .masks
+        .iter()
+        .enumerate()
+        .map(|(hand, mask)| {
+            if mask & board_mask != 0 {
+                return 0.0;
+            }
+            let dead = mask | board_mask;
+            let mut value = 0.0;
+            for (a, a_mask) in ranges[first].masks.iter().enumerate() {
+                if a_mask & dead != 0 || reach[first][a] == 0.0 {
+                    continue;
+                }
+                for (b, b_mask) in ranges[second].masks.iter().enumerate() {
+                    if b_mask & (dead | a_mask) != 0 || reach[second][b] == 0.0 {
+                        continue;
+                    }
+                    value += reach[first][a] * reach[second][b] * payoff(hand, a, b);
+                }
+            }
+            value
+        })
+        .collect()
+}
+
+impl MultiwayNode {
+    fn cfr(&mut self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        match self {
+            MultiwayNode::Action(node) => node.cfr(context, reach),
+            MultiwayNode::Chance { children, .. } => {
+                let share = card_share(children.len());
+                let mut values = vec![0.0; context.ranges[context.traverser].hands.len()];
+                for child in children.iter_mut() {
+                    add_scaled(&mut values, &child.cfr(context, reach), share);
+                }
+                values
+            }
+            MultiwayNode::Terminal { .. } => self.terminal_values(context, reach),
+        }
+    }
+
+    // values under the average strategies, or with the traverser best responding to them
+    fn evaluate(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        match self {
+            MultiwayNode::Action(node) => node.evaluate(context, reach),
+            MultiwayNode::Chance { children, .. } => {
+                let share = card_share(children.len());
+                let mut values = vec![0.0; context.ranges[context.traverser].hands.len()];
+                for child in children.iter() {
+                    add_scaled(&mut values, &child.evaluate(context, reach), share);
+                }
+                values
+            }
+            MultiwayNode::Terminal { .. } => self.terminal_values(context, reach),
+        }
+    }
+
+    // the pot goes to the last player standing or is split between the best hands at showdown
+    fn terminal_values(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let (board, totals, folded, pot, ranks) = match self {
+            MultiwayNode::Terminal {
+                board,
+                totals,
+                folded,
+                pot,
+                ranks,
+            } => (board, totals, folded, *pot, ranks),
+            _ => return vec![],
+        };
+        let traverser = context.traverser;
+        let invested = totals[traverser];
+
+        if folded[traverser] {
+            return showdown_values(context, reach, board, |_, _, _| -invested);
+        }
+        let ranks = match ranks {
+            Some(ranks) => ranks,
+            None => return showdown_values(context, reach, board, |_, _, _| pot - invested),
+        };
+
+        let first = (traverser + 1) % PLAYERS;
+        let second = (traverser + 2) % PLAYERS;
+        showdown_values(context, reach, board, |hand, a, b| {
+            let own = ranks[traverser][hand];
+            let others = [
+                (!folded[first]).then(|| ranks[first][a]),
+                (!folded[second]).then(|| ranks[second][b]),
+            ];
+            let best = others.iter().flatten().cloned().fold(own, u16::max);
+            if own < best {
+                return -invested;
+            }
+            let winners = 1 + others.iter().flatten().filter(|rank| **rank == own).count();
+            pot / winners as f32 - invested
+        })
+    }
+
+    fn output_results(&self, ranges: &[PlayerRange], reach: &[Vec<f32>]) -> MultiwayNodeResult {
+        match self {
+            MultiwayNode::Action(node) => node.output_results(ranges, reach),
+            MultiwayNode::Chance {
+                street,
+                pot,
+                cards,
+                children,
+            } => MultiwayNodeResult {
+                node_type: NodeResultType::Chance,
+                street: *street,
+                pot: *pot,
+                player: None,
+                action_list: None,
+                bet_sizings: None,
+                hand_actions: None,
+                next_cards: Some(cards.clone()),
+                next_nodes: children
+                    .iter()
+                    .map(|child| child.output_results(ranges, reach))
+                    .collect(),
+            },
+            MultiwayNode::Terminal { board, pot, .. } => MultiwayNodeResult {
+                node_type: NodeResultType::Terminal,
+                street: board_street(board),
+                pot: *pot,
+                player: None,
+                action_list: None,
+                bet_sizings: None,
+                hand_actions: None,
+                next_cards: None,
+                next_nodes: vec![],
+            },
+        }
+    }
+}
+
+impl MultiwayActionNode {
+    fn cfr(&mut self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let strategy = normalize(&self.regrets, self.actions.len(), |regret| regret.max(0.0));
+        let hands = context.ranges[self.player].hands.len();
+
+        if self.player != context.traverser {
+            // the acting player's reach is their own in both passes they don't traverse, count it once
+            if context.traverser == (self.player + 1) % PLAYERS {
+                update_strategy_sum(
+                    &mut self.strategy_sum,
+                    &strategy,
+                    &reach[self.player],
+                    context.iteration,
+                );
+            }
+            let mut values = vec![0.0; context.ranges[context.traverser].hands.len()];
+            for (action, child) in self.children.iter_mut().enumerate() {
+                let next = next_reach(reach, self.player, &strategy, action);
+                add_scaled(&mut values, &child.cfr(context, &next), 1.0);
+            }
+            return values;
+        }
+
+        let action_values: Vec<Vec<f32>> = self
+            .children
+            .iter_mut()
+            .map(|child| child.cfr(context, reach))
+            .collect();
+        let mut values = vec![0.0; hands];
+        for (action, action_value) in action_values.iter().enumerate() {
+            for hand in 0..hands {
+                values[hand] += strategy[hand + action * hands] * action_value[hand];
+            }
+        }
+
+        update_regrets(
+            &mut self.regrets,
+            &action_values,
+            &values,
+            context.iteration,
+        );
+        values
+    }
+
+    fn evaluate(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let strategy = self.average_strategy();
+        let hands = context.ranges[self.player].hands.len();
+
+        if self.player != context.traverser {
+            let mut values = vec![0.0; context.ranges[context.traverser].hands.len()];
+            for (action, child) in self.children.iter().enumerate() {
+                let next = next_reach(reach, self.player, &strategy, action);
+                add_scaled(&mut values, &child.evaluate(context, &next), 1.0);
+            }
+            return values;
+        }
+
+        let mut values = vec![if context.best_response { f32::MIN } else { 0.0 }; hands];
+        for (action, child) in self.children.iter().enumerate() {
+            let child_values = child.evaluate(context, reach);
+            for hand in 0..hands {
+                if context.best_response {
+                    values[hand] = values[hand].max(child_values[hand]);
+                } else {
+                    values[hand] += strategy[hand + action * hands] * child_values[hand];
+                }
+            }
+        }
+        values
+    }
+
+    fn average_strategy(&self) -> Vec<f32> {
+        normalize(&self.strategy_sum, self.actions.len(), |sum| sum)
+    }
+
+    fn output_results(&self, ranges: &[PlayerRange], reach: &[Vec<f32>]) -> MultiwayNodeResult {
+        let strategy = self.average_strategy();
+        let range = &ranges[self.player];
+        let hands = range.hands.len();
+
+        let hand_actions = range
+            .hands
+            .iter()
+            .enumerate()
+            .map(|(i, hand)| CombinationActions {
+                combination: combination_label(hand),
+                frequency: reach[self.player][i],
+                action_frequency: (0..self.actions.len())
+                    .map(|action| strategy[i + action * hands])
+                    .collect(),
+            })
+            .collect();
+
+        MultiwayNodeResult {
+            node_type: NodeResultType::Action,
+            street: self.street,
+            pot: self.pot,
+            player: Some(self.player as u8),
+            action_list: Some(self.actions.iter().map(|(action, _)| *action).collect()),
+            bet_sizings: Some(self.actions.iter().map(|(_, amount)| *amount).collect()),
+            hand_actions: Some(hand_actions),
+            next_cards: None,
+            next_nodes: self
+                .children
+                .iter()
+                .enumerate()
+                .map(|(action, child)| {
+                    child.output_results(ranges, &next_reach(reach, self.player, &strategy, action))
+                })
+                .collect(),
+        }
+    }
+}
+
+// a deal of three hands only reaches the cards none of its six hole cards block, like the heads up chance node
+// dividing by 45 and 44 rather than every card left
+fn card_share(cards: usize) -> f32 {
+    1.0 / (cards - 2 * PLAYERS) as f32
+}
+
+fn add_scaled(values: &mut [f32], child: &[f32], scale: f32) {
+    values
+        .iter_mut()
+        .zip(child.iter())
+        .for_each(|(value, child)| *value += child * scale);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(bet_sizes: Vec<f32>) -> MultiwayParams {
+        MultiwayParams {
+            starting_pot: 30.0,
+            starting_stack: 100.0,
+            bet_sizes,
+            raise_sizes: vec![],
+            max_raises: 0,
+        }
+    }
+
+    #[test]
+    fn test_check_down_splits_by_showdown() {
+        // the board plays for everyone but quad kings, who take the whole pot
+        let game =
+            MultiwayGame::new("as,ah,ac,ad,2s", &["KK", "QQ", "JJ"], params(vec![])).unwrap();
+        let evs = game.player_evs();
+        assert!((evs[0] - 30.0).abs() < 1e-4, "{:?}", evs);
+        assert!(evs[1].abs() < 1e-4 && evs[2].abs() < 1e-4);
+
+        let tied =
+            MultiwayGame::new("as,ks,qs,js,ts", &["22", "33", "44"], params(vec![])).unwrap();
+        assert!(tied.player_evs().iter().all(|ev| (ev - 10.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_nuts_never_lose() {
+        let mut game =
+            MultiwayGame::new("as,ah,ac,7d,2s", &["AKs", "KK", "QQ"], params(vec![1.0])).unwrap();
+        game.train(200, &CancellationToken::new()).unwrap();
+
+        let root = game.results().node_results;
+        assert_eq!(root.player, Some(0));
+        assert_eq!(
+            root.action_list,
+            Some(vec![ActionType::Check, ActionType::Bet])
+        );
+        assert_eq!(root.bet_sizings, Some(vec![0.0, 30.0]));
+
+        // facing the bet the two behind can fold or call, once both fold the hand is over
+        let facing = &root.next_nodes[1];
+        assert_eq!(facing.player, Some(1));
+        assert_eq!(
+            facing.action_list,
+            Some(vec![ActionType::Fold, ActionType::Call])
+        );
+        let last = &facing.next_nodes[0];
+        assert_eq!(last.player, Some(2));
+        assert!(last.next_nodes[0].next_nodes.is_empty());
+
+        // whatever was bet only moves between players, the quads take the starting pot and anything the others put in
+        let evs = game.player_evs();
+        assert!((evs.iter().sum::<f32>() - 30.0).abs() < 1e-3);
+        assert!(
+            evs[0] >= 30.0 - 1e-3 && evs[1] <= 1e-3 && evs[2] <= 1e-3,
+            "{:?}",
+            evs
+        );
+        assert!(game.best_response_gains().iter().all(|gain| *gain < 1.0));
+    }
+
+    #[test]
+    fn test_turn_start_deals_every_river() {
+        let game = MultiwayGame::new("as,kh,7c,2d", &["QQ", "JJ", "TT"], params(vec![])).unwrap();
+        let root = game.results().node_results;
+        // check, check, check, then the river
+        let chance = &root.next_nodes[0].next_nodes[0].next_nodes[0];
+        assert_eq!(chance.next_cards.as_ref().unwrap().len(), 48);
+        assert!(matches!(chance.node_type, NodeResultType::Chance));
+    }
+
+    #[test]
+    fn test_rivers_are_shared_by_unblocked_cards() {
+        // quads on the board, kings kick unless the river is one of the two kings the deal leaves, then all three split
+        let game = MultiwayGame::new("as,ah,ac,ad", &["KhKs", "QQ", "JJ"], params(vec![])).unwrap();
+        let evs = game.player_evs();
+        let expected = (30.0 * 40.0 + 10.0 * 2.0) / 42.0;
+        assert!((evs[0] - expected).abs() < 1e-3, "{:?}", evs);
+        assert!((evs.iter().sum::<f32>() - 30.0).abs() < 1e-3);
+
+        assert!(MultiwayGame::new("as,kh,7c", &["QQ", "JJ", "TT"], params(vec![])).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::SolverError;
+
+// what a terminal is worth to each player, indexed by player, for the traverser winning, tying or losing. utilities
+// are measured against both players ending the hand with their stack plus half the starting pot, so under chip ev
+// they're the familiar +pot / 2, 0 and -pot / 2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerminalPayoffs {
+    pub win: [f32; 2],
+    pub tie: [f32; 2],
+    pub lose: [f32; 2],
+}
+
+impl TerminalPayoffs {
+    pub fn chip_ev(pot_size: f32) -> Self {
+        let half = pot_size / 2.0;
+        Self {
+            win: [half, half],
+            tie: [0.0, 0.0],
+            lose: [-half, -half],
+        }
+    }
+
+    // chip linear payoffs can use the single utility showdown, anything else needs wins and losses apart
+    pub fn is_symmetric(&self, player: u8) -> bool {
+        let player = usize::from(player);
+        self.tie[player] == 0.0 && self.win[player] == -self.lose[player]
+    }
+}
+
+// how final stacks turn into utility. icm pays out the prize structure given both stacks and the stacks of everyone
+// else still in the tournament, bounty adds the knockout value, in chips, of busting the other player. icm values
+// are scaled back to chips at the tournament's chips per unit of prize money, so exploitability and evs keep reading
+// as chips. a progressive knockout pays out half the bounty straight away, the half added to the winner's own bounty
+// isn't counted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PayoffModel {
+    ChipEv,
+    Icm {
+        payouts: Vec<f32>,
+        #[serde(rename = "otherStacks")]
+        other_stacks: Vec<f32>,
+    },
+    Bounty {
+        #[serde(rename = "oopBounty")]
+        oop_bounty: f32,
+        #[serde(rename = "ipBounty")]
+        ip_bounty: f32,
+        #[serde(rename = "chipsPerBounty")]
+        chips_per_bounty: f32,
+        progressive: bool,
+    },
+}
+
+impl Default for PayoffModel {
+    fn default() -> Self {
+        PayoffModel::ChipEv
+    }
+}
+
+// past this many players the icm recursion over finishing orders gets too slow to run per terminal
+const MAX_ICM_PLAYERS: usize = 12;
+
+// final stacks come out of pot arithmetic, anything this small a share of the chips in play is a busted stack
+const BUSTED_SHARE: f32 = 1e-5;
+
+impl PayoffModel {
+    // stacks are (oop, ip) at the start of the hand, pot_size is the pot at the terminal once any uncalled bet is back
+    pub fn terminal_payoffs(
+        &self,
+        stacks: (f32, f32),
+        starting_pot: f32,
+        pot_size: f32,
+    ) -> TerminalPayoffs {
+        if *self == PayoffModel::ChipEv {
+            return TerminalPayoffs::chip_ev(pot_size);
+        }
+
+        let stacks = [stacks.0, stacks.1];
+        let put_in = (pot_size - starting_pot) / 2.0;
+        let baseline = [
+            stacks[0] + starting_pot / 2.0,
+            stacks[1] + starting_pot / 2.0,
+        ];
+        let outcome = |winner: Option<usize>| -> [f32; 2] {
+            let mut finals = [stacks[0] - put_in, stacks[1] - put_in];
+            match winner {
+                Some(winner) => finals[winner] += pot_size,
+                None => finals.iter_mut().for_each(|stack| *stack += pot_size / 2.0),
+            }
+            finals
+        };
+
+        let base = self.values(baseline);
+        let utility = |finals: [f32; 2], player: usize| self.values(finals)[player] - base[player];
+
+        let mut payoffs = TerminalPayoffs::chip_ev(pot_size);
+        for player in 0..2 {
+            payoffs.win[player] = utility(outcome(Some(player)), player);
+            payoffs.tie[player] = utility(outcome(None), player);
+            payoffs.lose[player] = utility(outcome(Some(player ^ 1)), player);
+        }
+        payoffs
+    }
+
+    fn values(&self, finals: [f32; 2]) -> [f32; 2] {
+        let in_play = finals[0] + finals[1];
+        let finals = finals.map(|stack| {
+            if stack <= BUSTED_SHARE * in_play {
+                0.0
+            } else {
+                stack
+            }
+        });
+        match self {
+            PayoffModel::ChipEv => finals,
+            PayoffModel::Icm {
+                payouts,
+                other_stacks,
+            } => {
+                let mut stacks = finals.to_vec();
+                stacks.extend_from_slice(other_stacks);
+                let equity = icm_equity(&stacks, payouts);
+
+                let chips: f32 = stacks.iter().sum();
+                let prizes: f32 = payouts.iter().sum();
+                let scale = if prizes > 0.0 { chips / prizes } else { 0.0 };
+                [equity[0] * scale, equity[1] * scale]
+            }
+            PayoffModel::Bounty {
+                oop_bounty,
+                ip_bounty,
+                chips_per_bounty,
+                progressive,
+            } => {
+                let share = if *progressive { 0.5 } else { 1.0 };
+                let bounties = [*oop_bounty, *ip_bounty];
+                let mut values = finals;
+                for player in 0..2 {
+                    if finals[player] > 0.0 && finals[player ^ 1] <= 0.0 {
+                        values[player] += bounties[player ^ 1] * share * chips_per_bounty;
+                    }
+                }
+                values
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SolverError> {
+        let non_negative = |name: &str, values: &[f32]| -> Result<(), SolverError> {
+            match values.iter().find(|v| !(**v >= 0.0 && v.is_finite())) {
+                Some(value) => Err(SolverError::InconsistentBetConfig(format!(
+                    "{} can't be negative, got {}",
+                    name, value
+                ))),
+                None => Ok(()),
+            }
+        };
+
+        match self {
+            PayoffModel::ChipEv => Ok(()),
+            PayoffModel::Icm {
+                payouts,
+                other_stacks,
+            } => {
+                if payouts.is_empty() {
+                    return Err(SolverError::InconsistentBetConfig(
+                        "icm needs at least one payout".to_string(),
+                    ));
+                }
+                if other_stacks.len() + 2 > MAX_ICM_PLAYERS {
+                    return Err(SolverError::InconsistentBetConfig(format!(
+                        "icm supports at most {} players, got {}",
+                        MAX_ICM_PLAYERS,
+                        other_stacks.len() + 2
+                    )));
+                }
+                non_negative("payouts", payouts)?;
+                non_negative("other stacks", other_stacks)
+            }
+            PayoffModel::Bounty {
+                oop_bounty,
+                ip_bounty,
+                chips_per_bounty,
+                ..
+            } => non_negative("bounties", &[*oop_bounty, *ip_bounty, *chips_per_bounty]),
+        }
+    }
+}
+
+// malmuth harville, each remaining player takes the next place with probability in proportion to their stack.
+// players with no chips left share whatever places are left between them
+pub fn icm_equity(stacks: &[f32], payouts: &[f32]) -> Vec<f32> {
+    let players = stacks.len();
+    let mut memo = vec![None; 1 << players];
+    finishing_equity((1 << players) - 1, stacks, payouts, &mut memo)
+}
+
+fn finishing_equity(
+    remaining: usize,
+    stacks: &[f32],
+    payouts: &[f32],
+    memo: &mut Vec<Option<Vec<f32>>>,
+) -> Vec<f32> {
+    if let Some(equity) = &memo[remaining] {
+        return equity.clone();
+    }
+
+    let players = stacks.len();
+    let mut equity = vec![0.0; players];
+    let place = players - remaining.count_ones() as usize;
+    if remaining == 0 || place >= payouts.len() {
+        memo[remaining] = Some(equity.clone());
+        return equity;
+    }
+
+    let in_play: Vec<usize> = (0..players)
+        .filter(|player| remaining & (1 << player) != 0)
+        .collect();
+    let chips: f32 = in_play.iter().map(|player| stacks[*player]).sum();
+    if chips <= 0.0 {
+        let left: f32 = payouts[place..payouts.len().min(place + in_play.len())]
+            .iter()
+            .sum();
+        for player in in_play.iter() {
+            equity[*player] = left / in_play.len() as f32;
+        }
+        memo[remaining] = Some(equity.clone());
+        return equity;
+    }
+
+    for next in in_play.iter() {
+        let probability = stacks[*next] / chips;
+        if probability <= 0.0 {
+            continue;
+        }
+        equity[*next] += probability * payouts[place];
+        let rest = finishing_equity(remaining & !(1 << next), stacks, payouts, memo);
+        for (player, value) in rest.iter().enumerate() {
+            equity[player] += probability * value;
+        }
+    }
+
+    memo[remaining] = Some(equity.clone());
+    equity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icm_equity() {
+        // equal stacks split the prizes evenly, a winner take all is chip linear
+        let equal = icm_equity(&[100.0, 100.0, 100.0], &[50.0, 30.0, 20.0]);
+        assert!(equal.iter().all(|e| (e - 100.0 / 3.0).abs() < 1e-3));
+        let linear = icm_equity(&[100.0, 300.0], &[1.0]);
+        assert!((linear[0] - 0.25).abs() < 1e-6 && (linear[1] - 0.75).abs() < 1e-6);
+
+        let equity = icm_equity(&[50.0, 30.0, 20.0], &[0.5, 0.3, 0.2]);
+        assert!((equity[0] - 0.3839).abs() < 1e-3, "{:?}", equity);
+        assert!((equity.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+        // a busted player finishes last
+        let busted = icm_equity(&[0.0, 60.0, 40.0], &[0.5, 0.3, 0.2]);
+        assert!((busted[0] - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_terminal_payoffs() {
+        let chips = PayoffModel::ChipEv.terminal_payoffs((100.0, 100.0), 10.0, 50.0);
+        assert_eq!(chips, TerminalPayoffs::chip_ev(50.0));
+        assert!(chips.is_symmetric(0));
+
+        // on the bubble losing the stack costs more than winning the same pot gains
+        let icm = PayoffModel::Icm {
+            payouts: vec![50.0, 30.0, 20.0],
+            other_stacks: vec![50.0, 10.0],
+        };
+        let bubble = icm.terminal_payoffs((50.0, 50.0), 10.0, 100.0);
+        assert!(bubble.win[0] > 0.0 && bubble.lose[0] < 0.0);
+        assert!(bubble.win[0] < -bubble.lose[0]);
+        assert!(!bubble.is_symmetric(0));
+
+        // busting the other player adds half their bounty on a progressive knockout
+        let bounty = PayoffModel::Bounty {
+            oop_bounty: 10.0,
+            ip_bounty: 20.0,
+            chips_per_bounty: 2.0,
+            progressive: true,
+        };
+        let all_in = bounty.terminal_payoffs((40.0, 100.0), 10.0, 90.0);
+        assert!((all_in.win[0] - 45.0).abs() < 1e-4);
+        assert!((all_in.win[1] - (45.0 + 10.0)).abs() < 1e-4);
+        let not_all_in = bounty.terminal_payoffs((40.0, 100.0), 10.0, 50.0);
+        assert_eq!(not_all_in.win[1], 25.0);
+
+        // a stack left with rounding residue after calling all in is still busted
+        let residue = bounty.terminal_payoffs((40.0, 100.0), 10.0, 89.9999);
+        assert!(
+            (residue.win[1] - (45.0 + 10.0)).abs() < 1e-3,
+            "{:?}",
+            residue
+        );
+    }
+}
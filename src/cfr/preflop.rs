@@ -0,0 +1,704 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::cancellation::CancellationToken;
+use super::discounting::{next_reach, normalize, update_regrets, update_strategy_sum};
+use super::game_params::GameParams;
+use super::preflop_params::PreflopParams;
+use crate::error::SolverError;
+use crate::ranges::preflop_equity::{PreflopEquity, STARTING_HANDS};
+
+// the button posts the small blind and is in position after the flop, the big blind is out of position
+pub const SMALL_BLIND: u8 = 0;
+pub const BIG_BLIND: u8 = 1;
+
+const EQUITY_SEED: u64 = 0x5eed;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreflopAction {
+    Fold,
+    Check,
+    Call,
+    Limp,
+    Raise(f32),
+    AllIn(f32),
+}
+
+impl PreflopAction {
+    // raises are labelled by their raise to size in big blinds, e.g. raise 2.5
+    pub fn label(&self, big_blind: f32) -> String {
+        match self {
+            PreflopAction::Fold => "fold".to_string(),
+            PreflopAction::Check => "check".to_string(),
+            PreflopAction::Call => "call".to_string(),
+            PreflopAction::Limp => "limp".to_string(),
+            PreflopAction::Raise(to) => {
+                format!("raise {}", (to / big_blind * 100.0).round() / 100.0)
+            }
+            PreflopAction::AllIn(_) => "allIn".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflopHandResult {
+    pub hand: String,
+    pub frequency: f32,
+    pub action_frequency: Vec<f32>,
+}
+
+// terminal nodes have no player, actions or hands, frequency is the acting player's reach like in postflop results
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflopNodeResult {
+    pub player: Option<u8>,
+    pub pot: f32,
+    pub actions: Vec<String>,
+    pub hands: Vec<PreflopHandResult>,
+    pub children: Vec<PreflopNodeResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflopResult {
+    pub params: PreflopParams,
+    pub exploitability: f32,
+    pub root: PreflopNodeResult,
+}
+
+// where a preflop line that sees a flop leaves the hand, ranges are in the native range syntax
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PostflopStart {
+    pub starting_pot: f32,
+    pub starting_stack: f32,
+    pub oop_range: String,
+    pub ip_range: String,
+}
+
+impl PostflopStart {
+    pub fn game_params(&self, template: &GameParams) -> GameParams {
+        let mut params = template.clone();
+        params.starting_pot = self.starting_pot;
+        params.starting_stack = self.starting_stack;
+        params.oop_stack = None;
+        params.ip_stack = None;
+        params
+    }
+}
+
+struct PreflopActionNode {
+    player: u8,
+    bets: [f32; 2],
+    actions: Vec<PreflopAction>,
+    children: Vec<PreflopNode>,
+    regrets: Vec<f32>,
+    strategy_sum: Vec<f32>,
+}
+
+// bets leave out the antes, which are dead money. every line that sees a flop ends at a showdown that splits the pot
+// by preflop equity
+enum PreflopNode {
+    Action(PreflopActionNode),
+    Fold { folder: u8, bets: [f32; 2] },
+    Showdown { bets: [f32; 2] },
+}
+
+struct Context<'a> {
+    params: &'a PreflopParams,
+    equity: &'a PreflopEquity,
+    traverser: u8,
+    iteration: u32,
+}
+
+pub struct PreflopGame {
+    params: PreflopParams,
+    equity: Arc<PreflopEquity>,
+    root: PreflopNode,
+    iteration: u32,
+}
+
+impl PreflopGame {
+    pub fn new(params: PreflopParams) -> Result<Self, SolverError> {
+        params.validate()?;
+        info!(
+            "Sampling preflop equity with {} runouts per matchup",
+            params.equity_samples
+        );
+        let equity = Arc::new(PreflopEquity::sampled(params.equity_samples, EQUITY_SEED));
+        Self::with_equity(params, equity)
+    }
+
+    // the equity table only depends on the sample count, so games with different sizings can share one
+    pub fn with_equity(
+        params: PreflopParams,
+        equity: Arc<PreflopEquity>,
+    ) -> Result<Self, SolverError> {
+        params.validate()?;
+        let root = build_node(
+            &params,
+            SMALL_BLIND,
+            [params.small_blind, params.big_blind],
+            0,
+        );
+        Ok(Self {
+            params,
+            equity,
+            root,
+            iteration: 0,
+        })
+    }
+
+    pub fn train(
+        &mut self,
+        target_exploitability: f32,
+        cancel: &CancellationToken,
+    ) -> Result<(), SolverError> {
+        let starting_reach = self.starting_reach();
+        loop {
+            cancel.check()?;
+            if self.iteration % 25 == 0 {
+                let exploitability = self.exploitability();
+                info!(
+                    "Preflop iteration {} exploitability = {} percent of the pot",
+                    self.iteration, exploitability
+                );
+                if exploitability < target_exploitability {
+                    return Ok(());
+                }
+            }
+            if self.iteration >= self.params.max_iterations {
+                info!(
+                    "Preflop stopped after {} iterations short of {} percent of the pot",
+                    self.iteration, target_exploitability
+                );
+                return Ok(());
+            }
+
+            for traverser in [SMALL_BLIND, BIG_BLIND] {
+                let context = Context {
+                    params: &self.params,
+                    equity: &self.equity,
+                    traverser,
+                    iteration: self.iteration,
+                };
+                self.root.cfr(&context, &starting_reach);
+            }
+            self.iteration += 1;
+        }
+    }
+
+    // how much the two best responses win on average against the average strategies, in percent of the blinds and
+    // antes
+    pub fn exploitability(&self) -> f32 {
+        let reach = self.starting_reach();
+        let matchups: f32 = (0..STARTING_HANDS)
+            .map(|i| {
+                (0..STARTING_HANDS)
+                    .map(|j| reach[0][i] * reach[1][j] * self.equity.compatibility(i, j))
+                    .sum::<f32>()
+            })
+            .sum();
+
+        let total: f32 = [SMALL_BLIND, BIG_BLIND]
+            .iter()
+            .map(|traverser| {
+                let context = Context {
+                    params: &self.params,
+                    equity: &self.equity,
+                    traverser: *traverser,
+                    iteration: self.iteration,
+                };
+                let values = self.root.best_response(&context, &reach);
+                values
+                    .iter()
+                    .zip(reach[usize::from(*traverser)].iter())
+                    .map(|(value, reach)| value * reach)
+                    .sum::<f32>()
+                    / matchups
+            })
+            .sum();
+        total / 2.0 / self.params.starting_pot() * 100.0
+    }
+
+    pub fn results(&self) -> PreflopResult {
+        PreflopResult {
+            params: self.params.clone(),
+            exploitability: self.exploitability(),
+            root: self
+                .root
+                .output_results(&self.params, &self.equity, &self.starting_reach()),
+        }
+    }
+
+    // follows a line of action labels, e.g. raise 2.5 then call, to where it sees a flop. each player's range is
+    // their starting hands weighted by how often they take that line
+    pub fn postflop_start(&self, line: &[&str]) -> Result<PostflopStart, SolverError> {
+        let mut reach = self.starting_reach();
+        let mut node = &self.root;
+        for (depth, label) in line.iter().enumerate() {
+            let action_node = match node {
+                PreflopNode::Action(action_node) => action_node,
+                _ => {
+                    return Err(SolverError::InvalidMessage(format!(
+                        "preflop action ends before {}",
+                        line[depth..].join(", ")
+                    )))
+                }
+            };
+            let action = action_node
+                .actions
+                .iter()
+                .position(|action| action.label(self.params.big_blind) == *label)
+                .ok_or_else(|| {
+                    SolverError::InvalidMessage(format!(
+                        "no {} after {}, the options are {}",
+                        label,
+                        if depth == 0 {
+                            "the blinds".to_string()
+                        } else {
+                            line[..depth].join(", ")
+                        },
+                        action_node.labels(self.params.big_blind).join(", ")
+                    ))
+                })?;
+
+            let strategy = action_node.average_strategy();
+            let player = usize::from(action_node.player);
+            for (hand, reach) in reach[player].iter_mut().enumerate() {
+                *reach *= strategy[hand + action * STARTING_HANDS];
+            }
+            node = &action_node.children[action];
+        }
+
+        let bets = match node {
+            PreflopNode::Showdown { bets } => bets,
+            _ => {
+                return Err(SolverError::InvalidMessage(format!(
+                    "{} doesn't see a flop",
+                    line.join(", ")
+                )))
+            }
+        };
+
+        let hands = &self.equity.hands;
+        let range = |player: u8| -> Result<String, SolverError> {
+            let player_reach = &reach[usize::from(player)];
+            let entries: Vec<String> = hands
+                .iter()
+                .enumerate()
+                .filter_map(|(i, hand)| {
+                    // the full fraction, so small parts of the range aren't lost to rounding
+                    let weight = (player_reach[i] / hand.combos().len() as f32).min(1.0);
+                    if weight >= 1.0 {
+                        Some(hand.label())
+                    } else if weight > 0.0 {
+                        Some(format!("{}:{}", hand.label(), weight))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            if entries.is_empty() {
+                return Err(SolverError::EmptyRange(line.join(", ")));
+            }
+            Ok(entries.join(","))
+        };
+
+        Ok(PostflopStart {
+            starting_pot: bets[0] + bets[1] + 2.0 * self.params.ante,
+            starting_stack: self.params.starting_stack - self.params.ante - bets[0].max(bets[1]),
+            oop_range: range(BIG_BLIND)?,
+            ip_range: range(SMALL_BLIND)?,
+        })
+    }
+
+    // both players start with every hand, each class weighted by its number of combos
+    fn starting_reach(&self) -> [Vec<f32>; 2] {
+        let reach: Vec<f32> = self
+            .equity
+            .hands
+            .iter()
+            .map(|hand| hand.combos().len() as f32)
+            .collect();
+        [reach.clone(), reach]
+    }
+}
+
+fn build_node(params: &PreflopParams, player: u8, bets: [f32; 2], raises: u8) -> PreflopNode {
+    let opponent = player ^ 1;
+    let own = bets[usize::from(player)];
+    let facing = bets[usize::from(opponent)];
+    let cap = params.starting_stack - params.ante;
+
+    let mut actions = vec![];
+    if facing > own {
+        actions.push(PreflopAction::Fold);
+        if raises == 0 {
+            if params.allow_limp {
+                actions.push(PreflopAction::Limp);
+            }
+        } else {
+            actions.push(PreflopAction::Call);
+        }
+    } else {
+        actions.push(PreflopAction::Check);
+    }
+
+    if facing < cap {
+        for to in params.raise_sizes(raises, facing) {
+            if to > facing && to < cap && !actions.contains(&PreflopAction::Raise(to)) {
+                actions.push(PreflopAction::Raise(to));
+            }
+        }
+        actions.push(PreflopAction::AllIn(cap));
+    }
+
+    let children = actions
+        .iter()
+        .map(|action| {
+            let mut next = bets;
+            match action {
+                PreflopAction::Fold => PreflopNode::Fold {
+                    folder: player,
+                    bets,
+                },
+                PreflopAction::Check => PreflopNode::Showdown { bets },
+                PreflopAction::Call => {
+                    next[usize::from(player)] = facing;
+                    PreflopNode::Showdown { bets: next }
+                }
+                PreflopAction::Limp => {
+                    next[usize::from(player)] = facing;
+                    build_node(params, opponent, next, raises)
+                }
+                PreflopAction::Raise(to) | PreflopAction::AllIn(to) => {
+                    next[usize::from(player)] = *to;
+                    build_node(params, opponent, next, raises + 1)
+                }
+            }
+        })
+        .collect();
+
+    let size = actions.len() * STARTING_HANDS;
+    PreflopNode::Action(PreflopActionNode {
+        player,
+        bets,
+        actions,
+        children,
+        regrets: vec![0.0; size],
+        strategy_sum: vec![0.0; size],
+    })
+}
+
+impl PreflopNode {
+    // counterfactual values of the traverser's hands, weighted by the opponent's reach and card removal
+    fn cfr(&mut self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        match self {
+            PreflopNode::Action(node) => node.cfr(context, reach),
+            _ => self.terminal_values(context, reach),
+        }
+    }
+
+    fn best_response(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        match self {
+            PreflopNode::Action(node) => node.best_response(context, reach),
+            _ => self.terminal_values(context, reach),
+        }
+    }
+
+    fn terminal_values(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let traverser = usize::from(context.traverser);
+        let opponent_reach = &reach[traverser ^ 1];
+        let ante = context.params.ante;
+        let equity = context.equity;
+
+        (0..STARTING_HANDS)
+            .map(|hand| match self {
+                PreflopNode::Fold { folder, bets } => {
+                    let payoff = if usize::from(*folder) == traverser {
+                        -(bets[traverser] + ante)
+                    } else {
+                        bets[traverser ^ 1] + ante
+                    };
+                    let unblocked: f32 = opponent_reach
+                        .iter()
+                        .enumerate()
+                        .map(|(villain, reach)| reach * equity.compatibility(hand, villain))
+                        .sum();
+                    payoff * unblocked
+                }
+                PreflopNode::Showdown { bets } => {
+                    let pot = bets[0] + bets[1] + 2.0 * ante;
+                    let invested = bets[traverser] + ante;
+                    opponent_reach
+                        .iter()
+                        .enumerate()
+                        .map(|(villain, reach)| {
+                            reach
+                                * equity.compatibility(hand, villain)
+                                * (equity.equity(hand, villain) * pot - invested)
+                        })
+                        .sum()
+                }
+                PreflopNode::Action(_) => 0.0,
+            })
+            .collect()
+    }
+
+    fn output_results(
+        &self,
+        params: &PreflopParams,
+        equity: &PreflopEquity,
+        reach: &[Vec<f32>],
+    ) -> PreflopNodeResult {
+        let (bets, node) = match self {
+            PreflopNode::Action(node) => (node.bets, node),
+            PreflopNode::Fold { bets, .. } | PreflopNode::Showdown { bets } => {
+                return PreflopNodeResult {
+                    player: None,
+                    pot: bets[0] + bets[1] + 2.0 * params.ante,
+                    actions: vec![],
+                    hands: vec![],
+                    children: vec![],
+                }
+            }
+        };
+
+        let player = usize::from(node.player);
+        let strategy = node.average_strategy();
+        let hands = equity
+            .hands
+            .iter()
+            .enumerate()
+            .map(|(i, hand)| PreflopHandResult {
+                hand: hand.label(),
+                frequency: reach[player][i] / hand.combos().len() as f32,
+                action_frequency: (0..node.actions.len())
+                    .map(|action| strategy[i + action * STARTING_HANDS])
+                    .collect(),
+            })
+            .collect();
+
+        let children = node
+            .children
+            .iter()
+            .enumerate()
+            .map(|(action, child)| {
+                child.output_results(
+                    params,
+                    equity,
+                    &next_reach(reach, player, &strategy, action),
+                )
+            })
+            .collect();
+
+        PreflopNodeResult {
+            player: Some(node.player),
+            pot: bets[0] + bets[1] + 2.0 * params.ante,
+            actions: node.labels(params.big_blind),
+            hands,
+            children,
+        }
+    }
+}
+
+impl PreflopActionNode {
+    fn labels(&self, big_blind: f32) -> Vec<String> {
+        self.actions
+            .iter()
+            .map(|action| action.label(big_blind))
+            .collect()
+    }
+
+    fn cfr(&mut self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let strategy = self.current_strategy();
+        let player = usize::from(self.player);
+
+        if self.player != context.traverser {
+            update_strategy_sum(
+                &mut self.strategy_sum,
+                &strategy,
+                &reach[player],
+                context.iteration,
+            );
+            let mut values = vec![0.0; STARTING_HANDS];
+            for (action, child) in self.children.iter_mut().enumerate() {
+                let next = next_reach(reach, player, &strategy, action);
+                let child_values = child.cfr(context, &next);
+                values
+                    .iter_mut()
+                    .zip(child_values.iter())
+                    .for_each(|(value, child)| *value += child);
+            }
+            return values;
+        }
+
+        let action_values: Vec<Vec<f32>> = self
+            .children
+            .iter_mut()
+            .map(|child| child.cfr(context, reach))
+            .collect();
+
+        let mut values = vec![0.0; STARTING_HANDS];
+        for (action, action_value) in action_values.iter().enumerate() {
+            for hand in 0..STARTING_HANDS {
+                values[hand] += strategy[hand + action * STARTING_HANDS] * action_value[hand];
+            }
+        }
+
+        update_regrets(
+            &mut self.regrets,
+            &action_values,
+            &values,
+            context.iteration,
+        );
+        values
+    }
+
+    fn best_response(&self, context: &Context, reach: &[Vec<f32>]) -> Vec<f32> {
+        let strategy = self.average_strategy();
+        let player = usize::from(self.player);
+
+        if self.player != context.traverser {
+            let mut values = vec![0.0; STARTING_HANDS];
+            for (action, child) in self.children.iter().enumerate() {
+                let next = next_reach(reach, player, &strategy, action);
+                let child_values = child.best_response(context, &next);
+                values
+                    .iter_mut()
+                    .zip(child_values.iter())
+                    .for_each(|(value, child)| *value += child);
+            }
+            return values;
+        }
+
+        let mut values = vec![f32::MIN; STARTING_HANDS];
+        for child in self.children.iter() {
+            let child_values = child.best_response(context, reach);
+            values
+                .iter_mut()
+                .zip(child_values.iter())
+                .for_each(|(value, child)| *value = value.max(*child));
+        }
+        values
+    }
+
+    fn current_strategy(&self) -> Vec<f32> {
+        normalize(&self.regrets, self.actions.len(), |regret| regret.max(0.0))
+    }
+
+    fn average_strategy(&self) -> Vec<f32> {
+        normalize(&self.strategy_sum, self.actions.len(), |sum| sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::traversal::build_traversal_from_ranges;
+    use crate::ranges::utility::parse_board;
+
+    fn solve(params: PreflopParams) -> PreflopGame {
+        let equity = Arc::new(PreflopEquity::sampled(40, EQUITY_SEED));
+        let mut game = PreflopGame::with_equity(params, equity).unwrap();
+        game.train(1.0, &CancellationToken::new()).unwrap();
+        game
+    }
+
+    fn frequencies<'a>(node: &'a PreflopNodeResult, hand: &str) -> &'a [f32] {
+        &node
+            .hands
+            .iter()
+            .find(|h| h.hand == hand)
+            .unwrap()
+            .action_frequency
+    }
+
+    #[test]
+    fn test_push_or_fold() {
+        let params = PreflopParams::new(0.5, 1.0, 0.0, 10.0, vec![], vec![], vec![], false);
+        let game = solve(params);
+        let root = game.results().root;
+
+        assert_eq!(root.actions, vec!["fold", "allIn"]);
+        assert_eq!(root.pot, 1.5);
+        assert!(frequencies(&root, "AA")[1] > 0.95);
+        assert!(frequencies(&root, "KK")[1] > 0.95);
+
+        let facing_jam = &root.children[1];
+        assert_eq!(facing_jam.player, Some(BIG_BLIND));
+        assert_eq!(facing_jam.actions, vec!["fold", "call"]);
+        assert!(frequencies(facing_jam, "AA")[1] > 0.95);
+        assert!(frequencies(facing_jam, "72o")[0] > 0.95);
+        assert_eq!(facing_jam.children[1].pot, 20.0);
+    }
+
+    #[test]
+    fn test_training_stops_at_the_iteration_cap() {
+        let mut params = PreflopParams::new(0.5, 1.0, 0.0, 10.0, vec![], vec![], vec![], false);
+        params.max_iterations = 30;
+        let equity = Arc::new(PreflopEquity::sampled(40, EQUITY_SEED));
+        let mut game = PreflopGame::with_equity(params, equity).unwrap();
+        game.train(0.0, &CancellationToken::new()).unwrap();
+        assert_eq!(game.iteration, 30);
+    }
+
+    #[test]
+    fn test_open_and_call_starts_a_postflop_game() {
+        let mut params =
+            PreflopParams::new(0.5, 1.0, 0.1, 100.0, vec![2.5], vec![3.0], vec![2.2], true);
+        params.equity_samples = 40;
+        let game = solve(params);
+        let root = game.results().root;
+        assert_eq!(root.actions, vec!["fold", "limp", "raise 2.5", "allIn"]);
+        assert!((root.pot - 1.7).abs() < 1e-5);
+        let three_bet = &root.children[2];
+        assert_eq!(
+            three_bet.actions,
+            vec!["fold", "call", "raise 7.5", "allIn"]
+        );
+        assert_eq!(
+            three_bet.children[2].actions,
+            vec!["fold", "call", "raise 16.5", "allIn"]
+        );
+
+        let start = game.postflop_start(&["raise 2.5", "call"]).unwrap();
+        assert!((start.starting_pot - 5.2).abs() < 1e-5);
+        assert!((start.starting_stack - 97.4).abs() < 1e-5);
+        let weight = |range: &str, hand: &str| -> f32 {
+            range
+                .split(',')
+                .find_map(|entry| match entry.split_once(':') {
+                    Some((label, fraction)) if label == hand => Some(fraction.parse().unwrap()),
+                    None if entry == hand => Some(1.0),
+                    _ => None,
+                })
+                .unwrap_or(0.0)
+        };
+        assert!(weight(&start.ip_range, "AKo") > weight(&start.ip_range, "72o"));
+        assert!(!start.oop_range.is_empty());
+        // weights are passed on as fractions rather than rounded percentages
+        assert!(!start.ip_range.contains('@'));
+
+        // the ranges and sizes plug straight into a postflop solve
+        let board = parse_board("qs,jh,2h").unwrap();
+        assert!(build_traversal_from_ranges(board, &start.oop_range, &start.ip_range).is_ok());
+        let postflop = start.game_params(&GameParams::default());
+        assert_eq!(postflop.starting_pot, start.starting_pot);
+
+        assert!(game.postflop_start(&["limp", "check"]).is_ok());
+        assert!(matches!(
+            game.postflop_start(&["raise 2.5", "fold"]),
+            Err(SolverError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            game.postflop_start(&["raise 3"]),
+            Err(SolverError::InvalidMessage(_))
+        ));
+    }
+}
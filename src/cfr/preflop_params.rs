@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::SolverError;
+
+// heads up, the small blind is the button and acts first. amounts are in chips and stacks are what each player has
+// before posting. opens are raise to sizes in big blinds, 3bets and 4bets raise to a multiple of the raise they face,
+// the first raise over a limp counts as an open and every raise after the 4bet is all in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflopParams {
+    pub small_blind: f32,
+    pub big_blind: f32,
+    pub ante: f32,
+    pub starting_stack: f32,
+    pub open_sizes: Vec<f32>,
+    pub three_bet_sizes: Vec<f32>,
+    pub four_bet_sizes: Vec<f32>,
+    pub allow_limp: bool,
+    #[serde(default = "default_equity_samples")]
+    pub equity_samples: u32,
+    // training stops here even if the exploitability target hasn't been reached
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u32,
+}
+
+fn default_equity_samples() -> u32 {
+    1000
+}
+
+fn default_max_iterations() -> u32 {
+    5000
+}
+
+impl PreflopParams {
+    pub fn new(
+        small_blind: f32,
+        big_blind: f32,
+        ante: f32,
+        starting_stack: f32,
+        open_sizes: Vec<f32>,
+        three_bet_sizes: Vec<f32>,
+        four_bet_sizes: Vec<f32>,
+        allow_limp: bool,
+    ) -> Self {
+        Self {
+            small_blind,
+            big_blind,
+            ante,
+            starting_stack,
+            open_sizes,
+            three_bet_sizes,
+            four_bet_sizes,
+            allow_limp,
+            equity_samples: default_equity_samples(),
+            max_iterations: default_max_iterations(),
+        }
+    }
+
+    // the dead money and blinds in the middle before anyone acts
+    pub fn starting_pot(&self) -> f32 {
+        self.small_blind + self.big_blind + 2.0 * self.ante
+    }
+
+    // raise to sizes for the given number of earlier raises, the big blind isn't counted as one
+    pub fn raise_sizes(&self, raises: u8, last_raise_to: f32) -> Vec<f32> {
+        match raises {
+            0 => self
+                .open_sizes
+                .iter()
+                .map(|size| size * self.big_blind)
+                .collect(),
+            1 => self
+                .three_bet_sizes
+                .iter()
+                .map(|size| size * last_raise_to)
+                .collect(),
+            2 => self
+                .four_bet_sizes
+                .iter()
+                .map(|size| size * last_raise_to)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SolverError> {
+        if !(self.big_blind > 0.0 && self.big_blind.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "big blind must be positive, got {}",
+                self.big_blind
+            )));
+        }
+        if !(self.small_blind > 0.0 && self.small_blind <= self.big_blind) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "small blind must be positive and no bigger than the big blind, got {}",
+                self.small_blind
+            )));
+        }
+        if !(self.ante >= 0.0 && self.ante.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "ante can't be negative, got {}",
+                self.ante
+            )));
+        }
+        if !(self.starting_stack > self.big_blind + self.ante && self.starting_stack.is_finite()) {
+            return Err(SolverError::InconsistentBetConfig(format!(
+                "starting stack must cover the big blind and ante, got {}",
+                self.starting_stack
+            )));
+        }
+        if self.equity_samples == 0 {
+            return Err(SolverError::InconsistentBetConfig(
+                "preflop equity needs at least one sample per matchup".to_string(),
+            ));
+        }
+
+        let raises = [
+            ("open", &self.open_sizes),
+            ("3bet", &self.three_bet_sizes),
+            ("4bet", &self.four_bet_sizes),
+        ];
+        for (name, sizes) in raises.iter() {
+            if let Some(size) = sizes.iter().find(|s| !(**s > 1.0 && s.is_finite())) {
+                return Err(SolverError::InconsistentBetConfig(format!(
+                    "{} sizes must be bigger than what they raise, got {}",
+                    name, size
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+use super::aggregation::action_label;
+use super::game::Game;
+use super::game_params::GameParams;
+use super::payoff::PayoffModel;
+use super::solution_file::SolutionReader;
+use super::traversal::build_traversal_from_parsed_ranges;
+use crate::error::SolverError;
+use crate::nodes::chance_node::canonical_card;
+use crate::nodes::node::{ActionType, NodeResult, NodeResultType, PlayerNodeResult};
+use crate::ranges::combination::{Board, Hand};
+use crate::ranges::parser::ParsedRange;
+use crate::ranges::utility::{
+    card_to_number, check_hand_overlap, number_to_card, parse_combination,
+};
+
+// where a line through a stored solution leaves the hand, to be solved again on its own. ranges are each player's
+// reach weighted range at the node, every combo at its starting weight times how often the player's own strategy
+// takes it down the line, kept exactly rather than rounded to a whole percentage. evs are each combo's share of the
+// pot at the node in the parent solution, best response values against the other player's strategy there
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubgameStart {
+    pub board: Board,
+    pub starting_pot: f32,
+    pub starting_stack: f32,
+    pub oop_range: ParsedRange,
+    pub ip_range: ParsedRange,
+    pub oop_evs: Vec<(Hand, f32)>,
+    pub ip_evs: Vec<(Hand, f32)>,
+    pub blueprint_payoffs: PayoffModel,
+}
+
+impl SubgameStart {
+    // the template's bet sizes, usually finer than the parent's, played with the pot and stacks the line leaves
+    pub fn game_params(&self, template: &GameParams) -> GameParams {
+        let mut params = template.clone();
+        params.starting_pot = self.starting_pot;
+        params.starting_stack = self.starting_stack;
+        params.oop_stack = None;
+        params.ip_stack = None;
+        params
+    }
+
+    pub fn game(&self, template: &GameParams) -> Result<Game, SolverError> {
+        let traversal =
+            build_traversal_from_parsed_ranges(self.board, &self.oop_range, &self.ip_range)?;
+        Ok(Game::new(traversal, self.game_params(template), self.board))
+    }
+
+    // re-solves without leaving the protected player more exploitable than the blueprint was, the opponent keeps
+    // the option of the values they had there. this is the resolve gadget, the max margin gadget isn't implemented
+    pub fn safe_game(&self, template: &GameParams, protect: u8) -> Result<Game, SolverError> {
+        // icm and bounty utilities are measured from the stacks each solve starts with, so the blueprint's values
+        // don't carry over to the subgame
+        if self.blueprint_payoffs != PayoffModel::ChipEv
+            || template.payoff_model != PayoffModel::ChipEv
+        {
+            return Err(SolverError::InconsistentBetConfig(
+                "safe re-solving needs chip ev payoffs in both the blueprint and the subgame"
+                    .to_string(),
+            ));
+        }
+        let opponent = protect ^ 1;
+        let evs = if opponent == 1 {
+            &self.ip_evs
+        } else {
+            &self.oop_evs
+        };
+        // utilities in the subgame are relative to half the pot
+        let values = evs
+            .iter()
+            .map(|(hand, ev)| (*hand, ev - self.starting_pot / 2.0))
+            .collect();
+        Ok(self.game(template)?.with_resolve_gadget(opponent, values))
+    }
+}
+
+// follows a line written the way action sequences are, X-B5-C-Td, from the root of a stored solution. a new solve
+// starts where a street does, so the line has to end on a dealt card, or be empty for the root itself. only the
+// nodes along the line are decoded
+pub fn subgame_start<B: AsRef<[u8]>>(
+    reader: &SolutionReader<B>,
+    line: &str,
+) -> Result<SubgameStart, SolverError> {
+    let metadata = reader.metadata()?;
+    let mut board = metadata.starting_board;
+    let mut pot = metadata.game_params.starting_pot;
+    let mut node = reader.node(0)?;
+    let mut sequence: Vec<&str> = vec![];
+
+    for step in line
+        .split('-')
+        .map(str::trim)
+        .filter(|step| !step.is_empty())
+    {
+        let position = match node.result.node_type {
+            NodeResultType::Terminal => None,
+            NodeResultType::Chance => {
+                let card = card_to_number(step.to_lowercase())?;
+                // only canonical cards are stored, the ranges would need their suits swapped to follow an isomorph
+                let canonical = canonical_card(&board, card);
+                if canonical != card && !board.contains(&card) {
+                    return Err(SolverError::InvalidMessage(format!(
+                        "'{}' is solved as its isomorph {} after '{}', follow the line with that card instead",
+                        step,
+                        number_to_card(canonical),
+                        sequence.join("-")
+                    )));
+                }
+                node.result
+                    .next_cards
+                    .as_ref()
+                    .and_then(|cards| cards.iter().position(|next| *next == card))
+                    .map(|position| {
+                        board[board.iter().position(|c| *c == 52).unwrap_or(4)] = card;
+                        position
+                    })
+            }
+            NodeResultType::Action => {
+                let actions = action_steps(&node.result);
+                actions
+                    .iter()
+                    .position(|(label, _)| label.eq_ignore_ascii_case(step))
+                    .map(|position| {
+                        pot += actions[position].1;
+                        position
+                    })
+            }
+        };
+
+        let child = position
+            .and_then(|position| node.children.get(position))
+            .ok_or_else(|| {
+                SolverError::InvalidMessage(format!(
+                    "'{}' doesn't follow '{}' in the solution{}",
+                    step,
+                    sequence.join("-"),
+                    describe_options(&node.result)
+                ))
+            })?;
+        sequence.push(step);
+        node = reader.node(*child)?;
+    }
+
+    let ends_on_card = sequence
+        .last()
+        .map_or(true, |step| card_to_number(step.to_lowercase()).is_ok());
+    if !matches!(node.result.node_type, NodeResultType::Action) || !ends_on_card {
+        return Err(SolverError::InvalidMessage(format!(
+            "'{}' doesn't end at the start of a street, end the line on a dealt card",
+            line
+        )));
+    }
+
+    let contributed = (pot - metadata.game_params.starting_pot) / 2.0;
+    let oop_values = player_values(node.result.oop_values.as_ref(), &board, "OOP")?;
+    let ip_values = player_values(node.result.ip_values.as_ref(), &board, "IP")?;
+    Ok(SubgameStart {
+        board,
+        starting_pot: pot,
+        starting_stack: metadata.game_params.effective_stack() - contributed,
+        oop_range: reach_range(&oop_values, "OOP")?,
+        ip_range: reach_range(&ip_values, "IP")?,
+        oop_evs: oop_values
+            .iter()
+            .map(|(hand, _, ev)| (*hand, *ev))
+            .collect(),
+        ip_evs: ip_values.iter().map(|(hand, _, ev)| (*hand, *ev)).collect(),
+        blueprint_payoffs: metadata.game_params.payoff_model.clone(),
+    })
+}
+
+// each action's label and the chips it puts in the pot
+fn action_steps(node: &NodeResult) -> Vec<(String, f32)> {
+    let actions = node.action_list.clone().unwrap_or_default();
+    let sizings = node.bet_sizings.clone().unwrap_or_default();
+    actions
+        .iter()
+        .zip(sizings.iter())
+        .map(|(action, sizing)| {
+            let chips = match action {
+                ActionType::Bet | ActionType::Call => *sizing,
+                _ => 0.0,
+            };
+            (action_label(*action, *sizing), chips)
+        })
+        .collect()
+}
+
+fn describe_options(node: &NodeResult) -> String {
+    match node.node_type {
+        NodeResultType::Action => format!(
+            ", the options are {}",
+            action_steps(node)
+                .iter()
+                .map(|(label, _)| label.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        NodeResultType::Chance if node.next_cards.is_none() => {
+            ", the solution wasn't exported past this street".to_string()
+        }
+        NodeResultType::Chance => ", expected a card such as Td".to_string(),
+        NodeResultType::Terminal => ", the hand is already over".to_string(),
+    }
+}
+
+// every combo the new board leaves with its reach and ev
+fn player_values(
+    values: Option<&PlayerNodeResult>,
+    board: &Board,
+    player: &str,
+) -> Result<Vec<(Hand, f32, f32)>, SolverError> {
+    let hands = values.ok_or_else(|| {
+        SolverError::InvalidMessage(format!(
+            "the solution has no {} values at this node",
+            player
+        ))
+    })?;
+
+    let combos = hands
+        .hands
+        .iter()
+        .map(|hand| {
+            Ok((
+                parse_combination(&hand.combination)?,
+                hand.reach.unwrap_or(0.0),
+                hand.ev,
+            ))
+        })
+        .collect::<Result<Vec<(Hand, f32, f32)>, SolverError>>()?;
+    Ok(combos
+        .into_iter()
+        .filter(|(hand, _, _)| !check_hand_overlap(*hand, board))
+        .collect())
+}
+
+fn reach_range(values: &[(Hand, f32, f32)], player: &str) -> Result<ParsedRange, SolverError> {
+    let range = ParsedRange::from_weights(values.iter().map(|(hand, reach, _)| (*hand, *reach)));
+
+    if range.is_empty() {
+        return Err(SolverError::EmptyRange(player.to_string()));
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::{test_params, trained_game_with};
+    use crate::cfr::solution_file::encoded_solution;
+    use crate::nodes::node::ResultFilter;
+    use crate::ranges::utility::parse_board;
+    use std::collections::HashMap;
+
+    fn turn_params() -> GameParams {
+        GameParams {
+            oop_turn_bets: vec![vec![0.5]],
+            ip_turn_bets: vec![vec![0.5]],
+            ..test_params(vec![vec![0.5]])
+        }
+    }
+
+    fn turn_solution() -> (NodeResult, SolutionReader<Vec<u8>>) {
+        let game = trained_game_with(
+            "as,kh,7c,2d",
+            "QQ,JJ,AK:0.5",
+            "QQ,TT,AK",
+            turn_params(),
+            1.0,
+        );
+        let filter = ResultFilter::default();
+        let reader = SolutionReader::from_bytes(encoded_solution(&game, &filter)).unwrap();
+        (game.node_results(&filter).unwrap(), reader)
+    }
+
+    #[test]
+    fn test_river_start_after_turn_checks_through() {
+        let (root, reader) = turn_solution();
+        let start = subgame_start(&reader, "X-X-Qd").unwrap();
+
+        assert_eq!(start.board, parse_board("as,kh,7c,2d,qd").unwrap());
+        assert_eq!(start.starting_pot, 10.0);
+        assert_eq!(start.starting_stack, 100.0);
+
+        // oop acts first on the turn, so their river weights are the starting weight times how often they check
+        for hand in root.hand_actions.as_ref().unwrap() {
+            let combo = parse_combination(&hand.combination).unwrap();
+            if check_hand_overlap(combo, &start.board) {
+                continue;
+            }
+            let expected = hand.frequency * hand.action_frequency[0];
+            assert!(
+                (start.oop_range.weight(&combo) - expected).abs() < 1e-5,
+                "{} {} vs {}",
+                hand.combination,
+                start.oop_range.weight(&combo),
+                expected
+            );
+        }
+        assert_eq!(
+            start.oop_range.weight(&parse_combination("QdQc").unwrap()),
+            0.0
+        );
+
+        // the river solve runs on the ranges as they are
+        let mut game = start.game(&turn_params()).unwrap();
+        game.train(1.0).unwrap();
+        assert!(game.results(&ResultFilter::default()).is_ok());
+    }
+
+    #[test]
+    fn test_pot_and_stack_follow_the_line() {
+        let (_, reader) = turn_solution();
+        let start = subgame_start(&reader, "B5-C-Qd").unwrap();
+        assert_eq!(start.starting_pot, 20.0);
+        assert_eq!(start.starting_stack, 95.0);
+
+        assert!(subgame_start(&reader, "B5-C").is_err());
+        assert!(subgame_start(&reader, "B7").is_err());
+        assert!(subgame_start(&reader, "X-X-Kh").is_err());
+        assert!(subgame_start(&reader, "B5-F").is_err());
+    }
+
+    #[test]
+    fn test_safe_resolve_keeps_opponent_at_blueprint_values() {
+        let (_, reader) = turn_solution();
+        let start = subgame_start(&reader, "X-X-Qd").unwrap();
+        assert_eq!(start.ip_evs.len(), start.ip_range.len());
+        let mut bounty = turn_params();
+        bounty.payoff_model = PayoffModel::Bounty {
+            oop_bounty: 10.0,
+            ip_bounty: 10.0,
+            chips_per_bounty: 1.0,
+            progressive: false,
+        };
+        assert!(start.safe_game(&bounty, 0).is_err());
+
+        let mut finer = turn_params();
+        finer.oop_river_bets = vec![vec![0.33, 0.75, 1.5]];
+        finer.ip_river_bets = vec![vec![0.33, 0.75, 1.5]];
+        let mut game = start.safe_game(&finer, 0).unwrap();
+        game.train(0.05).unwrap();
+
+        // the results are the subgame's, without the gadget's choice in front of it
+        let root = game.results(&ResultFilter::default()).unwrap().node_results;
+        assert_eq!(root.player, Some(0));
+        assert_eq!(root.action_list.as_ref().unwrap().len(), 4);
+
+        // every ip combo's best response to the re-solved strategy is worth no more than it was against the
+        // blueprint, up to how far either solve is from equilibrium. the blueprint stopped at 1% of the pot
+        let blueprint: HashMap<Hand, f32> = start.ip_evs.iter().copied().collect();
+        for hand in root.ip_values.unwrap().hands.iter() {
+            let combo = parse_combination(&hand.combination).unwrap();
+            if start.ip_range.weight(&combo) <= 0.0 {
+                continue;
+            }
+            assert!(
+                hand.ev <= blueprint[&combo] + 0.02 * start.starting_pot,
+                "{} gets {} against the re-solve and {} against the blueprint",
+                hand.combination,
+                hand.ev,
+                blueprint[&combo]
+            );
+        }
+    }
+}
@@ -0,0 +1,718 @@
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use super::game::Game;
+use super::game_params::GameParams;
+use crate::error::SolverError;
+use crate::nodes::node::{
+    ActionType, CombinationActions, CombinationValues, NodeResult, NodeResultType,
+    PlayerNodeResult, ResultFilter, ResultSink,
+};
+use crate::ranges::combination::{Board, Combination};
+use crate::ranges::texture::BoardTexture;
+use crate::ranges::utility::{hand_to_string, parse_combination};
+
+// file layout, all integers little endian:
+//   header   magic and version, the rest is left zeroed since the sections aren't placed until the file is done
+//   nodes    one zstd frame per node, children referenced by node id. ids are handed out in tree order, every
+//            node's children come after it
+//   metadata zstd compressed json of everything in GameResult except the tree
+//   index    fixed size entry per node id with its offset, length, street and type, never compressed so a reader
+//            can find any node straight from a memory map
+//   trailer  the header again with the node count and where the metadata and index sections start, so the file can
+//            be written front to back without seeking. version 1 files carry these in the header and have no trailer
+const MAGIC: &[u8; 4] = b"RPSF";
+pub const FORMAT_VERSION: u16 = 2;
+const HEADER_LEN: usize = 40;
+const INDEX_ENTRY_LEN: usize = 14;
+const COMPRESSION_LEVEL: i32 = 3;
+const NO_PLAYER: u8 = u8::MAX;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionMetadata {
+    pub oop_range: Vec<Combination>,
+    pub ip_range: Vec<Combination>,
+    pub game_params: GameParams,
+    pub starting_board: Board,
+    // missing from files written before textures were exported
+    pub texture: Option<BoardTexture>,
+}
+
+// a single decoded node, next_nodes is left empty and children holds the ids to ask the reader for instead
+#[derive(Debug)]
+pub struct StoredNode {
+    pub id: u32,
+    pub result: NodeResult,
+    pub children: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    offset: u64,
+    len: u32,
+    street: u8,
+    node_type: u8,
+}
+
+// the tree is walked once and every node is compressed and written out as soon as it's built, only the index is
+// kept until the end
+pub fn encode_solution<W: Write>(
+    game: &Game,
+    filter: &ResultFilter,
+    out: W,
+) -> Result<(), SolverError> {
+    let mut writer = SolutionWriter::new(out)?;
+    game.export_results(filter, &mut writer)?;
+    writer.finish(&game.metadata()?)
+}
+
+// the whole file in memory, for tests that read back what they wrote
+#[cfg(test)]
+pub fn encoded_solution(game: &Game, filter: &ResultFilter) -> Vec<u8> {
+    let mut bytes = vec![];
+    encode_solution(game, filter, &mut bytes).unwrap();
+    bytes
+}
+
+// node records go out in whatever order they're finished, the index is what keeps them findable by id
+struct SolutionWriter<W> {
+    out: W,
+    offset: u64,
+    index: Vec<IndexEntry>,
+}
+
+impl<W: Write> SolutionWriter<W> {
+    fn new(mut out: W) -> Result<Self, SolverError> {
+        let mut header = Encoder::default();
+        header.buf.extend_from_slice(MAGIC);
+        header.u16(FORMAT_VERSION);
+        header.buf.resize(HEADER_LEN, 0);
+        out.write_all(&header.buf)?;
+
+        Ok(Self {
+            out,
+            offset: HEADER_LEN as u64,
+            index: vec![],
+        })
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> Result<(), SolverError> {
+        self.out.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn finish(mut self, metadata: &SolutionMetadata) -> Result<(), SolverError> {
+        let compressed = compress(&serde_json::to_vec(metadata)?)?;
+        let meta_offset = self.offset;
+        self.append(&compressed)?;
+
+        let index_offset = self.offset;
+        let mut index = Encoder::default();
+        for entry in self.index.iter() {
+            index.u64(entry.offset);
+            index.u32(entry.len);
+            index.u8(entry.street);
+            index.u8(entry.node_type);
+        }
+        self.append(&index.buf)?;
+
+        let mut trailer = Encoder::default();
+        trailer.buf.extend_from_slice(MAGIC);
+        trailer.u16(FORMAT_VERSION);
+        trailer.u16(0);
+        trailer.u32(self.index.len() as u32);
+        trailer.u32(0);
+        trailer.u64(meta_offset);
+        trailer.u64(compressed.len() as u64);
+        trailer.u64(index_offset);
+        self.append(&trailer.buf)?;
+
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ResultSink for SolutionWriter<W> {
+    fn reserve(&mut self, street: u8, node_type: &NodeResultType) -> u32 {
+        self.index.push(IndexEntry {
+            offset: 0,
+            len: 0,
+            street,
+            node_type: node_type_tag(node_type),
+        });
+        self.index.len() as u32 - 1
+    }
+
+    fn write(&mut self, id: u32, node: &NodeResult, children: &[u32]) -> Result<(), SolverError> {
+        let mut encoder = Encoder::default();
+        encoder.node(node, children)?;
+        let compressed = compress(&encoder.buf)?;
+
+        let offset = self.offset;
+        self.append(&compressed)?;
+        let entry = &mut self.index[id as usize];
+        entry.offset = offset;
+        entry.len = compressed.len() as u32;
+        Ok(())
+    }
+}
+
+// works over anything that derefs to bytes, a memory map for files on disk or a vec for an object just downloaded.
+// only the header is read up front, nodes are decompressed as they're asked for
+pub struct SolutionReader<B> {
+    bytes: B,
+    version: u16,
+    node_count: usize,
+    meta_offset: usize,
+    meta_len: usize,
+    index_offset: usize,
+}
+
+impl SolutionReader<Mmap> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SolverError> {
+        let file = File::open(path)?;
+        // the file is only ever read through the map, anyone rewriting it underneath us gets a corrupt read at worst
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(map)
+    }
+}
+
+impl<B: AsRef<[u8]>> SolutionReader<B> {
+    pub fn from_bytes(bytes: B) -> Result<Self, SolverError> {
+        let data = bytes.as_ref();
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            return Err(SolverError::CorruptSolution(
+                "not a solution file".to_string(),
+            ));
+        }
+
+        let version = Decoder::new(&data[4..6]).u16()?;
+        if version > FORMAT_VERSION {
+            return Err(SolverError::CorruptSolution(format!(
+                "format version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            )));
+        }
+        let header = if version < 2 {
+            &data[..HEADER_LEN]
+        } else {
+            match data.len().checked_sub(HEADER_LEN) {
+                Some(start) if start >= HEADER_LEN && &data[start..start + 4] == MAGIC => {
+                    &data[start..]
+                }
+                _ => {
+                    return Err(SolverError::CorruptSolution(
+                        "the trailer is missing, the file was cut short".to_string(),
+                    ))
+                }
+            }
+        };
+
+        let mut header = Decoder::new(&header[6..]);
+        let _flags = header.u16()?;
+        let node_count = header.u32()? as usize;
+        let _reserved = header.u32()?;
+        let meta_offset = header.u64()? as usize;
+        let meta_len = header.u64()? as usize;
+        let index_offset = header.u64()? as usize;
+
+        let index_end = index_offset.checked_add(node_count.saturating_mul(INDEX_ENTRY_LEN));
+        if index_end.map_or(true, |end| end > data.len())
+            || meta_offset.saturating_add(meta_len) > data.len()
+        {
+            return Err(SolverError::CorruptSolution(
+                "sections run past the end of the file".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            bytes,
+            version,
+            node_count,
+            meta_offset,
+            meta_len,
+            index_offset,
+        })
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn metadata(&self) -> Result<SolutionMetadata, SolverError> {
+        let data = &self.bytes.as_ref()[self.meta_offset..self.meta_offset + self.meta_len];
+        Ok(serde_json::from_slice(&decompress(data)?)?)
+    }
+
+    pub fn node(&self, id: u32) -> Result<StoredNode, SolverError> {
+        let entry = self.entry(id)?;
+        let start = entry.offset as usize;
+        let data = self
+            .bytes
+            .as_ref()
+            .get(start..start + entry.len as usize)
+            .ok_or_else(|| {
+                SolverError::CorruptSolution(format!("node {} runs past the end of the file", id))
+            })?;
+
+        let (result, children) = Decoder::new(&decompress(data)?).node()?;
+        Ok(StoredNode {
+            id,
+            result,
+            children,
+        })
+    }
+
+    // answered from the index alone, nothing gets decompressed
+    pub fn street_node_ids(&self, street: u8) -> Result<Vec<u32>, SolverError> {
+        let action = node_type_tag(&NodeResultType::Action);
+        let mut ids = vec![];
+        for id in 0..self.node_count as u32 {
+            let entry = self.entry(id)?;
+            if entry.street == street && entry.node_type == action {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    // every action node on the street, in the same order as NodeResult::street_nodes
+    pub fn street_nodes(&self, street: u8) -> Result<Vec<StoredNode>, SolverError> {
+        self.street_node_ids(street)?
+            .into_iter()
+            .map(|id| self.node(id))
+            .collect()
+    }
+
+    // rebuilds the full tree below a node, subtree(0) gives back the node_results that were written
+    pub fn subtree(&self, id: u32) -> Result<NodeResult, SolverError> {
+        let stored = self.node(id)?;
+        let mut result = stored.result;
+        result.next_nodes = stored
+            .children
+            .iter()
+            .map(|child| self.subtree(*child))
+            .collect::<Result<_, _>>()?;
+        Ok(result)
+    }
+
+    fn entry(&self, id: u32) -> Result<IndexEntry, SolverError> {
+        if id as usize >= self.node_count {
+            return Err(SolverError::CorruptSolution(format!(
+                "node {} doesn't exist, the solution has {} nodes",
+                id, self.node_count
+            )));
+        }
+        let start = self.index_offset + id as usize * INDEX_ENTRY_LEN;
+        let mut decoder = Decoder::new(&self.bytes.as_ref()[start..start + INDEX_ENTRY_LEN]);
+        Ok(IndexEntry {
+            offset: decoder.u64()?,
+            len: decoder.u32()?,
+            street: decoder.u8()?,
+            node_type: decoder.u8()?,
+        })
+    }
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, SolverError> {
+    Ok(zstd::stream::encode_all(data, COMPRESSION_LEVEL)?)
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, SolverError> {
+    Ok(zstd::stream::decode_all(data)?)
+}
+
+fn node_type_tag(node_type: &NodeResultType) -> u8 {
+    match node_type {
+        NodeResultType::Action => 0,
+        NodeResultType::Chance => 1,
+        NodeResultType::Terminal => 2,
+    }
+}
+
+fn action_tag(action: ActionType) -> u8 {
+    match action {
+        ActionType::Fold => 0,
+        ActionType::Check => 1,
+        ActionType::Call => 2,
+        ActionType::Bet => 3,
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32s(&mut self, values: &[f32]) {
+        self.u32(values.len() as u32);
+        values.iter().for_each(|v| self.f32(*v));
+    }
+
+    fn bytes(&mut self, values: &[u8]) {
+        self.u32(values.len() as u32);
+        self.buf.extend_from_slice(values);
+    }
+
+    // a presence byte ahead of every optional field
+    fn present<T>(&mut self, value: &Option<T>) -> bool {
+        self.u8(value.is_some() as u8);
+        value.is_some()
+    }
+
+    fn combination(&mut self, label: &str) -> Result<(), SolverError> {
+        // stored as the two cards in label order, so the label comes back unchanged
+        self.buf.extend_from_slice(&parse_combination(label)?);
+        Ok(())
+    }
+
+    fn node(&mut self, node: &NodeResult, children: &[u32]) -> Result<(), SolverError> {
+        self.u8(node_type_tag(&node.node_type));
+        self.u8(node.street);
+        self.u8(node.player.unwrap_or(NO_PLAYER));
+
+        self.u32(children.len() as u32);
+        children.iter().for_each(|child| self.u32(*child));
+
+        if self.present(&node.action_list) {
+            let actions: Vec<u8> = node
+                .action_list
+                .iter()
+                .flatten()
+                .map(|a| action_tag(*a))
+                .collect();
+            self.bytes(&actions);
+        }
+        if self.present(&node.bet_sizings) {
+            self.f32s(node.bet_sizings.as_deref().unwrap_or_default());
+        }
+        if self.present(&node.next_cards) {
+            self.bytes(node.next_cards.as_deref().unwrap_or_default());
+        }
+
+        if self.present(&node.hand_actions) {
+            let hand_actions = node.hand_actions.as_deref().unwrap_or_default();
+            self.u32(hand_actions.len() as u32);
+            for hand in hand_actions.iter() {
+                self.combination(&hand.combination)?;
+                self.f32(hand.frequency);
+                self.f32s(&hand.action_frequency);
+            }
+        }
+
+        for values in [&node.oop_values, &node.ip_values] {
+            if self.present(values) {
+                self.player_values(values.as_ref().unwrap())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn player_values(&mut self, values: &PlayerNodeResult) -> Result<(), SolverError> {
+        self.u32(values.hands.len() as u32);
+        for hand in values.hands.iter() {
+            self.combination(&hand.combination)?;
+            if self.present(&hand.reach) {
+                self.f32(hand.reach.unwrap_or_default());
+            }
+            self.f32(hand.ev);
+            self.f32(hand.equity);
+            self.f32(hand.equity_realization);
+            if self.present(&hand.action_ev) {
+                self.f32s(hand.action_ev.as_deref().unwrap_or_default());
+            }
+        }
+
+        for value in [
+            values.range_ev,
+            values.range_equity,
+            values.range_equity_realization,
+        ] {
+            if self.present(&value) {
+                self.f32(value.unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SolverError> {
+        let taken = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| SolverError::CorruptSolution("node record is truncated".to_string()))?;
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, SolverError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SolverError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SolverError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SolverError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, SolverError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32s(&mut self) -> Result<Vec<f32>, SolverError> {
+        let len = self.u32()? as usize;
+        (0..len).map(|_| self.f32()).collect()
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, SolverError> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn optional<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, SolverError>,
+    ) -> Result<Option<T>, SolverError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+
+    fn combination(&mut self) -> Result<String, SolverError> {
+        let cards = self.take(2)?;
+        Ok(hand_to_string(&[cards[0], cards[1]]))
+    }
+
+    fn node(&mut self) -> Result<(NodeResult, Vec<u32>), SolverError> {
+        let node_type = match self.u8()? {
+            0 => NodeResultType::Action,
+            1 => NodeResultType::Chance,
+            2 => NodeResultType::Terminal,
+            tag => {
+                return Err(SolverError::CorruptSolution(format!(
+                    "unknown node type {}",
+                    tag
+                )))
+            }
+        };
+        let street = self.u8()?;
+        let player = Some(self.u8()?).filter(|p| *p != NO_PLAYER);
+
+        let num_children = self.u32()? as usize;
+        let children = (0..num_children)
+            .map(|_| self.u32())
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        let action_list = self.optional(|d| {
+            d.bytes()?
+                .into_iter()
+                .map(|tag| match tag {
+                    0 => Ok(ActionType::Fold),
+                    1 => Ok(ActionType::Check),
+                    2 => Ok(ActionType::Call),
+                    3 => Ok(ActionType::Bet),
+                    _ => Err(SolverError::CorruptSolution(format!(
+                        "unknown action {}",
+                        tag
+                    ))),
+                })
+                .collect()
+        })?;
+        let bet_sizings = self.optional(|d| d.f32s())?;
+        let next_cards = self.optional(|d| d.bytes())?;
+
+        let hand_actions = self.optional(|d| {
+            let len = d.u32()? as usize;
+            (0..len)
+                .map(|_| {
+                    Ok(CombinationActions {
+                        combination: d.combination()?,
+                        frequency: d.f32()?,
+                        action_frequency: d.f32s()?,
+                    })
+                })
+                .collect()
+        })?;
+        let oop_values = self.optional(|d| d.player_values())?;
+        let ip_values = self.optional(|d| d.player_values())?;
+
+        Ok((
+            NodeResult {
+                node_type,
+                street,
+                player,
+                action_list,
+                bet_sizings,
+                hand_actions,
+                oop_values,
+                ip_values,
+                next_cards,
+                next_nodes: vec![],
+            },
+            children,
+        ))
+    }
+
+    fn player_values(&mut self) -> Result<PlayerNodeResult, SolverError> {
+        let len = self.u32()? as usize;
+        let hands = (0..len)
+            .map(|_| {
+                Ok(CombinationValues {
+                    combination: self.combination()?,
+                    reach: self.optional(|d| d.f32())?,
+                    ev: self.f32()?,
+                    equity: self.f32()?,
+                    equity_realization: self.f32()?,
+                    action_ev: self.optional(|d| d.f32s())?,
+                })
+            })
+            .collect::<Result<Vec<_>, SolverError>>()?;
+
+        Ok(PlayerNodeResult {
+            hands,
+            range_ev: self.optional(|d| d.f32())?,
+            range_equity: self.optional(|d| d.f32())?,
+            range_equity_realization: self.optional(|d| d.f32())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::{test_params, trained_game_with};
+
+    fn solved(board: &str, bets: Vec<Vec<f32>>) -> Game {
+        trained_game_with(board, "QQ,JJ,AK", "QQ,TT,AK", test_params(bets), 1.0)
+    }
+
+    #[test]
+    fn test_round_trip_and_lazy_street_reads() {
+        let game = solved("as,kh,7c,2d", vec![vec![0.5]]);
+        let filter = ResultFilter::default();
+        let root = game.node_results(&filter).unwrap();
+        let reader = SolutionReader::from_bytes(encoded_solution(&game, &filter)).unwrap();
+
+        assert_eq!(reader.version(), FORMAT_VERSION);
+        assert_eq!(
+            reader.metadata().unwrap().starting_board,
+            game.metadata().unwrap().starting_board
+        );
+        assert_eq!(
+            serde_json::to_value(&reader.subtree(0).unwrap()).unwrap(),
+            serde_json::to_value(&root).unwrap()
+        );
+
+        let rivers = reader.street_nodes(3).unwrap();
+        let expected_rivers = root.street_nodes(3);
+        assert_eq!(rivers.len(), expected_rivers.len());
+        assert_eq!(
+            serde_json::to_value(&rivers[5].result.hand_actions).unwrap(),
+            serde_json::to_value(&expected_rivers[5].hand_actions).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_flop_solutions_export_every_street() {
+        let game = solved("as,kh,7c", vec![vec![]]);
+        let filter = ResultFilter::default();
+        let reader = SolutionReader::from_bytes(encoded_solution(&game, &filter)).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&reader.subtree(0).unwrap()).unwrap(),
+            serde_json::to_value(&game.node_results(&filter).unwrap()).unwrap()
+        );
+
+        // the export filter still applies, only the turn is written and the rivers are left out
+        let turn_only = ResultFilter {
+            max_street: Some(2),
+            runout: vec![],
+        };
+        let reader = SolutionReader::from_bytes(encoded_solution(&game, &turn_only)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&reader.subtree(0).unwrap()).unwrap(),
+            serde_json::to_value(&game.node_results(&turn_only).unwrap()).unwrap()
+        );
+        assert!(reader.street_node_ids(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reads_version_one_files() {
+        let game = solved("as,ah,ac,kd,2s", vec![vec![0.5]]);
+        let bytes = encoded_solution(&game, &ResultFilter::default());
+
+        // version 1 kept the trailer's fields in the header and ended at the index
+        let trailer = bytes.len() - HEADER_LEN;
+        let mut old = bytes[..trailer].to_vec();
+        old[..HEADER_LEN].copy_from_slice(&bytes[trailer..]);
+        old[4..6].copy_from_slice(&1u16.to_le_bytes());
+
+        let reader = SolutionReader::from_bytes(old).unwrap();
+        assert_eq!(reader.version(), 1);
+        assert_eq!(
+            serde_json::to_value(reader.subtree(0).unwrap()).unwrap(),
+            serde_json::to_value(game.node_results(&ResultFilter::default()).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_foreign_newer_and_truncated_files() {
+        assert!(SolutionReader::from_bytes(b"{\"nodeResults\":{}}".to_vec()).is_err());
+
+        let game = solved("as,ah,ac,kd,2s", vec![vec![0.5]]);
+        let mut bytes = encoded_solution(&game, &ResultFilter::default());
+        assert!(SolutionReader::from_bytes(bytes[..bytes.len() - 1].to_vec()).is_err());
+
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(SolutionReader::from_bytes(bytes).is_err());
+    }
+}
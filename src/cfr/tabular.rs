@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::aggregation::action_label;
+use super::game::GameResult;
+use super::solution_file::SolutionReader;
+use crate::error::SolverError;
+use crate::nodes::node::{NodeResult, NodeResultType};
+use crate::ranges::combination::Board;
+use crate::ranges::utility::number_to_card;
+
+pub const CSV_HEADER: &str = "action_sequence,board,player,combo,action,frequency,ev,reach";
+
+// one row per (action sequence, board, combo, action) at every action node. the sequence only holds actions, dealt
+// cards show up in the board column instead. frequency is how often the combo takes the action, reach how often it
+// gets to the node and ev the action's ev for the acting player, left empty when it wasn't exported.
+// rows are written as each node is visited so the table is never held in memory
+pub fn write_game_rows(result: &GameResult, out: impl Write) -> Result<(), SolverError> {
+    let mut out = std::io::BufWriter::new(out);
+    writeln!(out, "{}", CSV_HEADER)?;
+    walk_tree(
+        &result.node_results,
+        &mut vec![],
+        &mut board_cards(&result.starting_board),
+        &mut out,
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+// same rows as write_game_rows, decoding one node of a solution file at a time
+pub fn write_solution_rows<B: AsRef<[u8]>>(
+    reader: &SolutionReader<B>,
+    out: impl Write,
+) -> Result<(), SolverError> {
+    let board = reader.metadata()?.starting_board;
+    let mut out = std::io::BufWriter::new(out);
+    writeln!(out, "{}", CSV_HEADER)?;
+    walk_solution(reader, 0, &mut vec![], &mut board_cards(&board), &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn walk_tree(
+    node: &NodeResult,
+    sequence: &mut Vec<String>,
+    board: &mut Vec<u8>,
+    out: &mut impl Write,
+) -> Result<(), SolverError> {
+    match node.node_type {
+        NodeResultType::Terminal => {}
+        NodeResultType::Chance => {
+            let cards = node.next_cards.as_deref().unwrap_or_default();
+            for (card, next) in cards.iter().zip(node.next_nodes.iter()) {
+                board.push(*card);
+                walk_tree(next, sequence, board, out)?;
+                board.pop();
+            }
+        }
+        NodeResultType::Action => {
+            let labels = write_node_rows(node, sequence, board, out)?;
+            for (label, next) in labels.into_iter().zip(node.next_nodes.iter()) {
+                sequence.push(label);
+                walk_tree(next, sequence, board, out)?;
+                sequence.pop();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn walk_solution<B: AsRef<[u8]>>(
+    reader: &SolutionReader<B>,
+    id: u32,
+    sequence: &mut Vec<String>,
+    board: &mut Vec<u8>,
+    out: &mut impl Write,
+) -> Result<(), SolverError> {
+    let stored = reader.node(id)?;
+    let node = &stored.result;
+    match node.node_type {
+        NodeResultType::Terminal => {}
+        NodeResultType::Chance => {
+            let cards = node.next_cards.as_deref().unwrap_or_default();
+            for (card, next) in cards.iter().zip(stored.children.iter()) {
+                board.push(*card);
+                walk_solution(reader, *next, sequence, board, out)?;
+                board.pop();
+            }
+        }
+        NodeResultType::Action => {
+            let labels = write_node_rows(node, sequence, board, out)?;
+            for (label, next) in labels.into_iter().zip(stored.children.iter()) {
+                sequence.push(label);
+                walk_solution(reader, *next, sequence, board, out)?;
+                sequence.pop();
+            }
+        }
+    }
+    Ok(())
+}
+
+// writes the node's rows and gives back its action labels for the sequence of the nodes below it
+fn write_node_rows(
+    node: &NodeResult,
+    sequence: &[String],
+    board: &[u8],
+    out: &mut impl Write,
+) -> Result<Vec<String>, SolverError> {
+    let actions = node.action_list.clone().unwrap_or_default();
+    let sizings = node.bet_sizings.clone().unwrap_or_default();
+    let labels: Vec<String> = actions
+        .iter()
+        .zip(sizings.iter())
+        .map(|(action, sizing)| action_label(*action, *sizing))
+        .collect();
+
+    let values = match node.player {
+        Some(0) => node.oop_values.as_ref(),
+        _ => node.ip_values.as_ref(),
+    };
+    let action_evs: HashMap<&str, &Vec<f32>> = values
+        .iter()
+        .flat_map(|values| values.hands.iter())
+        .filter_map(|hand| {
+            hand.action_ev
+                .as_ref()
+                .map(|evs| (hand.combination.as_str(), evs))
+        })
+        .collect();
+
+    let sequence = sequence.join("-");
+    let board: String = board.iter().map(|card| number_to_card(*card)).collect();
+    let player = node.player.map_or(String::new(), |p| p.to_string());
+
+    for hand in node.hand_actions.iter().flatten() {
+        let evs = action_evs.get(hand.combination.as_str());
+        for (i, label) in labels.iter().enumerate() {
+            let ev = evs
+                .and_then(|evs| evs.get(i))
+                .map_or(String::new(), |ev| ev.to_string());
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                sequence,
+                board,
+                player,
+                hand.combination,
+                label,
+                hand.action_frequency[i],
+                ev,
+                hand.frequency
+            )?;
+        }
+    }
+    Ok(labels)
+}
+
+fn board_cards(board: &Board) -> Vec<u8> {
+    board.iter().copied().filter(|card| *card != 52).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::{trained_game, Game};
+    use crate::cfr::solution_file::encoded_solution;
+    use crate::nodes::node::ResultFilter;
+
+    fn river_game() -> Game {
+        trained_game("as,ah,ac,kd,2s", "QQ,KK", "QQ,KK,JJ")
+    }
+
+    fn river_result() -> GameResult {
+        river_game().results(&ResultFilter::default()).unwrap()
+    }
+
+    #[test]
+    fn test_rows_for_every_combo_and_action() {
+        let result = river_result();
+        let mut csv = vec![];
+        write_game_rows(&result, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+
+        // oop opens with check or bet for all nine combos
+        let root_rows: Vec<Vec<&str>> = lines
+            .map(|line| line.split(',').collect::<Vec<&str>>())
+            .filter(|row| row[0].is_empty())
+            .collect();
+        assert_eq!(root_rows.len(), 9 * 2);
+        for row in root_rows.iter() {
+            assert_eq!(row.len(), 8);
+            assert_eq!(row[1], "AsAhAcKd2s");
+            assert_eq!(row[2], "0");
+            assert!(row[6].parse::<f32>().is_ok());
+        }
+        assert!(csv.lines().any(|line| line.starts_with("X,")));
+    }
+
+    #[test]
+    fn test_solution_file_gives_the_same_rows() {
+        let game = river_game();
+        let mut from_tree = vec![];
+        write_game_rows(
+            &game.results(&ResultFilter::default()).unwrap(),
+            &mut from_tree,
+        )
+        .unwrap();
+
+        let reader =
+            SolutionReader::from_bytes(encoded_solution(&game, &ResultFilter::default())).unwrap();
+        let mut from_file = vec![];
+        write_solution_rows(&reader, &mut from_file).unwrap();
+
+        assert_eq!(from_tree, from_file);
+    }
+}
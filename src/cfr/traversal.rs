@@ -1,17 +1,36 @@
+use crate::error::SolverError;
 use crate::ranges::{
     combination::{Board, Combination},
     range_manager::{RangeManager, RangeManagers, DefaultRangeManager, IsomorphicRangeManager},
-    utility::{build_initial_suit_groups, build_player_specific_merged_range, construct_starting_range_from_string},
+    parser::{parse_player_range, NamedRanges, ParsedRange},
+    utility::{build_initial_suit_groups, build_player_specific_merged_range, starting_combinations},
 };
 
-pub fn build_traversal_from_ranges(board: Board, oop_range: &str, ip_range: &str) -> Traversal {
-    let merged = if oop_range.eq_ignore_ascii_case("random") || ip_range.eq_ignore_ascii_case("random") {
-        construct_starting_range_from_string("random".to_string(), &board)
-    } else {
-        construct_starting_range_from_string(format!("{},{}", oop_range, ip_range), &board)
-    };
-    let oop_combinations = build_player_specific_merged_range(oop_range.to_string(), &merged);
-    let ip_combinations = build_player_specific_merged_range(ip_range.to_string(), &merged);
+pub fn build_traversal_from_ranges(
+    board: Board,
+    oop_range: &str,
+    ip_range: &str,
+) -> Result<Traversal, SolverError> {
+    check_range_text("OOP", oop_range)?;
+    check_range_text("IP", ip_range)?;
+    let oop_parsed = parse_player_range("OOP", oop_range, &NamedRanges::new())?;
+    let ip_parsed = parse_player_range("IP", ip_range, &NamedRanges::new())?;
+
+    build_traversal_from_parsed_ranges(board, &oop_parsed, &ip_parsed)
+}
+
+// for ranges that never were text, like the reach weighted ranges a stored solution hands on to a later solve
+pub fn build_traversal_from_parsed_ranges(
+    board: Board,
+    oop_range: &ParsedRange,
+    ip_range: &ParsedRange,
+) -> Result<Traversal, SolverError> {
+    let merged = starting_combinations(&oop_range.union(ip_range), &board);
+    let oop_combinations = build_player_specific_merged_range(oop_range, &merged);
+    let ip_combinations = build_player_specific_merged_range(ip_range, &merged);
+
+    check_player_range("OOP", &oop_combinations)?;
+    check_player_range("IP", &ip_combinations)?;
 
     let sg = build_initial_suit_groups(&board);
     let mut iso = false;
@@ -32,7 +51,22 @@ pub fn build_traversal_from_ranges(board: Board, oop_range: &str, ip_range: &str
         RangeManagers::from(DefaultRangeManager::new(ip_combinations, board))
     };
 
-    Traversal::new(oop_rm, ip_rm)
+    Ok(Traversal::new(oop_rm, ip_rm))
+}
+
+fn check_range_text(player: &str, range_string: &str) -> Result<(), SolverError> {
+    if range_string.trim().is_empty() {
+        return Err(SolverError::InvalidRange(format!("{} range is empty", player)));
+    }
+    Ok(())
+}
+
+// the merged range keeps every combo either player holds, so a player's own combos are the ones with weight
+fn check_player_range(player: &str, combinations: &[Combination]) -> Result<(), SolverError> {
+    if combinations.iter().all(|combo| combo.combos <= 0.0) {
+        return Err(SolverError::EmptyRange(player.to_string()));
+    }
+    Ok(())
 }
 
 pub struct Traversal {
@@ -41,6 +75,10 @@ pub struct Traversal {
     pub traverser: u8,
     pub iteration: u32,
     pub persist_evs: bool,
+    // best response follows the traverser's average strategy instead, giving what the strategy itself is worth
+    pub follow_strategy: bool,
+    // persisted evs add the chips already in the pot, only when utilities are chips too
+    pub add_pot_share: bool,
 }
 
 impl Traversal {
@@ -51,6 +89,8 @@ impl Traversal {
             traverser: 0,
             iteration: 0,
             persist_evs: false,
+            follow_strategy: false,
+            add_pot_share: true,
         }
     }
 
@@ -75,11 +115,12 @@ impl Traversal {
         self.oop_rm.get_range_for_board(board).len()
     }
 
-    pub fn get_num_hands_for_player(&self, player: u8, board: &Board) -> usize {
+    // used while building the tree so that a board without ranges fails there instead of mid traversal
+    pub fn get_num_hands_for_player(&self, player: u8, board: &Board) -> Result<usize, SolverError> {
         if player == 1 {
-            return self.ip_rm.get_range_for_board(board).len();
+            return Ok(self.ip_rm.try_get_range_for_board(board)?.len());
         }
-        self.oop_rm.get_range_for_board(board).len()
+        Ok(self.oop_rm.try_get_range_for_board(board)?.len())
     }
 
     pub fn get_next_reach_probs(&self, new_board: &Board, opp_reach_probs: &[f32]) -> Vec<f32> {
@@ -111,3 +152,28 @@ impl Traversal {
         self.ip_rm.merge_canonical_utilities(board, utility)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::parse_board;
+
+    #[test]
+    fn test_range_removed_by_board_is_rejected() {
+        let board = parse_board("as,ah,ac,kd,2s").unwrap();
+        match build_traversal_from_ranges(board, "AA", "KK") {
+            Err(SolverError::EmptyRange(player)) => assert_eq!(player, "OOP"),
+            _ => panic!("expected OOP's range to be empty"),
+        }
+    }
+
+    #[test]
+    fn test_river_traversal_builds() {
+        let board = parse_board("as,ah,ac,kd,2s").unwrap();
+        let traversal = build_traversal_from_ranges(board, "QQ", "KK").unwrap();
+        assert_eq!(traversal.get_num_hands_for_player(0, &board).unwrap(), 6 + 3);
+        assert!(traversal
+            .get_num_hands_for_player(0, &parse_board("as,ah,ac").unwrap())
+            .is_err());
+    }
+}
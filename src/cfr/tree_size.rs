@@ -0,0 +1,277 @@
+use std::mem::size_of;
+
+use crate::cfr::game_params::GameParams;
+use crate::error::SolverError;
+use crate::nodes::chance_node::build_next;
+use crate::ranges::combination::{Board, Combination};
+use crate::ranges::utility::construct_starting_range_from_string;
+
+// rough fixed cost of a node in the tree (enum tag, stacks, vec headers) so that trees with many tiny nodes are
+// not underestimated
+const NODE_OVERHEAD_BYTES: usize = 160;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TreeSize {
+    pub action_nodes: usize,
+    pub chance_nodes: usize,
+    pub terminal_nodes: usize,
+    pub accumulator_floats: usize,
+    pub persisted_floats: usize,
+    pub range_bytes: usize,
+}
+
+impl TreeSize {
+    pub fn bytes(&self) -> usize {
+        (self.accumulator_floats + self.persisted_floats) * size_of::<f32>()
+            + (self.action_nodes + self.chance_nodes + self.terminal_nodes) * NODE_OVERHEAD_BYTES
+            + self.range_bytes
+    }
+
+    pub fn megabytes(&self) -> usize {
+        (self.bytes() + (1 << 20) - 1) >> 20
+    }
+}
+
+// walks the same bet tree as Game::construct_tree without allocating any of the node vectors, hand counts are
+// taken from the starting ranges and treated as an upper bound for every later street
+pub fn estimate_game_size(
+    board: &Board,
+    oop_range: &str,
+    ip_range: &str,
+    params: &GameParams,
+) -> Result<TreeSize, SolverError> {
+    let oop_hands = construct_starting_range_from_string(oop_range.to_string(), board)?.len();
+    let ip_hands = construct_starting_range_from_string(ip_range.to_string(), board)?.len();
+
+    Ok(estimate_tree_size(board, oop_hands, ip_hands, params))
+}
+
+pub fn estimate_tree_size(
+    board: &Board,
+    oop_hands: usize,
+    ip_hands: usize,
+    params: &GameParams,
+) -> TreeSize {
+    let mut estimator = TreeSizeEstimator {
+        params,
+        oop_hands,
+        ip_hands,
+        size: TreeSize::default(),
+    };
+
+    estimator.action_node(
+        0,
+        params.starting_pot,
+        params.effective_stack(),
+        params.effective_stack(),
+        0,
+        board,
+    );
+
+    estimator.size.range_bytes = estimate_range_bytes(board, oop_hands + ip_hands);
+    estimator.size
+}
+
+fn estimate_range_bytes(board: &Board, hands: usize) -> usize {
+    let per_board = hands * (size_of::<Combination>() + size_of::<usize>());
+    let boards = if board[3] == 52 {
+        1 + 49 + 49 * 48
+    } else if board[4] == 52 {
+        1 + 48
+    } else {
+        1
+    };
+    boards * per_board
+}
+
+struct TreeSizeEstimator<'a> {
+    params: &'a GameParams,
+    oop_hands: usize,
+    ip_hands: usize,
+    size: TreeSize,
+}
+
+impl<'a> TreeSizeEstimator<'a> {
+    fn action_node(
+        &mut self,
+        player_node: u8,
+        pot_size: f32,
+        ip_stack: f32,
+        oop_stack: f32,
+        bet_number: u8,
+        board: &Board,
+    ) {
+        let mut street = 3;
+        if board[3] == 52 {
+            street = 1;
+        } else if board[4] == 52 {
+            street = 2;
+        }
+
+        let mut num_actions = 0;
+        let last_bet_size = (ip_stack - oop_stack).abs();
+
+        if player_node == 1 || bet_number > 0 {
+            let call_stacks = ip_stack.min(oop_stack);
+            if street == 3 || call_stacks == 0.0 {
+                self.size.terminal_nodes += 1;
+            } else {
+                self.size.chance_nodes += 1;
+
+                let mut next_cards = vec![];
+                let mut next_weights = vec![];
+                build_next(board, &mut next_cards, &mut next_weights);
+
+                for card in next_cards {
+                    let mut new_board = *board;
+                    if street == 1 {
+                        new_board[3] = card;
+                    } else {
+                        new_board[4] = card;
+                    }
+                    self.action_node(
+                        0,
+                        pot_size + last_bet_size,
+                        call_stacks,
+                        call_stacks,
+                        0,
+                        &new_board,
+                    );
+                }
+            }
+            num_actions += 1;
+
+            if bet_number > 0 {
+                self.size.terminal_nodes += 1;
+                num_actions += 1;
+            }
+        } else {
+            self.action_node(1, pot_size, ip_stack, oop_stack, 0, board);
+            num_actions += 1;
+        }
+
+        if oop_stack > 0.0 && ip_stack > 0.0 {
+            let mut current_bets = self
+                .params
+                .get_current_bets(street, player_node, bet_number)
+                .to_vec();
+            if pot_size * self.params.all_in_cut_off >= ip_stack.max(oop_stack) {
+                current_bets.push(self.params.all_in_cut_off);
+            }
+
+            for bet_size in current_bets.iter() {
+                let sizing = bet_size * (pot_size + last_bet_size) + last_bet_size;
+
+                let final_bet_size = if player_node == 1 {
+                    let final_bet_size = ip_stack.min(sizing).min(oop_stack + last_bet_size);
+                    self.action_node(
+                        0,
+                        pot_size + final_bet_size,
+                        ip_stack - final_bet_size,
+                        oop_stack,
+                        bet_number + 1,
+                        board,
+                    );
+                    final_bet_size
+                } else {
+                    let final_bet_size = oop_stack.min(sizing).min(ip_stack + last_bet_size);
+                    self.action_node(
+                        1,
+                        pot_size + final_bet_size,
+                        ip_stack,
+                        oop_stack - final_bet_size,
+                        bet_number + 1,
+                        board,
+                    );
+                    final_bet_size
+                };
+                num_actions += 1;
+
+                if final_bet_size < sizing {
+                    break;
+                }
+            }
+        }
+
+        let num_hands = if player_node == 1 {
+            self.ip_hands
+        } else {
+            self.oop_hands
+        };
+
+        self.size.action_nodes += 1;
+        // regret and strategy accumulators
+        self.size.accumulator_floats += 2 * num_hands * num_actions;
+        // values kept once training ends, the acting player's ev per action plus ev, equity and reach for both players
+        self.size.persisted_floats +=
+            num_hands * num_actions + 3 * (self.oop_hands + self.ip_hands);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::card_to_number;
+
+    fn river_params(starting_stack: f32, bets: Vec<Vec<f32>>) -> GameParams {
+        GameParams::new(
+            1,
+            10.0,
+            starting_stack,
+            1.0,
+            0.75,
+            vec![vec![]],
+            vec![vec![]],
+            bets.clone(),
+            vec![vec![]],
+            vec![vec![]],
+            bets,
+        )
+    }
+
+    fn river_board() -> Board {
+        [
+            card_to_number("kc".to_string()).unwrap(),
+            card_to_number("7h".to_string()).unwrap(),
+            card_to_number("2h".to_string()).unwrap(),
+            card_to_number("3s".to_string()).unwrap(),
+            card_to_number("9d".to_string()).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_check_down_river_tree() {
+        let size = estimate_tree_size(&river_board(), 10, 20, &river_params(100.0, vec![vec![]]));
+
+        // oop check, ip check
+        assert_eq!(size.action_nodes, 2);
+        assert_eq!(size.terminal_nodes, 1);
+        assert_eq!(size.chance_nodes, 0);
+        assert_eq!(size.accumulator_floats, 2 * 10 + 2 * 20);
+        assert_eq!(size.persisted_floats, 10 + 20 + 2 * 3 * (10 + 20));
+    }
+
+    #[test]
+    fn test_all_in_river_tree() {
+        // a half pot bet is already all in, the all in cut off adds a second all in sizing
+        let size = estimate_tree_size(&river_board(), 10, 10, &river_params(5.0, vec![vec![0.5]]));
+
+        // oop root, ip after check and two all ins facing each player
+        assert_eq!(size.action_nodes, 6);
+        // ip check back plus a call and fold for each all in
+        assert_eq!(size.terminal_nodes, 9);
+        assert_eq!(size.accumulator_floats, 2 * 10 * (3 + 3 + 4 * 2));
+        assert_eq!(
+            size.persisted_floats,
+            10 * (3 + 3 + 4 * 2) + 6 * 3 * (10 + 10)
+        );
+        assert!(size.bytes() > (size.accumulator_floats + size.persisted_floats) * 4);
+    }
+
+    #[test]
+    fn test_unparseable_ranges_are_an_error() {
+        let params = river_params(100.0, vec![vec![]]);
+        assert!(estimate_game_size(&river_board(), "AA,KK", "QQ", &params).is_ok());
+        assert!(estimate_game_size(&river_board(), "AA,KKx", "QQ", &params).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::ranges::combination::Board;
+
+#[derive(Debug)]
+pub enum SolverError {
+    InvalidCard(String),
+    InvalidBoard(String),
+    InvalidRange(String),
+    EmptyRange(String),
+    InconsistentBetConfig(String),
+    UnknownBoard(Board),
+    InvalidMessage(String),
+    CorruptSolution(String),
+    BudgetExceeded { required_mb: usize, limit_mb: usize },
+    Cancelled,
+    Interrupted,
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    Storage(cloud_storage::Error),
+    Messaging(lapin::Error),
+    Runtime(String),
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::InvalidCard(card) => write!(
+                f,
+                "invalid card '{}', expected a rank (2-9, T, J, Q, K, A) followed by a suit (s, h, c, d)",
+                card
+            ),
+            SolverError::InvalidBoard(reason) => write!(f, "invalid board: {}", reason),
+            SolverError::InvalidRange(reason) => write!(f, "invalid range: {}", reason),
+            SolverError::EmptyRange(player) => write!(
+                f,
+                "{} range has no combinations left once the board cards are removed",
+                player
+            ),
+            SolverError::InconsistentBetConfig(reason) => {
+                write!(f, "inconsistent bet configuration: {}", reason)
+            }
+            SolverError::UnknownBoard(board) => {
+                write!(f, "no range was built for board {:?}", board)
+            }
+            SolverError::InvalidMessage(reason) => write!(f, "invalid message: {}", reason),
+            SolverError::CorruptSolution(reason) => write!(f, "unreadable solution file: {}", reason),
+            SolverError::BudgetExceeded {
+                required_mb,
+                limit_mb,
+            } => write!(
+                f,
+                "job needs an estimated {} MB but the solver memory limit is {} MB",
+                required_mb, limit_mb
+            ),
+            SolverError::Cancelled => write!(f, "job was cancelled"),
+            SolverError::Interrupted => write!(f, "job was interrupted by solver shutdown"),
+            SolverError::Json(e) => write!(f, "json error: {}", e),
+            SolverError::Io(e) => write!(f, "io error: {}", e),
+            SolverError::Storage(e) => write!(f, "storage error: {}", e),
+            SolverError::Messaging(e) => write!(f, "messaging error: {}", e),
+            SolverError::Runtime(reason) => write!(f, "runtime error: {}", reason),
+        }
+    }
+}
+
+impl SolverError {
+    // bad input fails the same way every time, only infrastructure problems are worth another attempt
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            SolverError::Storage(_) | SolverError::Messaging(_) | SolverError::Runtime(_)
+        )
+    }
+}
+
+impl std::error::Error for SolverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SolverError::Json(e) => Some(e),
+            SolverError::Io(e) => Some(e),
+            SolverError::Storage(e) => Some(e),
+            SolverError::Messaging(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for SolverError {
+    fn from(e: serde_json::Error) -> Self {
+        SolverError::Json(e)
+    }
+}
+
+impl From<std::io::Error> for SolverError {
+    fn from(e: std::io::Error) -> Self {
+        SolverError::Io(e)
+    }
+}
+
+impl From<cloud_storage::Error> for SolverError {
+    fn from(e: cloud_storage::Error) -> Self {
+        SolverError::Storage(e)
+    }
+}
+
+impl From<lapin::Error> for SolverError {
+    fn from(e: lapin::Error) -> Self {
+        SolverError::Messaging(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for SolverError {
+    fn from(e: std::str::Utf8Error) -> Self {
+        SolverError::InvalidMessage(format!("message body is not utf-8: {}", e))
+    }
+}
+
+impl From<tokio::task::JoinError> for SolverError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        SolverError::Runtime(format!("solver task failed: {}", e))
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for SolverError {
+    fn from(e: rayon::ThreadPoolBuildError) -> Self {
+        SolverError::Runtime(format!("could not build solver thread pool: {}", e))
+    }
+}
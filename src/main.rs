@@ -3,6 +3,7 @@
 #![feature(test)]
 #![feature(stdsimd)]
 mod cfr;
+mod error;
 mod nodes;
 mod ranges;
 mod messaging;
@@ -18,10 +19,23 @@ extern crate rayon;
 
 use std::error::Error;
 use crate::{
-    cfr::{game_params::GameParams, game::run_trainer},
+    cfr::{
+        batch::{run_batch, BatchConfig},
+        cancellation::CancellationToken,
+        game_params::GameParams,
+        game::run_trainer,
+        multiway::{MultiwayConfig, MultiwayGame},
+        preflop::PreflopGame,
+        preflop_params::PreflopParams,
+        propagation::subgame_start,
+        solution_file::SolutionReader,
+    },
+    nodes::node::ResultFilter,
     ranges::{
         combination::Board,
-        utility::card_to_number,
+        equity::range_equity,
+        interchange::import_range,
+        utility::{card_to_number, parse_board},
     },
 };
 use tracing::info;
@@ -30,10 +44,27 @@ use crate::messaging::run_consumer;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     tracing_subscriber::fmt::init();
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("equity") {
+        return run_equity_command(&args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("batch") {
+        return run_batch_command(&args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("multiway") {
+        return run_multiway_command(&args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("preflop") {
+        return run_preflop_command(&args[2..]);
+    }
+    if args.get(1).map(String::as_str) == Some("subgame") {
+        return run_subgame_command(&args[2..]);
+    }
+
     let board: Board = [
-        card_to_number("qs".to_string()),
-        card_to_number("jh".to_string()),
-        card_to_number("2h".to_string()),
+        card_to_number("qs".to_string())?,
+        card_to_number("jh".to_string())?,
+        card_to_number("2h".to_string())?,
         52,
         52,
     ];
@@ -61,10 +92,101 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // let oop = "AA,KK,QQ,JJ,TT,99,88,77,66,55,44,33,22,A2s+,K2s+,Q2s+,JTs,J9s,J8s,J7s,T9s,T8s,T7s,T6s,98s,97s,96s,87s,86s,76s,65s,A5o+,KTo+,QTo+";
     // let ip = "AA,KK,QQ,JJ,TT,99,88,77,66,55,44,33,22,A2s+,K2s+,Q2s+,JTs,J9s,J8s,J7s,T9s,T8s,T7s,T6s,98s,97s,96s,87s,86s,76s,65s,A5o+,KTo+,QTo+";
-    match run_trainer(board, oop, ip, params, "btn_bb_srp").await {
+    match run_trainer(board, oop, ip, params, None, rayon::current_num_threads(), ResultFilter::default(), CancellationToken::new(), |_| {}).await {
         Ok(_) => {}
         Err(e) => info!("Error during execution {}", e)
     }
     // run_consumer().await;
     Ok(())
 }
+
+// poker-solver equity <board> <range> <range> [buckets], e.g. poker-solver equity qs,jh,2h "AA,KK" "QQ+,AKs"
+fn run_equity_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 3 {
+        return Err("usage: poker-solver equity <board> <oop range> <ip range> [buckets]".into());
+    }
+    let board = parse_board(&args[0])?;
+    let buckets = match args.get(3) {
+        Some(buckets) => buckets.parse()?,
+        None => 20,
+    };
+
+    let report = range_equity(board, &import_range(&args[1])?, &import_range(&args[2])?, buckets)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+// poker-solver batch <config json> <output dir>, run it again with the same directory to resume
+fn run_batch_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 2 {
+        return Err("usage: poker-solver batch <config json> <output dir>".into());
+    }
+    let config: BatchConfig = serde_json::from_slice(&std::fs::read(&args[0])?)?;
+    let flops = config.weighted_flops()?;
+
+    let report = run_batch(&config, &flops, std::path::Path::new(&args[1]), &CancellationToken::new())?;
+    info!(
+        "Solved {} flops, OOP EV {} IP EV {}",
+        report.flops.len(),
+        report.oop_ev,
+        report.ip_ev
+    );
+    Ok(())
+}
+
+// poker-solver preflop <config json> [line], with a line such as "raise 2.5,call" it prints the pot, stacks and ranges
+// that line brings to the flop, otherwise the whole preflop strategy
+fn run_preflop_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.is_empty() {
+        return Err("usage: poker-solver preflop <config json> [line]".into());
+    }
+    let params: PreflopParams = serde_json::from_slice(&std::fs::read(&args[0])?)?;
+    let mut game = PreflopGame::new(params)?;
+    game.train(0.35, &CancellationToken::new())?;
+
+    match args.get(1) {
+        Some(line) => {
+            let line: Vec<&str> = line.split(',').map(str::trim).collect();
+            println!("{}", serde_json::to_string_pretty(&game.postflop_start(&line)?)?);
+        }
+        None => println!("{}", serde_json::to_string_pretty(&game.results())?),
+    }
+    Ok(())
+}
+
+// poker-solver subgame <solution file> <line> <params json> [oop|ip], solves again from where a line such as X-B5-C-Td
+// leaves the stored solution, with the bet sizes in params and both players' ranges as they reach that node. naming a
+// player re-solves safely for them, their opponent keeps what they had against the blueprint
+fn run_subgame_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 3 {
+        return Err("usage: poker-solver subgame <solution file> <line> <params json> [oop|ip]".into());
+    }
+    let reader = SolutionReader::open(&args[0])?;
+    let template: GameParams = serde_json::from_slice(&std::fs::read(&args[2])?)?;
+    let start = subgame_start(&reader, &args[1])?;
+
+    let mut game = match args.get(3).map(|player| player.to_lowercase()).as_deref() {
+        None => start.game(&template)?,
+        Some("oop") => start.safe_game(&template, 0)?,
+        Some("ip") => start.safe_game(&template, 1)?,
+        Some(other) => return Err(format!("expected oop or ip to protect, got '{}'", other).into()),
+    };
+    game.train(0.35)?;
+    println!("{}", serde_json::to_string_pretty(&game.results(&ResultFilter::default())?)?);
+    Ok(())
+}
+
+// poker-solver multiway <config json>, experimental three way solving, prints the strategies and how far each player
+// is from a best response
+fn run_multiway_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.is_empty() {
+        return Err("usage: poker-solver multiway <config json>".into());
+    }
+    let config: MultiwayConfig = serde_json::from_slice(&std::fs::read(&args[0])?)?;
+    let ranges: Vec<&str> = config.ranges.iter().map(String::as_str).collect();
+    let mut game = MultiwayGame::new(&config.board, &ranges, config.params)?;
+    game.train(config.iterations, &CancellationToken::new())?;
+
+    println!("{}", serde_json::to_string_pretty(&game.results())?);
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+use crate::cfr::cancellation::CancellationToken;
+use crate::error::SolverError;
+
+const DEFAULT_MAX_JOBS: usize = 2;
+const DEFAULT_MEMORY_LIMIT_MB: usize = 8192;
+
+// holding a reservation keeps its memory and job slot taken, both are released on drop
+pub struct Reservation {
+    _memory: OwnedSemaphorePermit,
+    _job: OwnedSemaphorePermit,
+    pub threads: usize,
+}
+
+pub struct ResourceBudget {
+    max_jobs: usize,
+    memory_limit_mb: usize,
+    threads_per_job: usize,
+    memory: Arc<Semaphore>,
+    jobs: Arc<Semaphore>,
+}
+
+impl ResourceBudget {
+    pub fn new(max_jobs: usize, memory_limit_mb: usize, total_threads: usize) -> Self {
+        let max_jobs = max_jobs.max(1);
+        Self {
+            max_jobs,
+            memory_limit_mb,
+            threads_per_job: (total_threads / max_jobs).max(1),
+            memory: Arc::new(Semaphore::new(memory_limit_mb)),
+            jobs: Arc::new(Semaphore::new(max_jobs)),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let max_jobs = env_or("SOLVER_MAX_JOBS", DEFAULT_MAX_JOBS);
+        let memory_limit_mb = env_or("SOLVER_MEMORY_LIMIT_MB", DEFAULT_MEMORY_LIMIT_MB);
+        let total_threads = env_or(
+            "SOLVER_THREADS",
+            std::thread::available_parallelism().map_or(1, |n| n.get()),
+        );
+
+        Self::new(max_jobs, memory_limit_mb, total_threads)
+    }
+
+    // one extra delivery per slot is prefetched so the next job can be sized while the current ones train
+    pub fn prefetch_count(&self) -> u16 {
+        (self.max_jobs * 2).min(usize::from(u16::MAX)) as u16
+    }
+
+    pub fn threads_per_job(&self) -> usize {
+        self.threads_per_job
+    }
+
+    // waits until enough memory and then a job slot are free, so a job waiting for memory doesn't sit on a slot.
+    // jobs that could never fit are rejected up front, and a cancel or shutdown ends the wait
+    pub async fn reserve(
+        &self,
+        required_mb: usize,
+        cancel: &CancellationToken,
+    ) -> Result<Reservation, SolverError> {
+        if required_mb > self.memory_limit_mb {
+            return Err(SolverError::BudgetExceeded {
+                required_mb,
+                limit_mb: self.memory_limit_mb,
+            });
+        }
+
+        let acquire = async {
+            let memory = self
+                .memory
+                .clone()
+                .acquire_many_owned(required_mb.max(1) as u32)
+                .await
+                .map_err(closed)?;
+            let job = self.jobs.clone().acquire_owned().await.map_err(closed)?;
+
+            Ok(Reservation {
+                _memory: memory,
+                _job: job,
+                threads: self.threads_per_job,
+            })
+        };
+
+        tokio::select! {
+            reservation = acquire => reservation,
+            stopped = cancel.stopped() => Err(stopped),
+        }
+    }
+
+    pub fn available_memory_mb(&self) -> usize {
+        self.memory.available_permits()
+    }
+}
+
+fn closed(_: AcquireError) -> SolverError {
+    SolverError::Runtime("resource budget was closed".to_string())
+}
+
+fn env_or(key: &str, default: usize) -> usize {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reserve_releases_on_drop() {
+        let budget = ResourceBudget::new(2, 100, 8);
+        assert_eq!(budget.threads_per_job(), 4);
+
+        let reservation = budget.reserve(60, &CancellationToken::new()).await.unwrap();
+        assert_eq!(reservation.threads, 4);
+        assert_eq!(budget.available_memory_mb(), 40);
+
+        drop(reservation);
+        assert_eq!(budget.available_memory_mb(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_rejects_oversized_jobs() {
+        let budget = ResourceBudget::new(1, 100, 1);
+        match budget.reserve(101, &CancellationToken::new()).await {
+            Err(SolverError::BudgetExceeded {
+                required_mb,
+                limit_mb,
+            }) => {
+                assert_eq!(required_mb, 101);
+                assert_eq!(limit_mb, 100);
+            }
+            _ => panic!("expected the job to be rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_memory_leaves_the_job_slot_free() {
+        let budget = ResourceBudget::new(2, 100, 2);
+        let token = CancellationToken::new();
+        let _running = budget.reserve(70, &token).await.unwrap();
+
+        let waiting = budget.reserve(60, &token);
+        let poll_once = async {
+            tokio::task::yield_now().await;
+            assert_eq!(budget.jobs.available_permits(), 1);
+        };
+        tokio::select! {
+            _ = waiting => panic!("there isn't memory for the second job"),
+            _ = poll_once => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_ends_the_wait() {
+        let budget = ResourceBudget::new(1, 100, 1);
+        let _running = budget
+            .reserve(100, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        let token = CancellationToken::new();
+        let cancel = async {
+            tokio::task::yield_now().await;
+            token.cancel();
+        };
+        let (waiting, _) = tokio::join!(budget.reserve(10, &token), cancel);
+        assert!(matches!(waiting, Err(SolverError::Cancelled)));
+        assert_eq!(budget.available_memory_mb(), 0);
+    }
+}
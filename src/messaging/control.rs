@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::str;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures_lite::StreamExt;
+use lapin::message::Delivery;
+use lapin::options::{
+    BasicConsumeOptions, BasicPublishOptions, ExchangeDeclareOptions, QueueBindOptions,
+    QueueDeclareOptions,
+};
+use lapin::types::{FieldTable, ShortString};
+use lapin::{BasicProperties, Channel, ExchangeKind};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::cfr::cancellation::CancellationToken;
+use crate::error::SolverError;
+
+const DEFAULT_CONTROL_EXCHANGE: &str = "solver.control";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelRequest {
+    pub job_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReply {
+    pub job_id: String,
+    pub cancelled: bool,
+}
+
+// tokens for every job this worker has picked up, so a cancel request or shutdown can reach the training loop.
+// entries are keyed by registration rather than job id, a redelivered or duplicate job id is running twice and a
+// cancel has to reach both
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<u64, (String, CancellationToken)>>,
+    next_registration: AtomicU64,
+    shutdown: watch::Sender<bool>,
+}
+
+// removes the job from the registry once the delivery has been dealt with
+pub struct JobGuard {
+    registry: Arc<JobRegistry>,
+    registration: u64,
+    pub token: CancellationToken,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.registry
+            .jobs
+            .lock()
+            .unwrap()
+            .remove(&self.registration);
+    }
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            next_registration: AtomicU64::new(0),
+            shutdown,
+        }
+    }
+
+    // jobs registered after shutdown started are interrupted straight away so they go back to the queue untouched
+    pub fn register(self: &Arc<Self>, job_id: &str) -> JobGuard {
+        let token = CancellationToken::new();
+        if self.is_shutting_down() {
+            token.interrupt();
+        }
+        let registration = self.next_registration.fetch_add(1, Ordering::Relaxed);
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(registration, (job_id.to_string(), token.clone()));
+
+        JobGuard {
+            registry: self.clone(),
+            registration,
+            token,
+        }
+    }
+
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let mut cancelled = false;
+        for (_, token) in self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(id, _)| id == job_id)
+        {
+            token.cancel();
+            cancelled = true;
+        }
+        cancelled
+    }
+
+    pub fn begin_shutdown(&self) {
+        self.shutdown.send_replace(true);
+        self.interrupt_all();
+    }
+
+    // stops every running job without shutting down, jobs registered later still run
+    pub fn interrupt_all(&self) {
+        for (_, token) in self.jobs.lock().unwrap().values() {
+            token.interrupt();
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    pub async fn wait_for_shutdown(&self) {
+        let mut shutdown = self.shutdown.subscribe();
+        while !*shutdown.borrow() {
+            if shutdown.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// resolves on SIGTERM or ctrl-c, whichever comes first
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Could not listen for SIGTERM, only ctrl-c will stop the solver: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Could not listen for ctrl-c: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+// cancel requests are fanned out to every worker, each binds its own exclusive queue and only the worker running
+// the job acts on it and replies
+pub async fn start_control_consumer(
+    channel: Channel,
+    registry: Arc<JobRegistry>,
+) -> Result<(), SolverError> {
+    let exchange = std::env::var("SOLVER_CONTROL_EXCHANGE")
+        .unwrap_or_else(|_| DEFAULT_CONTROL_EXCHANGE.into());
+    channel
+        .exchange_declare(
+            &exchange,
+            ExchangeKind::Fanout,
+            ExchangeDeclareOptions {
+                durable: true,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                auto_delete: true,
+                ..QueueDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    channel
+        .queue_bind(
+            queue.name().as_str(),
+            &exchange,
+            "",
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+    let mut consumer = channel
+        .basic_consume(
+            queue.name().as_str(),
+            "solver-control",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..BasicConsumeOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    tokio::spawn(async move {
+        while let Some(delivery) = consumer.next().await {
+            match delivery {
+                Ok(delivery) => {
+                    if let Err(e) = handle_cancel(&channel, &registry, &delivery).await {
+                        error!("Error handling cancel request {}", e);
+                    }
+                }
+                Err(e) => error!("Error consuming control message {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn handle_cancel(
+    channel: &Channel,
+    registry: &JobRegistry,
+    delivery: &Delivery,
+) -> Result<(), SolverError> {
+    let request: CancelRequest = serde_json::from_str(str::from_utf8(&delivery.data)?)?;
+    if !registry.cancel(&request.job_id) {
+        return Ok(());
+    }
+    info!("Cancelling job {}", request.job_id);
+
+    if let Some(reply_to) = delivery.properties.reply_to() {
+        let mut properties =
+            BasicProperties::default().with_content_type(ShortString::from("application/json"));
+        if let Some(correlation_id) = delivery.properties.correlation_id() {
+            properties = properties.with_correlation_id(correlation_id.clone());
+        }
+        let reply = CancelReply {
+            job_id: request.job_id,
+            cancelled: true,
+        };
+        channel
+            .basic_publish(
+                "",
+                reply_to.as_str(),
+                BasicPublishOptions::default(),
+                &serde_json::to_vec(&reply)?,
+                properties,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_reaches_registered_job() {
+        let registry = Arc::new(JobRegistry::new());
+        let guard = registry.register("job-1");
+
+        assert!(!registry.cancel("job-2"));
+        assert!(registry.cancel("job-1"));
+        assert!(matches!(guard.token.check(), Err(SolverError::Cancelled)));
+
+        drop(guard);
+        assert!(!registry.cancel("job-1"));
+    }
+
+    #[test]
+    fn test_duplicate_job_ids_stay_separate() {
+        let registry = Arc::new(JobRegistry::new());
+        let first = registry.register("job-1");
+        let second = registry.register("job-1");
+
+        // the first one finishing leaves the second cancellable
+        drop(first);
+        assert!(registry.cancel("job-1"));
+        assert!(matches!(second.token.check(), Err(SolverError::Cancelled)));
+
+        let third = registry.register("job-2");
+        let fourth = registry.register("job-2");
+        assert!(registry.cancel("job-2"));
+        assert!(third.token.check().is_err() && fourth.token.check().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_interrupts_running_and_new_jobs() {
+        let registry = Arc::new(JobRegistry::new());
+        let running = registry.register("running");
+
+        registry.begin_shutdown();
+        registry.wait_for_shutdown().await;
+        let late = registry.register("late");
+
+        assert!(matches!(running.token.check(), Err(SolverError::Interrupted)));
+        assert!(matches!(late.token.check(), Err(SolverError::Interrupted)));
+    }
+
+    #[test]
+    fn test_interrupt_all_leaves_later_jobs_running() {
+        let registry = Arc::new(JobRegistry::new());
+        let running = registry.register("running");
+
+        registry.interrupt_all();
+        let next = registry.register("next");
+
+        assert!(matches!(running.token.check(), Err(SolverError::Interrupted)));
+        assert!(next.token.check().is_ok());
+        assert!(!registry.is_shutting_down());
+    }
+}
@@ -0,0 +1,223 @@
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicPublishOptions};
+use lapin::types::{AMQPValue, FieldTable, LongString, ShortString};
+use lapin::{BasicProperties, Channel};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::error::SolverError;
+
+pub const ATTEMPTS_HEADER: &str = "x-solver-attempts";
+pub const ERROR_HEADER: &str = "x-solver-error";
+
+const DEFAULT_QUEUE: &str = "sims";
+const DEFAULT_DEAD_LETTER_QUEUE: &str = "sims.dead";
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+pub struct FailurePolicy {
+    pub queue: String,
+    pub dead_letter_queue: String,
+    pub error_queue: Option<String>,
+    pub max_attempts: u32,
+}
+
+impl FailurePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            queue: std::env::var("SOLVER_QUEUE").unwrap_or_else(|_| DEFAULT_QUEUE.into()),
+            dead_letter_queue: std::env::var("SOLVER_DEAD_LETTER_QUEUE")
+                .unwrap_or_else(|_| DEFAULT_DEAD_LETTER_QUEUE.into()),
+            error_queue: std::env::var("SOLVER_ERROR_QUEUE").ok(),
+            max_attempts: std::env::var("SOLVER_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+                .max(1),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReply {
+    pub error: String,
+    pub attempts: u32,
+    pub dead_lettered: bool,
+}
+
+// retries go back onto the work queue as a fresh message carrying the attempt count, anything that can't succeed
+// or has run out of attempts is parked on the dead letter queue and the sender gets an error reply. the original
+// delivery is always acked so one bad job can't hold up the channel. returns whether the job will be retried
+pub async fn handle_failure(
+    channel: &Channel,
+    policy: &FailurePolicy,
+    delivery: &Delivery,
+    cause: &SolverError,
+) -> Result<bool, SolverError> {
+    let attempts = previous_attempts(delivery) + 1;
+    let properties = delivery
+        .properties
+        .clone()
+        .with_headers(failure_headers(delivery, attempts, cause));
+
+    let (queue, retrying) = next_queue(policy, cause, attempts);
+    if retrying {
+        warn!(
+            "Attempt {} of {} failed, retrying: {}",
+            attempts, policy.max_attempts, cause
+        );
+    } else {
+        error!(
+            "Giving up after {} attempt(s), moving message to {}: {}",
+            attempts, queue, cause
+        );
+    }
+    channel
+        .basic_publish(
+            "",
+            queue,
+            BasicPublishOptions::default(),
+            &delivery.data,
+            properties,
+        )
+        .await?;
+
+    if !retrying {
+        let reply = ErrorReply {
+            error: cause.to_string(),
+            attempts,
+            dead_lettered: true,
+        };
+        publish_error_reply(channel, policy, delivery, &reply).await?;
+    }
+
+    delivery.ack(BasicAckOptions::default()).await?;
+    Ok(retrying)
+}
+
+async fn publish_error_reply(
+    channel: &Channel,
+    policy: &FailurePolicy,
+    delivery: &Delivery,
+    reply: &ErrorReply,
+) -> Result<(), SolverError> {
+    let routing_key = match (delivery.properties.reply_to(), &policy.error_queue) {
+        (Some(reply_to), _) => reply_to.as_str().to_string(),
+        (None, Some(error_queue)) => error_queue.clone(),
+        (None, None) => return Ok(()),
+    };
+
+    let mut properties =
+        BasicProperties::default().with_content_type(ShortString::from("application/json"));
+    if let Some(correlation_id) = delivery.properties.correlation_id() {
+        properties = properties.with_correlation_id(correlation_id.clone());
+    }
+
+    channel
+        .basic_publish(
+            "",
+            &routing_key,
+            BasicPublishOptions::default(),
+            &serde_json::to_vec(reply)?,
+            properties,
+        )
+        .await?;
+    Ok(())
+}
+
+// the work queue while the job can still succeed and has attempts left, otherwise the dead letter queue
+fn next_queue<'a>(
+    policy: &'a FailurePolicy,
+    cause: &SolverError,
+    attempts: u32,
+) -> (&'a str, bool) {
+    if cause.is_retryable() && attempts < policy.max_attempts {
+        (&policy.queue, true)
+    } else {
+        (&policy.dead_letter_queue, false)
+    }
+}
+
+pub fn previous_attempts(delivery: &Delivery) -> u32 {
+    attempts_header(delivery.properties.headers().as_ref())
+}
+
+// a missing header or one of a type we don't write counts as no earlier attempts
+fn attempts_header(headers: Option<&FieldTable>) -> u32 {
+    headers
+        .and_then(|headers| {
+            headers
+                .inner()
+                .iter()
+                .find(|(key, _)| key.as_str() == ATTEMPTS_HEADER)
+                .map(|(_, value)| value.clone())
+        })
+        .and_then(|value| match value {
+            AMQPValue::LongUInt(n) => Some(n),
+            AMQPValue::LongInt(n) => Some(n.max(0) as u32),
+            AMQPValue::LongLongInt(n) => Some(n.max(0) as u32),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+fn failure_headers(delivery: &Delivery, attempts: u32, cause: &SolverError) -> FieldTable {
+    let mut headers = delivery.properties.headers().clone().unwrap_or_default();
+    headers.insert(ATTEMPTS_HEADER.into(), AMQPValue::LongUInt(attempts));
+    headers.insert(
+        ERROR_HEADER.into(),
+        AMQPValue::LongString(LongString::from(cause.to_string())),
+    );
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> FailurePolicy {
+        FailurePolicy {
+            queue: DEFAULT_QUEUE.to_string(),
+            dead_letter_queue: DEFAULT_DEAD_LETTER_QUEUE.to_string(),
+            error_queue: None,
+            max_attempts: 3,
+        }
+    }
+
+    fn headers(value: AMQPValue) -> FieldTable {
+        let mut headers = FieldTable::default();
+        headers.insert(ATTEMPTS_HEADER.into(), value);
+        headers
+    }
+
+    #[test]
+    fn test_attempts_header() {
+        assert_eq!(attempts_header(None), 0);
+        assert_eq!(attempts_header(Some(&FieldTable::default())), 0);
+        assert_eq!(attempts_header(Some(&headers(AMQPValue::LongUInt(2)))), 2);
+        assert_eq!(
+            attempts_header(Some(&headers(AMQPValue::LongLongInt(3)))),
+            3
+        );
+        assert_eq!(attempts_header(Some(&headers(AMQPValue::LongInt(-1)))), 0);
+        assert_eq!(
+            attempts_header(Some(&headers(AMQPValue::LongString(LongString::from("2"))))),
+            0
+        );
+    }
+
+    #[test]
+    fn test_retry_or_dead_letter() {
+        let policy = policy();
+        let retryable = SolverError::Runtime("broker went away".to_string());
+
+        assert_eq!(next_queue(&policy, &retryable, 1), ("sims", true));
+        assert_eq!(next_queue(&policy, &retryable, 2), ("sims", true));
+        // the last attempt allowed has been used up
+        assert_eq!(next_queue(&policy, &retryable, 3), ("sims.dead", false));
+
+        // bad input goes straight to the dead letter queue
+        let invalid = SolverError::InvalidBoard("two cards".to_string());
+        assert_eq!(next_queue(&policy, &invalid, 1), ("sims.dead", false));
+    }
+}
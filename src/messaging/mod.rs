@@ -1,17 +1,55 @@
+pub mod budget;
+pub mod control;
+pub mod dead_letter;
+pub mod status;
+
 use futures_lite::StreamExt;
-use lapin::{options::*, types::FieldTable, Connection, ConnectionProperties};
+use lapin::{options::*, types::FieldTable, Channel, Connection, ConnectionProperties};
 use serde::{Deserialize, Serialize};
 use std::str;
+use std::sync::Arc;
 use lapin::message::Delivery;
 use tracing::{error, info};
 use std::{thread, time::Duration};
-use crate::{Board, card_to_number, GameParams};
+use crate::GameParams;
+use crate::cfr::cancellation::CancellationToken;
 use crate::cfr::game::run_trainer;
+use crate::cfr::payoff::PayoffModel;
+use crate::nodes::node::ResultFilter;
+use crate::ranges::utility::{card_to_number, parse_board};
+use crate::error::SolverError;
+use crate::ranges::interchange::{import_range, resolve_named_ranges};
+use crate::ranges::parser::NamedRanges;
+use crate::ranges::validation::validate_inputs;
+use crate::cfr::tree_size::estimate_game_size;
+use crate::messaging::budget::ResourceBudget;
+use crate::messaging::dead_letter::{handle_failure, FailurePolicy};
+use crate::messaging::status::{JobStatus, StatusPublisher};
+use crate::messaging::control::{shutdown_signal, start_control_consumer, JobRegistry};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+enum ConsumerExit {
+    StreamClosed,
+    Shutdown,
+}
 
 pub async fn run_consumer() {
+    let registry = Arc::new(JobRegistry::new());
+    let signal_registry = registry.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutdown requested, interrupting running jobs");
+        signal_registry.begin_shutdown();
+    });
+
     loop {
-        match build_and_run_consumer().await {
-            Ok(_) => {}
+        match build_and_run_consumer(&registry).await {
+            Ok(ConsumerExit::Shutdown) => {
+                info!("All jobs settled, consumer stopped");
+                return;
+            }
+            Ok(ConsumerExit::StreamClosed) => {}
             Err(e) => {
                 error!("Error while running consumer, retrying {}", e);
                 thread::sleep(Duration::from_millis(5000));
@@ -24,12 +62,15 @@ pub async fn run_consumer() {
 #[derive(Default, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct SolutionConfig {
+    pub job_id: Option<String>,
     pub bucket_name: String,
     pub board: String,
     pub oop_range: String,
     pub ip_range: String,
     pub starting_pot: f32,
     pub starting_stack: f32,
+    pub oop_stack: Option<f32>,
+    pub ip_stack: Option<f32>,
     pub all_in_cut_off: f32,
     pub default_bets: Option<Vec<Vec<f32>>>,
     pub default_bet: f32,
@@ -39,9 +80,13 @@ struct SolutionConfig {
     pub oop_turn_bets: Option<Vec<Vec<f32>>>,
     pub ip_river_bets: Option<Vec<Vec<f32>>>,
     pub oop_river_bets: Option<Vec<Vec<f32>>>,
+    pub payoff_model: Option<PayoffModel>,
+    pub named_ranges: Option<NamedRanges>,
+    pub export_max_street: Option<u8>,
+    pub export_runout: Option<String>,
 }
 
-async fn build_and_run_consumer() -> Result<(), Box<dyn std::error::Error>> {
+async fn build_and_run_consumer(registry: &Arc<JobRegistry>) -> Result<ConsumerExit, SolverError> {
     let addr = std::env::var("AMQP_ADDR").unwrap_or_else(|_| "amqp://127.0.0.1:5672/%2f".into());
     info!("{}", addr);
     let connection_props = ConnectionProperties::default()
@@ -49,11 +94,27 @@ async fn build_and_run_consumer() -> Result<(), Box<dyn std::error::Error>> {
         .with_reactor(tokio_reactor_trait::Tokio);
     let conn = Connection::connect(&addr, connection_props).await?;
     let channel = conn.create_channel().await?;
-    channel.basic_qos(1, BasicQosOptions::default()).await?;
+    let budget = Arc::new(ResourceBudget::from_env());
+    let policy = Arc::new(FailurePolicy::from_env());
+    let status = StatusPublisher::from_env(channel.clone()).await?;
+    channel
+        .basic_qos(budget.prefetch_count(), BasicQosOptions::default())
+        .await?;
+    channel
+        .queue_declare(
+            &policy.dead_letter_queue,
+            QueueDeclareOptions {
+                durable: true,
+                ..QueueDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+    start_control_consumer(conn.create_channel().await?, registry.clone()).await?;
 
     let mut consumer = channel
         .basic_consume(
-            "sims",
+            &policy.queue,
             "solver",
             BasicConsumeOptions::default(),
             FieldTable::default(),
@@ -61,49 +122,251 @@ async fn build_and_run_consumer() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     info!("rmq consumer connected, waiting for messages");
-    while let Some(delivery) = consumer.next().await {
-        match delivery {
-            Ok(delivery) => process_delivery(delivery).await?,
-            Err(e) => error!("Error consuming next {}", e),
+    let mut jobs = JoinSet::new();
+    let exit = loop {
+        tokio::select! {
+            _ = registry.wait_for_shutdown() => break ConsumerExit::Shutdown,
+            Some(_) = jobs.join_next(), if !jobs.is_empty() => {}
+            delivery = consumer.next() => match delivery {
+                Some(Ok(delivery)) => {
+                    let budget = budget.clone();
+                    let policy = policy.clone();
+                    let channel = channel.clone();
+                    let status = status.clone();
+                    let registry = registry.clone();
+                    jobs.spawn(async move {
+                        handle_delivery(delivery, &channel, &policy, &budget, &status, &registry).await
+                    });
+                }
+                Some(Err(e)) => error!("Error consuming next {}", e),
+                None => break ConsumerExit::StreamClosed,
+            }
+        }
+    };
+
+    match exit {
+        // stop taking work, then let the running jobs notice the interruption and hand their deliveries back
+        ConsumerExit::Shutdown => {
+            channel
+                .basic_cancel("solver", BasicCancelOptions::default())
+                .await?;
+        }
+        // dropping the jobs would abort them while their trainers keep running, stop and wait for them like a
+        // shutdown so their memory and threads are free before the next consumer takes work
+        ConsumerExit::StreamClosed => {
+            info!("Delivery stream closed, interrupting running jobs");
+            registry.interrupt_all();
+        }
+    }
+    info!("Waiting for {} in flight job(s) to settle", jobs.len());
+    while jobs.join_next().await.is_some() {}
+    Ok(exit)
+}
+
+async fn handle_delivery(
+    delivery: Delivery,
+    channel: &Channel,
+    policy: &FailurePolicy,
+    budget: &ResourceBudget,
+    status: &StatusPublisher,
+    registry: &Arc<JobRegistry>,
+) {
+    let config = parse_config(&delivery);
+    let job_id = job_id(&delivery, config.as_ref().ok());
+    let guard = registry.register(&job_id);
+    let mut ack_sent = false;
+    let result = match config {
+        Ok(config) => {
+            process_delivery(&delivery, config, &job_id, &guard.token, budget, status, &mut ack_sent)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    drop(guard);
+
+    if let Err(cause) = result {
+        // the job finished and only its ack failed, settling it again would ack twice and requeue a finished job
+        if ack_sent {
+            error!("Could not ack finished job {}: {}", job_id, cause);
+            return;
+        }
+        let settled = match cause {
+            SolverError::Cancelled => delivery
+                .ack(BasicAckOptions::default())
+                .await
+                .map(|_| JobStatus::Cancelled)
+                .map_err(SolverError::from),
+            SolverError::Interrupted => delivery
+                .nack(BasicNackOptions { requeue: true, ..BasicNackOptions::default() })
+                .await
+                .map(|_| JobStatus::Failed { reason: cause.to_string(), retrying: true })
+                .map_err(SolverError::from),
+            _ => handle_failure(channel, policy, &delivery, &cause)
+                .await
+                .map(|retrying| JobStatus::Failed { reason: cause.to_string(), retrying }),
+        };
+
+        match settled {
+            Ok(job_status) => status.publish(&job_id, job_status).await,
+            // leave the delivery unacked, the broker hands it out again once the channel closes
+            Err(e) => error!("Error handling failed delivery {}: {}", cause, e),
         }
     }
-    Ok(())
 }
 
-async fn process_delivery(delivery: Delivery) -> Result<(), Box<dyn std::error::Error>> {
-    let data = str::from_utf8(&delivery.data).expect("can't convert message to string");
-    let p: SolutionConfig = serde_json::from_str(data)?;
+// runout is written like a board, e.g. "7h" for a turn or "7h,2c" for a turn and river
+fn result_filter(p: &SolutionConfig) -> Result<ResultFilter, SolverError> {
+    let runout = match p.export_runout.as_deref() {
+        Some(runout) if !runout.trim().is_empty() => runout
+            .split(',')
+            .map(|card| card_to_number(card.to_string()))
+            .collect::<Result<Vec<u8>, SolverError>>()?,
+        _ => vec![],
+    };
+    if runout.len() > 2 {
+        return Err(SolverError::InvalidBoard(format!(
+            "export runout '{}' has more than a turn and river card",
+            p.export_runout.as_deref().unwrap_or_default()
+        )));
+    }
+    if let Some(street) = p.export_max_street {
+        if !(1..=3).contains(&street) {
+            return Err(SolverError::InvalidMessage(format!(
+                "export max street {} should be 1 (flop), 2 (turn) or 3 (river)",
+                street
+            )));
+        }
+    }
+
+    Ok(ResultFilter {
+        max_street: p.export_max_street,
+        runout,
+    })
+}
 
-    info!("received msg: {:?}", p);
+fn parse_config(delivery: &Delivery) -> Result<SolutionConfig, SolverError> {
+    let data = str::from_utf8(&delivery.data)?;
+    Ok(serde_json::from_str(data)?)
+}
 
-    let b: Vec<u8> = p
-        .board
-        .split(',')
-        .map(|x| card_to_number(x.to_string()))
-        .collect();
+// callers should send a job id, otherwise fall back to the correlation id and finally the delivery tag so status
+// events for a message that couldn't even be parsed still have a key
+fn job_id(delivery: &Delivery, config: Option<&SolutionConfig>) -> String {
+    config
+        .and_then(|p| p.job_id.clone())
+        .or_else(|| {
+            delivery
+                .properties
+                .correlation_id()
+                .as_ref()
+                .map(|id| id.as_str().to_string())
+        })
+        .unwrap_or_else(|| format!("delivery-{}", delivery.delivery_tag))
+}
 
-    let board: Board = [b[0], b[1], b[2], 52, 52];
+async fn process_delivery(
+    delivery: &Delivery,
+    p: SolutionConfig,
+    job_id: &str,
+    cancel: &CancellationToken,
+    budget: &ResourceBudget,
+    status: &StatusPublisher,
+    ack_sent: &mut bool,
+) -> Result<(), SolverError> {
+    info!("received msg for job {}: {:?}", job_id, p);
 
-    run_trainer(
+    // ranges may come straight from other solvers, bring them into our own syntax before anything reads them
+    let names = p.named_ranges.clone().unwrap_or_default();
+    let starting_board = parse_board(&p.board)?;
+    let oop_range = import_range(&p.oop_range)?;
+    let oop_range = resolve_named_ranges("OOP", &oop_range, &names, &starting_board)?;
+    let ip_range = import_range(&p.ip_range)?;
+    let ip_range = resolve_named_ranges("IP", &ip_range, &names, &starting_board)?;
+    let report = validate_inputs(&p.board, &oop_range, &ip_range)?;
+    info!(
+        "OOP range has {} combos ({} weighted, {} removed by the board), IP range has {} combos ({} weighted, {} removed by the board)",
+        report.oop.combos,
+        report.oop.weighted_combos,
+        report.oop.removed_by_board,
+        report.ip.combos,
+        report.ip.weighted_combos,
+        report.ip.removed_by_board
+    );
+    let board = report.board;
+    let filter = result_filter(&p)?;
+
+    let mut params = GameParams::new(
+        1,
+        p.starting_pot,
+        p.starting_stack,
+        p.all_in_cut_off,
+        p.default_bet,
+        p.oop_flop_bets.unwrap_or_else(|| vec![vec![]]),
+        p.oop_turn_bets.unwrap_or_else(|| vec![vec![]]),
+        p.oop_river_bets.unwrap_or_else(|| vec![vec![]]),
+        p.ip_flop_bets.unwrap_or_else(|| vec![vec![]]),
+        p.ip_turn_bets.unwrap_or_else(|| vec![vec![]]),
+        p.ip_river_bets.unwrap_or_else(|| vec![vec![]]),
+    );
+    params.oop_stack = p.oop_stack;
+    params.ip_stack = p.ip_stack;
+    params.payoff_model = p.payoff_model.unwrap_or_default();
+
+    params.validate()?;
+
+    let size = estimate_game_size(&board, &oop_range, &ip_range, &params)?;
+    info!("estimated tree size {:?}, {} MB", size, size.megabytes());
+
+    status
+        .publish(job_id, JobStatus::Accepted { estimated_mb: size.megabytes() })
+        .await;
+    let reservation = budget.reserve(size.megabytes(), cancel).await?;
+    // the job may have been cancelled while it waited for room
+    cancel.check()?;
+    status
+        .publish(job_id, JobStatus::Started { threads: reservation.threads })
+        .await;
+    info!(
+        "starting solve for board {} on {} threads, {} MB of budget left",
+        p.board,
+        reservation.threads,
+        budget.available_memory_mb()
+    );
+
+    // progress is reported from the blocking training thread, forward it to the publisher from here
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+    let progress_status = status.clone();
+    let progress_job_id = job_id.to_string();
+    let forward_progress = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            progress_status
+                .publish(&progress_job_id, JobStatus::from(progress))
+                .await;
+        }
+    });
+
+    let result = run_trainer(
         board,
-        &p.oop_range,
-        &p.ip_range,
-        GameParams::new(
-            1,
-            p.starting_pot,
-            p.starting_stack,
-            p.all_in_cut_off,
-            p.default_bet,
-            p.oop_flop_bets.unwrap_or_else(|| vec![vec![]]),
-            p.oop_turn_bets.unwrap_or_else(|| vec![vec![]]),
-            p.oop_river_bets.unwrap_or_else(|| vec![vec![]]),
-            p.ip_flop_bets.unwrap_or_else(|| vec![vec![]]),
-            p.ip_turn_bets.unwrap_or_else(|| vec![vec![]]),
-            p.ip_river_bets.unwrap_or_else(|| vec![vec![]]),
-        ),
-        p.bucket_name.as_ref(),
+        &oop_range,
+        &ip_range,
+        params,
+        Some(p.bucket_name.as_ref()),
+        reservation.threads,
+        filter,
+        cancel.clone(),
+        move |progress| {
+            let _ = progress_tx.send(progress);
+        },
     )
-        .await?;
+        .await;
+    drop(reservation);
+    forward_progress.await?;
+    let object = result?;
+
+    *ack_sent = true;
     delivery.ack(BasicAckOptions::default()).await?;
+    status
+        .publish(job_id, JobStatus::Done { bucket: p.bucket_name, object })
+        .await;
     Ok(())
 }
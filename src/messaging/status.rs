@@ -0,0 +1,165 @@
+use lapin::options::{BasicPublishOptions, ExchangeDeclareOptions};
+use lapin::types::{FieldTable, ShortString};
+use lapin::{BasicProperties, Channel, ExchangeKind};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::cfr::game::TrainingProgress;
+use crate::error::SolverError;
+
+const DEFAULT_STATUS_EXCHANGE: &str = "solver.status";
+
+// accepted as soon as the job is sized, a job can then wait for memory before it is started
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum JobStatus {
+    Accepted {
+        #[serde(rename = "estimatedMb")]
+        estimated_mb: usize,
+    },
+    Started {
+        threads: usize,
+    },
+    Solving {
+        iteration: u32,
+        exploitability: f32,
+        #[serde(rename = "targetExploitability")]
+        target_exploitability: f32,
+    },
+    Done {
+        bucket: String,
+        object: String,
+    },
+    Failed {
+        reason: String,
+        retrying: bool,
+    },
+    Cancelled,
+}
+
+impl From<TrainingProgress> for JobStatus {
+    fn from(progress: TrainingProgress) -> Self {
+        JobStatus::Solving {
+            iteration: progress.iteration,
+            exploitability: progress.exploitability,
+            target_exploitability: progress.target_exploitability,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEvent {
+    pub job_id: String,
+    #[serde(flatten)]
+    pub status: JobStatus,
+}
+
+// events go to a topic exchange with the job id as routing key, so a caller can bind a queue to just its own job
+#[derive(Clone)]
+pub struct StatusPublisher {
+    channel: Channel,
+    exchange: String,
+}
+
+impl StatusPublisher {
+    pub async fn from_env(channel: Channel) -> Result<Self, SolverError> {
+        let exchange = std::env::var("SOLVER_STATUS_EXCHANGE")
+            .unwrap_or_else(|_| DEFAULT_STATUS_EXCHANGE.into());
+        channel
+            .exchange_declare(
+                &exchange,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..ExchangeDeclareOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        Ok(Self { channel, exchange })
+    }
+
+    // status updates are best effort, a broker hiccup here shouldn't fail a solve that is otherwise fine
+    pub async fn publish(&self, job_id: &str, status: JobStatus) {
+        let event = JobEvent {
+            job_id: job_id.to_string(),
+            status,
+        };
+        if let Err(e) = self.try_publish(&event).await {
+            warn!("Could not publish status for job {}: {}", job_id, e);
+        }
+    }
+
+    async fn try_publish(&self, event: &JobEvent) -> Result<(), SolverError> {
+        let properties = BasicProperties::default()
+            .with_content_type(ShortString::from("application/json"))
+            .with_correlation_id(ShortString::from(event.job_id.clone()));
+
+        self.channel
+            .basic_publish(
+                &self.exchange,
+                &event.job_id,
+                BasicPublishOptions::default(),
+                &serde_json::to_vec(event)?,
+                properties,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_shape() {
+        let event = JobEvent {
+            job_id: "qsjh2h".to_string(),
+            status: JobStatus::Done {
+                bucket: "btn_bb_srp".to_string(),
+                object: "QsJh2h.sol".to_string(),
+            },
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "jobId": "qsjh2h",
+                "status": "done",
+                "bucket": "btn_bb_srp",
+                "object": "QsJh2h.sol",
+            })
+        );
+    }
+
+    #[test]
+    fn test_progress_becomes_solving() {
+        let status = JobStatus::from(TrainingProgress {
+            iteration: 50,
+            exploitability: 1.2,
+            target_exploitability: 0.35,
+        });
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["status"], "solving");
+        assert_eq!(json["iteration"], 50);
+        assert!(json.get("targetExploitability").is_some());
+    }
+
+    #[test]
+    fn test_waiting_and_started_are_separate_events() {
+        let accepted = serde_json::to_value(&JobStatus::Accepted { estimated_mb: 512 }).unwrap();
+        assert_eq!(accepted["status"], "accepted");
+        assert_eq!(accepted["estimatedMb"], 512);
+
+        let started = serde_json::to_value(&JobStatus::Started { threads: 4 }).unwrap();
+        assert_eq!(
+            started,
+            serde_json::json!({"status": "started", "threads": 4})
+        );
+    }
+}
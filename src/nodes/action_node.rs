@@ -1,5 +1,14 @@
 use super::node::{CfrNode, Node};
-use crate::nodes::node::{NodeResult, NodeResultType};
+use crate::nodes::all_in_showdown_node::runout_equities;
+use crate::error::SolverError;
+use crate::nodes::node::{
+    ActionType, CombinationActions, CombinationValues, NodeResult, NodeResultType,
+    PlayerNodeResult, ResultFilter, ResultSink,
+};
+use crate::ranges::combination::Combination;
+use crate::ranges::range_manager::RangeManager;
+use crate::ranges::utility::{board_street, canonical_positions, combination_label};
+use crate::nodes::terminal_node::terminal_utility;
 use crate::{cfr::traversal::Traversal, ranges::combination::Board};
 #[cfg(all(target_arch = "aarch64"))]
 use std::arch::aarch64::*;
@@ -17,7 +26,15 @@ pub struct ActionNode {
     next_nodes: Vec<Node>,
     regret_accumulator: Vec<f32>,
     strategy_accumulator: Vec<f32>,
-    node_ev: Option<Vec<f32>>,
+    hand_values: [Option<HandValues>; 2],
+    reach: [Option<Vec<f32>>; 2],
+}
+
+// filled in by the final best response pass, once with each player as the traverser
+struct HandValues {
+    action_ev: Option<Vec<f32>>,
+    ev: Vec<f32>,
+    equity: Vec<f32>,
 }
 
 impl CfrNode for ActionNode {
@@ -44,13 +61,27 @@ impl CfrNode for ActionNode {
     ) -> Vec<f32> {
         if self.player_node == traversal.traverser {
             let mut best_ev = vec![0.0; self.num_hands];
-            let mut node_evs = vec![];
+            let mut action_evs = vec![];
+            let followed_strategy = if traversal.follow_strategy {
+                Some(self.get_average_strategy())
+            } else {
+                None
+            };
             for action in 0..self.num_actions {
                 let next_ev =
                     self.next_nodes[action].best_response(traversal, op_reach_prob, board);
 
                 if traversal.persist_evs {
-                    node_evs.extend_from_slice(&next_ev)
+                    action_evs.extend_from_slice(&next_ev)
+                }
+                if let Some(strategy) = &followed_strategy {
+                    let action_offset = action * self.num_hands;
+                    best_ev
+                        .iter_mut()
+                        .zip(strategy[action_offset..].iter())
+                        .zip(next_ev.iter())
+                        .for_each(|((best, strategy), next)| *best += strategy * next);
+                    continue;
                 }
                 best_ev
                     .iter_mut()
@@ -62,31 +93,21 @@ impl CfrNode for ActionNode {
                     });
             }
             if traversal.persist_evs {
-                let opp_hands = traversal.get_range_for_opponent(board);
-                let mut card_removal = [0.0; 52];
-                let mut probability_sum = 0.0;
-
-                op_reach_prob
-                    .iter()
-                    .zip(opp_hands.iter())
-                    .for_each(|(prob, hand)| {
-                        if *prob > 0.0 {
-                            probability_sum += prob;
-
-                            card_removal[usize::from(hand.hand[0])] += prob;
-                            card_removal[usize::from(hand.hand[1])] += prob;
-                        }
-                    });
+                self.normalize_evs(traversal, op_reach_prob, board, &mut action_evs);
 
-                for (i, ev) in node_evs.iter_mut().enumerate() {
-                    *ev /= probability_sum
-                        - card_removal[usize::from(opp_hands[i % opp_hands.len()].hand[0])]
-                        - card_removal[usize::from(opp_hands[i % opp_hands.len()].hand[1])]
-                        + op_reach_prob[i % opp_hands.len()];
-                    *ev += self.pot_size;
+                let average_strategy = self.get_average_strategy();
+                let mut ev = vec![0.0; self.num_hands];
+                for action in 0..self.num_actions {
+                    let action_offset = action * self.num_hands;
+                    ev.iter_mut()
+                        .zip(average_strategy[action_offset..].iter())
+                        .zip(action_evs[action_offset..].iter())
+                        .for_each(|((ev, strategy), action_ev)| {
+                            *ev += strategy * action_ev;
+                        });
                 }
 
-                self.node_ev = Some(node_evs)
+                self.persist_values(traversal, op_reach_prob, board, Some(action_evs), ev);
             }
             best_ev
         } else {
@@ -115,26 +136,82 @@ impl CfrNode for ActionNode {
                         *node += *action;
                     });
             }
+
+            if traversal.persist_evs {
+                let mut ev = node_ev.clone();
+                self.normalize_evs(traversal, op_reach_prob, board, &mut ev);
+                self.persist_values(traversal, op_reach_prob, board, None, ev);
+            }
             node_ev
         }
     }
 
-    fn output_results(&self) -> Option<NodeResult> {
+    fn output_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+    ) -> Option<NodeResult> {
+        let street = board_street(board);
+
         Some(NodeResult {
-            node_type: NodeResultType::Action,
-            node_strategy: Some(self.get_average_strategy()),
-            node_ev: self.node_ev.clone(),
-            next_cards: None,
             next_nodes: self
                 .next_nodes
                 .iter()
-                .filter_map(|node| node.output_results())
+                .map(|node| {
+                    node.output_results(traversal, board, filter)
+                        .unwrap_or_else(|| NodeResult::terminal(street))
+                })
                 .collect(),
+            ..self.result(traversal, board, street)
         })
     }
+
+    fn export_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+        sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        let street = board_street(board);
+        let id = sink.reserve(street, &NodeResultType::Action);
+
+        let mut children = Vec::with_capacity(self.next_nodes.len());
+        for node in self.next_nodes.iter() {
+            let child = match node.export_results(traversal, board, filter, sink)? {
+                Some(child) => child,
+                None => {
+                    let child = sink.reserve(street, &NodeResultType::Terminal);
+                    sink.write(child, &NodeResult::terminal(street), &[])?;
+                    child
+                }
+            };
+            children.push(child);
+        }
+
+        sink.write(id, &self.result(traversal, board, street), &children)?;
+        Ok(Some(id))
+    }
 }
 
 impl ActionNode {
+    // everything but the next nodes, those are filled in or exported by the caller
+    fn result(&self, traversal: &Traversal, board: &Board, street: u8) -> NodeResult {
+        NodeResult {
+            node_type: NodeResultType::Action,
+            street,
+            player: Some(self.player_node),
+            action_list: Some(self.actions().iter().map(|(action, _)| *action).collect()),
+            bet_sizings: Some(self.actions().iter().map(|(_, sizing)| *sizing).collect()),
+            hand_actions: Some(self.hand_actions(traversal, board)),
+            oop_values: self.player_result(traversal, board, 0),
+            ip_values: self.player_result(traversal, board, 1),
+            next_cards: None,
+            next_nodes: vec![],
+        }
+    }
+
     pub fn new(
         player_node: u8,
         num_hands: usize,
@@ -152,7 +229,8 @@ impl ActionNode {
             next_nodes: vec![],
             regret_accumulator: vec![],
             strategy_accumulator: vec![],
-            node_ev: None,
+            hand_values: [None, None],
+            reach: [None, None],
         }
     }
 
@@ -166,6 +244,170 @@ impl ActionNode {
         self.next_nodes.push(child);
     }
 
+    // hands one child on and drops the rest of the node, for taking a node back out of a wrapper around it
+    pub fn into_child(mut self, index: usize) -> Node {
+        self.next_nodes.swap_remove(index)
+    }
+
+    // what the player has already put in, so utilities relative to the start of the hand become a share of this pot
+    fn pot_share(&self, player: u8) -> f32 {
+        let (own_stack, other_stack) = if player == 0 {
+            (self.oop_stack, self.ip_stack)
+        } else {
+            (self.ip_stack, self.oop_stack)
+        };
+        (self.pot_size + other_stack - own_stack) / 2.0
+    }
+
+    // best response values are summed over the opponent's reach, divide by the reach each hand isn't blocked from.
+    // evs may hold several actions back to back
+    fn normalize_evs(
+        &self,
+        traversal: &Traversal,
+        op_reach_prob: &[f32],
+        board: &Board,
+        evs: &mut [f32],
+    ) {
+        let opp_hands = traversal.get_range_for_opponent(board);
+        let unblocked = terminal_utility(1.0, op_reach_prob, opp_hands);
+        let pot_share = if traversal.add_pot_share {
+            self.pot_share(traversal.traverser)
+        } else {
+            0.0
+        };
+
+        for (i, ev) in evs.iter_mut().enumerate() {
+            let reach = unblocked[i % unblocked.len()];
+            *ev = if reach > 0.0 { *ev / reach + pot_share } else { 0.0 };
+        }
+    }
+
+    // the opponent's reach here is exactly their range at this node, so it is kept for when they are the traverser.
+    // equity needs both ranges, so both players get theirs from one walk over the runouts in the second pass
+    fn persist_values(
+        &mut self,
+        traversal: &Traversal,
+        op_reach_prob: &[f32],
+        board: &Board,
+        action_ev: Option<Vec<f32>>,
+        ev: Vec<f32>,
+    ) {
+        let traverser = usize::from(traversal.traverser);
+        self.hand_values[traverser] = Some(HandValues {
+            action_ev,
+            ev,
+            equity: vec![],
+        });
+        self.reach[traverser ^ 1] = Some(op_reach_prob.to_vec());
+
+        if let [Some(oop_reach), Some(ip_reach)] = &self.reach {
+            let mut equities =
+                runout_equities(traversal, &[ip_reach.as_slice(), oop_reach.as_slice()], board);
+            for (player, equity) in equities.drain(..).enumerate() {
+                if let Some(values) = self.hand_values[player].as_mut() {
+                    values.equity = equity;
+                }
+            }
+        }
+    }
+
+    // children are added as check or call, then fold when facing a bet, then bets smallest first. sizings are the
+    // chips the action puts in
+    fn actions(&self) -> Vec<(ActionType, f32)> {
+        let facing_bet = self.ip_stack != self.oop_stack;
+        self.next_nodes
+            .iter()
+            .map(|node| match node {
+                Node::TerminalNode(_) => (ActionType::Fold, 0.0),
+                Node::ActionNode(next) if next.pot_size > self.pot_size => {
+                    (ActionType::Bet, next.pot_size - self.pot_size)
+                }
+                _ if facing_bet => (ActionType::Call, (self.ip_stack - self.oop_stack).abs()),
+                _ => (ActionType::Check, 0.0),
+            })
+            .collect()
+    }
+
+    // one entry per combo in the acting player's range, isomorphic combos take the results of their canonical combo
+    fn hand_actions(&self, traversal: &Traversal, board: &Board) -> Vec<CombinationActions> {
+        let hands = player_range(traversal, self.player_node, board);
+        let canonical = canonical_positions(hands);
+        let average_strategy = self.get_average_strategy();
+        let reach = self.reach[usize::from(self.player_node)].as_ref();
+
+        hands
+            .iter()
+            .zip(canonical.iter())
+            .filter(|(hand, _)| hand.combos > 0.0)
+            .map(|(hand, &canon)| CombinationActions {
+                combination: combination_label(&hand.hand),
+                frequency: reach.map_or(0.0, |reach| reach[canon]),
+                action_frequency: (0..self.num_actions)
+                    .map(|action| average_strategy[action * self.num_hands + canon])
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn player_result(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        player: u8,
+    ) -> Option<PlayerNodeResult> {
+        let values = self.hand_values[usize::from(player)].as_ref()?;
+        let reach = self.reach[usize::from(player)].as_ref();
+        let hands = player_range(traversal, player, board);
+        let canonical = canonical_positions(hands);
+
+        let combinations = hands
+            .iter()
+            .zip(canonical.iter())
+            .filter(|(hand, _)| hand.combos > 0.0)
+            .map(|(hand, &canon)| {
+                let ev = values.ev[canon];
+                // only filled once both players have been the traverser
+                let equity = values.equity.get(canon).copied().unwrap_or_default();
+                CombinationValues {
+                    combination: combination_label(&hand.hand),
+                    reach: reach.map(|reach| reach[canon]),
+                    ev,
+                    equity,
+                    equity_realization: realization(ev, equity, self.pot_size),
+                    action_ev: values.action_ev.as_ref().map(|action_ev| {
+                        (0..self.num_actions)
+                            .map(|action| action_ev[action * hands.len() + canon])
+                            .collect()
+                    }),
+                }
+            })
+            .collect();
+
+        let (range_ev, range_equity) = match reach {
+            Some(reach) => {
+                let total: f32 = reach.iter().sum();
+                if total > 0.0 {
+                    let weighted = |values: &[f32]| -> f32 {
+                        values.iter().zip(reach.iter()).map(|(v, r)| v * r).sum::<f32>() / total
+                    };
+                    (Some(weighted(&values.ev)), Some(weighted(&values.equity)))
+                } else {
+                    (None, None)
+                }
+            }
+            None => (None, None),
+        };
+
+        Some(PlayerNodeResult {
+            hands: combinations,
+            range_ev,
+            range_equity,
+            range_equity_realization: range_ev
+                .zip(range_equity)
+                .map(|(ev, equity)| realization(ev, equity, self.pot_size)),
+        })
+    }
+
     fn traverser_cfr(
         &mut self,
         traversal: &Traversal,
@@ -886,6 +1128,23 @@ impl ActionNode {
     }
 }
 
+fn player_range<'a>(traversal: &'a Traversal, player: u8, board: &Board) -> &'a Vec<Combination> {
+    if player == 1 {
+        traversal.ip_rm.get_range_for_board(board)
+    } else {
+        traversal.oop_rm.get_range_for_board(board)
+    }
+}
+
+// how much of its raw equity share of the pot a hand actually collects
+fn realization(ev: f32, equity: f32, pot_size: f32) -> f32 {
+    if equity > 0.0 && pot_size > 0.0 {
+        ev / (equity * pot_size)
+    } else {
+        0.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -925,7 +1184,8 @@ mod tests {
                 })
                 .collect(),
             strategy_accumulator: vec![0.0; NUM_ACTIONS * NUM_HANDS],
-            node_ev: None,
+            hand_values: [None, None],
+            reach: [None, None],
         }
     }
 
@@ -943,7 +1203,7 @@ mod tests {
         let mut node = build_node();
 
         let strategy = node.get_strategy();
-        let traversal = build_traversal_from_ranges([2, 4, 5, 52, 52], "random", "random");
+        let traversal = build_traversal_from_ranges([2, 4, 5, 52, 52], "random", "random").unwrap();
         let prob = vec![0.5; NUM_HANDS];
 
         b.iter(|| {
@@ -956,7 +1216,7 @@ mod tests {
         let mut node = build_node();
 
         let strategy = node.get_strategy();
-        let traversal = build_traversal_from_ranges([2, 4, 5, 52, 52], "random", "random");
+        let traversal = build_traversal_from_ranges([2, 4, 5, 52, 52], "random", "random").unwrap();
         let util = vec![0.5; NUM_HANDS];
         let action = vec![vec![0.5; NUM_HANDS]; NUM_ACTIONS];
 
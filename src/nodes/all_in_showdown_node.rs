@@ -1,15 +1,22 @@
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use super::node::CfrNode;
-use crate::nodes::node::NodeResult;
+use crate::error::SolverError;
+use crate::nodes::node::{NodeResult, ResultFilter, ResultSink};
 use crate::{
-    cfr::traversal::Traversal,
-    nodes::showdown_node::showdown,
-    ranges::{combination::Board, utility::check_card_overlap},
+    cfr::{payoff::TerminalPayoffs, traversal::Traversal},
+    nodes::{
+        showdown_node::{showdown, showdown_utility},
+        terminal_node::terminal_utility,
+    },
+    ranges::{
+        combination::{Board, Range},
+        utility::check_card_overlap,
+    },
 };
 
 #[derive(Debug)]
 pub struct AllInShowdownNode {
-    win_utility: f32,
+    payoffs: TerminalPayoffs,
     street: u8,
 }
 
@@ -32,17 +39,29 @@ impl CfrNode for AllInShowdownNode {
         self.all_in_showdown_node_utility(traversal, op_reach_prob, board)
     }
 
-    fn output_results(&self) -> Option<NodeResult> {
+    fn output_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+    ) -> Option<NodeResult> {
         None
     }
+
+    fn export_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+        _sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        Ok(None)
+    }
 }
 
 impl AllInShowdownNode {
-    pub fn new(pot_size: f32, street: u8) -> Self {
-        Self {
-            win_utility: pot_size / 2.0,
-            street,
-        }
+    pub fn with_payoffs(payoffs: TerminalPayoffs, street: u8) -> Self {
+        Self { payoffs, street }
     }
 
     fn all_in_showdown_node_utility(
@@ -51,38 +70,136 @@ impl AllInShowdownNode {
         op_reach_probs: &[f32],
         board: &Board,
     ) -> Vec<f32> {
-        let mut utility = vec![0.0; traversal.get_num_hands_for_traverser(board)];
-        let hands = traversal.get_range_for_active_player(board);
+        runout_utility(traversal, op_reach_probs, board, self.street, |hands, probs| {
+            showdown_utility(hands, probs, &self.payoffs, traversal.traverser)
+        })
+    }
+}
 
-        if self.street == 1 {
-            let results: Vec<Option<(Board, Vec<f32>)>> = (0u8..52).into_par_iter().map(|turn| {
-                if check_card_overlap(turn, board) {
-                    return None;
-                }
+// raw equity of every traverser hand against the opponent's reach at this board, averaged over all remaining runouts
+pub fn runout_equity(traversal: &Traversal, op_reach_probs: &[f32], board: &Board) -> Vec<f32> {
+    runout_equities(traversal, &[op_reach_probs], board)
+        .pop()
+        .unwrap_or_default()
+}
 
-                let mut next_board = *board;
-                next_board[3] = turn;
-
-                let turn_probs = traversal.get_next_reach_probs(&next_board, op_reach_probs);
-                let mut turn_utility = vec![0.0; turn_probs.len()];
-                for river in (turn + 1)..52 {
-                    if !check_card_overlap(river, &next_board) {
-                        next_board[4] = river;
-                        let river_probs =
-                            traversal.get_next_reach_probs(&next_board, &turn_probs);
-                        let river_hands = traversal.get_range_for_active_player(&next_board);
-                        let river_utility =
-                            showdown(river_hands, &river_probs, self.win_utility);
-                        traversal.map_utility_backwards(
-                            &next_board,
-                            &river_utility,
-                            &mut turn_utility,
-                        );
+// equities against several reaches in one walk over the runouts, one result per reach. each is net showdown wins over
+// unblocked reach, both summed with the same runout weighting, so the normalization the all in utility applies
+// cancels out. both players hold the same merged list of hands, so this also gives the traverser's opponent their
+// equity against the traverser's reach
+pub fn runout_equities(
+    traversal: &Traversal,
+    op_reach_probs: &[&[f32]],
+    board: &Board,
+) -> Vec<Vec<f32>> {
+    let street = if board[3] == 52 {
+        1
+    } else if board[4] == 52 {
+        2
+    } else {
+        3
+    };
+
+    let net_wins = |hands: &Range, probs: &[f32]| showdown(hands, probs, 1.0);
+    let unblocked = |hands: &Range, probs: &[f32]| terminal_utility(1.0, probs, hands);
+    let sums = if street == 3 {
+        let hands = traversal.get_range_for_opponent(board);
+        op_reach_probs
+            .iter()
+            .map(|probs| vec![net_wins(hands, probs), unblocked(hands, probs)])
+            .collect()
+    } else {
+        runout_utilities(
+            traversal,
+            op_reach_probs,
+            board,
+            street,
+            &[&net_wins, &unblocked],
+        )
+    };
+
+    sums.iter()
+        .map(|sums| {
+            sums[0]
+                .iter()
+                .zip(sums[1].iter())
+                .map(|(net, count)| {
+                    let equity = 0.5 + 0.5 * net / count;
+                    if *count > 0.0 && equity.is_finite() {
+                        equity
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+type Payoff<'a> = &'a (dyn Fn(&Range, &[f32]) -> Vec<f32> + Sync);
+// indexed by reach and then payoff
+type Utilities = Vec<Vec<Vec<f32>>>;
+
+// deals every remaining turn and river and averages the river payoff back onto the hands at this board
+fn runout_utility(
+    traversal: &Traversal,
+    op_reach_probs: &[f32],
+    board: &Board,
+    street: u8,
+    payoff: impl Fn(&Range, &[f32]) -> Vec<f32> + Sync,
+) -> Vec<f32> {
+    runout_utilities(traversal, &[op_reach_probs], board, street, &[&payoff])
+        .pop()
+        .and_then(|mut utilities| utilities.pop())
+        .unwrap_or_default()
+}
+
+// the same walk with every payoff taken against every reach
+fn runout_utilities(
+    traversal: &Traversal,
+    op_reach_probs: &[&[f32]],
+    board: &Board,
+    street: u8,
+    payoffs: &[Payoff],
+) -> Utilities {
+    let num_hands = traversal.get_num_hands_for_traverser(board);
+    let mut utilities = vec![vec![vec![0.0; num_hands]; payoffs.len()]; op_reach_probs.len()];
+    let hands = traversal.get_range_for_active_player(board);
+
+    if street == 1 {
+        let results: Vec<Option<(Board, Utilities)>> = (0u8..52).into_par_iter().map(|turn| {
+            if check_card_overlap(turn, board) {
+                return None;
+            }
+
+            let mut next_board = *board;
+            next_board[3] = turn;
+
+            let turn_probs: Vec<Vec<f32>> = op_reach_probs
+                .iter()
+                .map(|probs| traversal.get_next_reach_probs(&next_board, probs))
+                .collect();
+            let mut turn_utilities: Utilities = turn_probs
+                .iter()
+                .map(|probs| vec![vec![0.0; probs.len()]; payoffs.len()])
+                .collect();
+            for river in (turn + 1)..52 {
+                if !check_card_overlap(river, &next_board) {
+                    next_board[4] = river;
+                    let river_hands = traversal.get_range_for_active_player(&next_board);
+                    for (probs, utilities) in turn_probs.iter().zip(turn_utilities.iter_mut()) {
+                        let river_probs = traversal.get_next_reach_probs(&next_board, probs);
+                        for (payoff, utility) in payoffs.iter().zip(utilities.iter_mut()) {
+                            let river_utility = payoff(river_hands, &river_probs);
+                            traversal.map_utility_backwards(&next_board, &river_utility, utility);
+                        }
                     }
                 }
-                next_board[4] = 52;
-                let turn_hands = traversal.get_range_for_active_player(&next_board);
+            }
+            next_board[4] = 52;
+            let turn_hands = traversal.get_range_for_active_player(&next_board);
 
+            for turn_utility in turn_utilities.iter_mut().flatten() {
                 turn_utility
                     .iter_mut()
                     .zip(turn_hands.iter())
@@ -92,37 +209,48 @@ impl AllInShowdownNode {
                         }
                     });
 
-                traversal.merge_canonical_utilities(&next_board, &mut turn_utility);
+                traversal.merge_canonical_utilities(&next_board, turn_utility);
+            }
 
-                next_board[4] = 52;
-                Some((next_board, turn_utility))
-            }).collect();
+            Some((next_board, turn_utilities))
+        }).collect();
 
-            results.iter().for_each(|res| {
-                match res {
-                    None => {}
-                    Some((board, turn_result)) => {
-                        traversal.map_utility_backwards(&board, &turn_result, &mut utility);
+        results.iter().for_each(|res| {
+            match res {
+                None => {}
+                Some((board, turn_results)) => {
+                    for (turn_result, utility) in
+                        turn_results.iter().flatten().zip(utilities.iter_mut().flatten())
+                    {
+                        traversal.map_utility_backwards(board, turn_result, utility);
                     }
                 }
-            });
+            }
+        });
+        for utility in utilities.iter_mut().flatten() {
             utility
                 .iter_mut()
                 .zip(hands.iter())
                 .for_each(|(val, hand)| {
                     *val /= 990.0 * f32::from(hand.weight);
                 });
-        } else {
-            for river in 0..52 {
-                if !check_card_overlap(river, board) {
-                    let mut next_board = *board;
-                    next_board[4] = river;
-                    let river_probs = traversal.get_next_reach_probs(&next_board, op_reach_probs);
-                    let hands = traversal.get_range_for_opponent(&next_board);
-                    let river_utility = showdown(hands, &river_probs, self.win_utility);
-                    traversal.map_utility_backwards(&next_board, &river_utility, &mut utility);
+        }
+    } else {
+        for river in 0..52 {
+            if !check_card_overlap(river, board) {
+                let mut next_board = *board;
+                next_board[4] = river;
+                let hands = traversal.get_range_for_opponent(&next_board);
+                for (probs, utility) in op_reach_probs.iter().zip(utilities.iter_mut()) {
+                    let river_probs = traversal.get_next_reach_probs(&next_board, probs);
+                    for (payoff, utility) in payoffs.iter().zip(utility.iter_mut()) {
+                        let river_utility = payoff(hands, &river_probs);
+                        traversal.map_utility_backwards(&next_board, &river_utility, utility);
+                    }
                 }
             }
+        }
+        for utility in utilities.iter_mut().flatten() {
             utility
                 .iter_mut()
                 .zip(hands.iter())
@@ -130,9 +258,60 @@ impl AllInShowdownNode {
                     *val /= 44.0 * f32::from(hand.weight);
                 });
         }
+    }
+
+    for utility in utilities.iter_mut().flatten() {
+        traversal.merge_canonical_utilities(board, utility);
+    }
+
+    utilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{runout_equities, runout_equity};
+    use crate::ranges::combination::Range;
+    use crate::cfr::traversal::build_traversal_from_ranges;
+    use crate::ranges::utility::parse_board;
+
+    #[test]
+    fn test_river_equity() {
+        let board = parse_board("as,ah,ac,kd,2s").unwrap();
+        let mut traversal = build_traversal_from_ranges(board, "QQ,KK", "QQ,KK").unwrap();
+        traversal.traverser = 0;
+
+        let opp_hands = traversal.get_range_for_opponent(&board);
+        let op_reach: Vec<f32> = opp_hands.iter().map(|combo| combo.combos).collect();
+        let equity = runout_equity(&traversal, &op_reach, &board);
+
+        // kings full beat queens full, queens chop with the one queens combo they don't block
+        let hands = traversal.get_range_for_active_player(&board);
+        for (hand, equity) in hands.iter().zip(equity.iter()) {
+            let expected = if hand.hand[0] / 4 == 11 { 1.0 } else { 0.5 / 4.0 };
+            assert!(
+                (equity - expected).abs() < 1e-4,
+                "{:?} has equity {} expected {}",
+                hand.hand,
+                equity,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_both_players_equities_from_one_walk() {
+        let board = parse_board("as,kh,7c,2d").unwrap();
+        let mut traversal = build_traversal_from_ranges(board, "QQ,JJ,AK", "KK,TT,A7").unwrap();
+        traversal.traverser = 0;
+        let reach = |hands: &Range| hands.iter().map(|combo| combo.combos).collect::<Vec<f32>>();
+        let oop_reach = reach(traversal.get_range_for_active_player(&board));
+        let ip_reach = reach(traversal.get_range_for_opponent(&board));
 
-        traversal.merge_canonical_utilities(board, &mut utility);
+        let oop_equity = runout_equity(&traversal, &ip_reach, &board);
+        traversal.traverser = 1;
+        let ip_equity = runout_equity(&traversal, &oop_reach, &board);
 
-        utility
+        let equities = runout_equities(&traversal, &[&ip_reach, &oop_reach], &board);
+        assert_eq!(equities, vec![oop_equity, ip_equity]);
     }
 }
@@ -0,0 +1,109 @@
+use crate::error::SolverError;
+use crate::nodes::node::{CfrNode, NodeResult, ResultFilter, ResultSink};
+use crate::nodes::terminal_node::terminal_utility;
+use crate::{
+    cfr::traversal::Traversal,
+    ranges::combination::{Board, Combination},
+};
+
+// the way out of a re-solved subgame in the resolve gadget. the player taking it gets each hand's counterfactual
+// value from the blueprint, per unit of unblocked reach and relative to half the pot, and the other player pays it,
+// so the gadget stays zero sum
+#[derive(Debug)]
+pub struct BlueprintValueNode {
+    player: u8,
+    values: Vec<f32>,
+}
+
+impl CfrNode for BlueprintValueNode {
+    fn cfr_traversal(
+        &mut self,
+        traversal: &Traversal,
+        op_reach_prob: &[f32],
+        board: &Board,
+    ) -> Vec<f32> {
+        self.dispatch_utility(traversal, op_reach_prob, board)
+    }
+
+    fn best_response(
+        &mut self,
+        traversal: &Traversal,
+        op_reach_prob: &[f32],
+        board: &Board,
+    ) -> Vec<f32> {
+        self.dispatch_utility(traversal, op_reach_prob, board)
+    }
+
+    fn output_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+    ) -> Option<NodeResult> {
+        None
+    }
+
+    fn export_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+        _sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        Ok(None)
+    }
+}
+
+impl BlueprintValueNode {
+    // values line up with the player's range at the subgame's board
+    pub fn new(player: u8, values: Vec<f32>) -> Self {
+        Self { player, values }
+    }
+
+    fn dispatch_utility(
+        &self,
+        traversal: &Traversal,
+        op_reach_prob: &[f32],
+        board: &Board,
+    ) -> Vec<f32> {
+        let opp_hands = traversal.get_range_for_opponent(board);
+
+        if traversal.traverser == self.player {
+            let mut utility = terminal_utility(1.0, op_reach_prob, opp_hands);
+            utility
+                .iter_mut()
+                .zip(self.values.iter())
+                .for_each(|(util, value)| *util *= value);
+            return utility;
+        }
+
+        let paid: Vec<f32> = op_reach_prob
+            .iter()
+            .zip(self.values.iter())
+            .map(|(prob, value)| -prob * value)
+            .collect();
+        unblocked_sum(&paid, opp_hands)
+    }
+}
+
+// like terminal_utility, but every opponent combo brings its own amount and amounts can be negative
+fn unblocked_sum(amounts: &[f32], hands: &[Combination]) -> Vec<f32> {
+    let mut total = 0.0;
+    let mut card_removal = [0.0; 52];
+    amounts.iter().zip(hands.iter()).for_each(|(amount, hand)| {
+        total += amount;
+        card_removal[usize::from(hand.hand[0])] += amount;
+        card_removal[usize::from(hand.hand[1])] += amount;
+    });
+
+    hands
+        .iter()
+        .zip(amounts.iter())
+        .map(|(combo, amount)| {
+            total
+                - card_removal[usize::from(combo.hand[0])]
+                - card_removal[usize::from(combo.hand[1])]
+                + amount
+        })
+        .collect()
+}
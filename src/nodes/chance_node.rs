@@ -1,11 +1,12 @@
-use crate::nodes::node::{CfrNode, Node, NodeResult, NodeResultType};
+use crate::error::SolverError;
+use crate::nodes::node::{CfrNode, Node, NodeResult, NodeResultType, ResultFilter, ResultSink};
 use crate::{
     cfr::traversal::Traversal,
     ranges::{
         combination::Board,
         utility::{
             board_has_turn, build_initial_suit_groups, build_next_suit_groups, check_card_overlap,
-            get_suit,
+            get_rank, get_suit,
         },
     },
 };
@@ -171,24 +172,42 @@ impl CfrNode for ChanceNode {
         result
     }
 
-    fn output_results(&self) -> Option<NodeResult> {
-        let next = if self.street == 1 {
-            self.next_nodes
-                .iter()
-                .filter_map(|node| node.output_results())
-                .collect()
-        } else {
-            vec![]
-        };
-
+    fn output_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+    ) -> Option<NodeResult> {
         Some(NodeResult {
-            node_type: NodeResultType::Chance,
-            node_strategy: None,
-            node_ev: None,
-            next_cards: Option::from(self.next_cards.clone()),
-            next_nodes: next,
+            next_nodes: self
+                .exported_runouts(board, filter)
+                .filter_map(|(node, next_board)| {
+                    node.output_results(traversal, &next_board, filter)
+                })
+                .collect(),
+            ..self.result()
         })
     }
+
+    fn export_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+        sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        let id = sink.reserve(self.street, &NodeResultType::Chance);
+
+        let mut children = vec![];
+        for (node, next_board) in self.exported_runouts(board, filter) {
+            if let Some(child) = node.export_results(traversal, &next_board, filter, sink)? {
+                children.push(child);
+            }
+        }
+
+        sink.write(id, &self.result(), &children)?;
+        Ok(Some(id))
+    }
 }
 
 impl ChanceNode {
@@ -209,6 +228,38 @@ impl ChanceNode {
     pub fn add_next_node(&mut self, child: Node) {
         self.next_nodes.push(child);
     }
+
+    fn result(&self) -> NodeResult {
+        NodeResult {
+            node_type: NodeResultType::Chance,
+            next_cards: Option::from(self.next_cards.clone()),
+            ..NodeResult::terminal(self.street)
+        }
+    }
+
+    // the next nodes the filter keeps, each with its board
+    fn exported_runouts<'a>(
+        &'a self,
+        board: &'a Board,
+        filter: &'a ResultFilter,
+    ) -> impl Iterator<Item = (&'a Node, Board)> + 'a {
+        let next_street = self.street + 1;
+        self.next_nodes
+            .iter()
+            .zip(self.next_cards.iter())
+            .filter(move |(_, card)| {
+                filter.includes_street(next_street) && filter.includes_card(next_street, **card)
+            })
+            .map(move |(node, card)| {
+                let mut next_board = *board;
+                if self.street == 1 {
+                    next_board[3] = *card;
+                } else {
+                    next_board[4] = *card;
+                }
+                (node, next_board)
+            })
+    }
 }
 
 fn merge_subgame_results(result: &mut [f32], weights: &[i8], sub_results: &[Vec<f32>]) {
@@ -222,13 +273,23 @@ fn merge_subgame_results(result: &mut [f32], weights: &[i8], sub_results: &[Vec<
     }
 }
 
-pub fn build_next(board: &Board, next_cards: &mut Vec<u8>, next_weights: &mut Vec<i8>) {
-    let suit_groups = if board_has_turn(board) {
+fn next_suit_groups(board: &Board) -> Vec<u8> {
+    if board_has_turn(board) {
         let flop_groups = build_initial_suit_groups(&[board[0], board[1], board[2], 52, 52]);
         build_next_suit_groups(board, &flop_groups)
     } else {
         build_initial_suit_groups(board)
-    };
+    }
+}
+
+// the card the solver deals in place of this one, the same rank in the first suit isomorphic to its suit
+pub fn canonical_card(board: &Board, card: u8) -> u8 {
+    let suit_groups = next_suit_groups(board);
+    get_rank(card) * 4 + suit_groups[usize::from(get_suit(card))]
+}
+
+pub fn build_next(board: &Board, next_cards: &mut Vec<u8>, next_weights: &mut Vec<i8>) {
+    let suit_groups = next_suit_groups(board);
 
     let mut suit_weights = [0i8; 4];
 
@@ -291,9 +352,9 @@ mod tests {
     #[test]
     fn test_correct_turn_cards_amount() {
         let board: Board = [
-            card_to_number("kc".to_string()),
-            card_to_number("7h".to_string()),
-            card_to_number("2h".to_string()),
+            card_to_number("kc".to_string()).unwrap(),
+            card_to_number("7h".to_string()).unwrap(),
+            card_to_number("2h".to_string()).unwrap(),
             52,
             52,
         ];
@@ -308,9 +369,9 @@ mod tests {
     #[test]
     fn test_correct_turn_cards_amount_2() {
         let board: Board = [
-            card_to_number("7c".to_string()),
-            card_to_number("7h".to_string()),
-            card_to_number("7d".to_string()),
+            card_to_number("7c".to_string()).unwrap(),
+            card_to_number("7h".to_string()).unwrap(),
+            card_to_number("7d".to_string()).unwrap(),
             52,
             52,
         ];
@@ -326,9 +387,9 @@ mod tests {
     #[test]
     fn test_correct_turn_cards_amount_3() {
         let board: Board = [
-            card_to_number("kc".to_string()),
-            card_to_number("7c".to_string()),
-            card_to_number("2c".to_string()),
+            card_to_number("kc".to_string()).unwrap(),
+            card_to_number("7c".to_string()).unwrap(),
+            card_to_number("2c".to_string()).unwrap(),
             52,
             52,
         ];
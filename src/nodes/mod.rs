@@ -1,5 +1,6 @@
 pub mod action_node;
 pub mod all_in_showdown_node;
+pub mod blueprint_value_node;
 pub mod chance_node;
 pub mod node;
 pub mod showdown_node;
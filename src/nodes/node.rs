@@ -1,6 +1,7 @@
 use super::action_node::ActionNode;
 use super::all_in_showdown_node::AllInShowdownNode;
-use super::chance_node::ChanceNode;
+use super::blueprint_value_node::BlueprintValueNode;
+use super::chance_node::{build_next, canonical_card, ChanceNode};
 use super::showdown_node::ShowdownNode;
 use super::terminal_node::TerminalNode;
 
@@ -8,12 +9,24 @@ use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
 
 use crate::cfr::traversal::Traversal;
+use crate::error::SolverError;
 use crate::ranges::combination::Board;
+use crate::ranges::utility::number_to_card;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NodeResultType {
     Action,
     Chance,
+    Terminal,
+}
+
+// calls aren't in the proto's Actions enum yet, everything else lines up with it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ActionType {
+    Fold,
+    Check,
+    Call,
+    Bet,
 }
 
 #[serde_with::skip_serializing_none]
@@ -21,12 +34,155 @@ pub enum NodeResultType {
 #[serde(rename_all = "camelCase")]
 pub struct NodeResult {
     pub node_type: NodeResultType,
-    pub node_strategy: Option<Vec<f32>>,
-    pub node_ev: Option<Vec<f32>>,
+    pub street: u8,
+    pub player: Option<u8>,
+    pub action_list: Option<Vec<ActionType>>,
+    pub bet_sizings: Option<Vec<f32>>,
+    pub hand_actions: Option<Vec<CombinationActions>>,
+    pub oop_values: Option<PlayerNodeResult>,
+    pub ip_values: Option<PlayerNodeResult>,
     pub next_cards: Option<Vec<u8>>,
     pub next_nodes: Vec<NodeResult>,
 }
 
+// mirrors CombinationActions in common.proto, frequency is how often the acting player gets here with the combo
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CombinationActions {
+    pub combination: String,
+    pub frequency: f32,
+    pub action_frequency: Vec<f32>,
+}
+
+// evs are the share of the pot at this node a combo expects to end up with, action evs follow the node's actions
+// and only exist for the player to act. under icm or bounty payoffs evs are the utility itself instead, what the combo
+// gains or loses on ending the hand with its stack plus half the starting pot
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CombinationValues {
+    pub combination: String,
+    pub reach: Option<f32>,
+    pub ev: f32,
+    pub equity: f32,
+    pub equity_realization: f32,
+    pub action_ev: Option<Vec<f32>>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerNodeResult {
+    pub hands: Vec<CombinationValues>,
+    pub range_ev: Option<f32>,
+    pub range_equity: Option<f32>,
+    pub range_equity_realization: Option<f32>,
+}
+
+impl NodeResult {
+    // stands in for folds and showdowns so an action node's next_nodes line up with its action_list
+    pub fn terminal(street: u8) -> Self {
+        NodeResult {
+            node_type: NodeResultType::Terminal,
+            street,
+            player: None,
+            action_list: None,
+            bet_sizings: None,
+            hand_actions: None,
+            oop_values: None,
+            ip_values: None,
+            next_cards: None,
+            next_nodes: vec![],
+        }
+    }
+
+    // every action node on the given street, in tree order, e.g. all river decisions under every turn and river card
+    pub fn street_nodes(&self, street: u8) -> Vec<&NodeResult> {
+        let mut nodes = vec![];
+        self.collect_street_nodes(street, &mut nodes);
+        nodes
+    }
+
+    fn collect_street_nodes<'a>(&'a self, street: u8, nodes: &mut Vec<&'a NodeResult>) {
+        if let NodeResultType::Action = self.node_type {
+            if self.street == street {
+                nodes.push(self);
+            }
+        }
+        for next in self.next_nodes.iter() {
+            next.collect_street_nodes(street, nodes);
+        }
+    }
+}
+
+// limits how much of the tree gets exported, the default exports every street under every runout. runout cards are
+// matched against the dealt cards as they appear in next_cards, turn first then river
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultFilter {
+    pub max_street: Option<u8>,
+    pub runout: Vec<u8>,
+}
+
+impl ResultFilter {
+    pub fn includes_street(&self, street: u8) -> bool {
+        self.max_street.map_or(true, |max| street <= max)
+    }
+
+    pub fn includes_card(&self, street: u8, card: u8) -> bool {
+        self.runout
+            .get(usize::from(street) - 2)
+            .map_or(true, |runout_card| *runout_card == card)
+    }
+
+    // only canonical cards are dealt, an isomorphic card would match nothing and export an empty tree. cards for
+    // streets already on the board have to be those cards
+    pub fn validate(&self, board: &Board) -> Result<(), SolverError> {
+        let mut board = *board;
+        for (position, card) in (3..5).zip(self.runout.iter()) {
+            if board[position] != 52 {
+                if board[position] != *card {
+                    return Err(SolverError::InvalidBoard(format!(
+                        "export runout card {} isn't the {} already on the board",
+                        number_to_card(*card),
+                        number_to_card(board[position])
+                    )));
+                }
+                continue;
+            }
+
+            let canonical = canonical_card(&board, *card);
+            if board.contains(card) || canonical != *card {
+                let mut cards = vec![];
+                build_next(&board, &mut cards, &mut vec![]);
+                return Err(SolverError::InvalidBoard(format!(
+                    "export runout card {} isn't dealt on this board{}, the cards dealt are {}",
+                    number_to_card(*card),
+                    if board.contains(card) {
+                        String::new()
+                    } else {
+                        format!(", it's solved as {}", number_to_card(canonical))
+                    },
+                    cards
+                        .iter()
+                        .map(|card| number_to_card(*card))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                )));
+            }
+            board[position] = *card;
+        }
+        Ok(())
+    }
+}
+
+// takes exported nodes one at a time as the tree is walked. a node's id is handed out before its children are
+// exported and the node is written once they have theirs, so every node's children come after it
+pub trait ResultSink {
+    fn reserve(&mut self, street: u8, node_type: &NodeResultType) -> u32;
+    fn write(&mut self, id: u32, node: &NodeResult, children: &[u32]) -> Result<(), SolverError>;
+}
+
 #[enum_dispatch]
 pub trait CfrNode {
     fn cfr_traversal(
@@ -41,7 +197,21 @@ pub trait CfrNode {
         op_reach_prob: &[f32],
         board: &Board,
     ) -> Vec<f32>;
-    fn output_results(&self) -> Option<NodeResult>;
+    fn output_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+    ) -> Option<NodeResult>;
+    // the same nodes as output_results passed to the sink as they're built, so the tree is never held in memory
+    // whole. None for the nodes output_results has no result for
+    fn export_results(
+        &self,
+        traversal: &Traversal,
+        board: &Board,
+        filter: &ResultFilter,
+        sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError>;
 }
 
 #[enum_dispatch(CfrNode)]
@@ -51,4 +221,5 @@ pub enum Node {
     ShowdownNode(ShowdownNode),
     TerminalNode(TerminalNode),
     AllInShowdownNode(AllInShowdownNode),
+    BlueprintValueNode(BlueprintValueNode),
 }
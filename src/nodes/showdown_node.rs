@@ -1,12 +1,14 @@
-use crate::nodes::node::{CfrNode, NodeResult};
+use crate::error::SolverError;
+use crate::nodes::node::{CfrNode, NodeResult, ResultFilter, ResultSink};
+use crate::nodes::terminal_node::terminal_utility;
 use crate::{
-    cfr::traversal::Traversal,
+    cfr::{payoff::TerminalPayoffs, traversal::Traversal},
     ranges::combination::{Board, Range},
 };
 
 #[derive(Debug)]
 pub struct ShowdownNode {
-    win_utility: f32,
+    payoffs: TerminalPayoffs,
 }
 
 impl CfrNode for ShowdownNode {
@@ -17,7 +19,7 @@ impl CfrNode for ShowdownNode {
         board: &Board,
     ) -> Vec<f32> {
         let opp_hands = traversal.get_range_for_opponent(board);
-        showdown(opp_hands, op_reach_prob, self.win_utility)
+        showdown_utility(opp_hands, op_reach_prob, &self.payoffs, traversal.traverser)
     }
 
     fn best_response(
@@ -27,20 +29,120 @@ impl CfrNode for ShowdownNode {
         board: &Board,
     ) -> Vec<f32> {
         let opp_hands = traversal.get_range_for_opponent(board);
-        showdown(opp_hands, op_reach_prob, self.win_utility)
+        showdown_utility(opp_hands, op_reach_prob, &self.payoffs, traversal.traverser)
     }
 
-    fn output_results(&self) -> Option<NodeResult> {
+    fn output_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+    ) -> Option<NodeResult> {
         None
     }
+
+    fn export_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+        _sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        Ok(None)
+    }
 }
 
 impl ShowdownNode {
     pub fn new(pot_size: f32) -> Self {
-        Self {
-            win_utility: pot_size / 2.0,
+        Self::with_payoffs(TerminalPayoffs::chip_ev(pot_size))
+    }
+
+    pub fn with_payoffs(payoffs: TerminalPayoffs) -> Self {
+        Self { payoffs }
+    }
+}
+
+// showdown utilities for any payoffs, chip ev keeps to the single pass showdown below
+pub fn showdown_utility(
+    hands: &Range,
+    op_reach_prob: &[f32],
+    payoffs: &TerminalPayoffs,
+    traverser: u8,
+) -> Vec<f32> {
+    let player = usize::from(traverser);
+    if payoffs.is_symmetric(traverser) {
+        return showdown(hands, op_reach_prob, payoffs.win[player]);
+    }
+
+    let tie = payoffs.tie[player];
+    let mut utility = split_showdown(
+        hands,
+        op_reach_prob,
+        payoffs.win[player] - tie,
+        payoffs.lose[player] - tie,
+    );
+    if tie != 0.0 {
+        utility
+            .iter_mut()
+            .zip(terminal_utility(tie, op_reach_prob, hands).iter())
+            .for_each(|(util, tied)| *util += tied);
+    }
+    utility
+}
+
+// like showdown but with wins and losses weighted separately, a tie is worth nothing
+pub fn split_showdown(
+    hands: &Range,
+    op_reach_prob: &[f32],
+    win_utility: f32,
+    lose_utility: f32,
+) -> Vec<f32> {
+    let num_hands = hands.len();
+    let mut utility = vec![0.0; num_hands];
+
+    let mut above = 0.0;
+    let mut above_removal = [0.0; 52];
+    let mut below = 0.0;
+    let mut below_removal = [0.0; 52];
+
+    op_reach_prob.iter().zip(hands).for_each(|(prob, hand)| {
+        above += prob;
+        above_removal[usize::from(hand.hand[0])] += prob;
+        above_removal[usize::from(hand.hand[1])] += prob;
+    });
+
+    let mut i = 0;
+    while i < num_hands {
+        let mut j = i + 1;
+        while j < num_hands && hands[j].rank == hands[i].rank {
+            j += 1;
+        }
+
+        for k in i..j {
+            let prob = op_reach_prob[k];
+            above -= prob;
+            above_removal[usize::from(hands[k].hand[0])] -= prob;
+            above_removal[usize::from(hands[k].hand[1])] -= prob;
+        }
+
+        for k in i..j {
+            let cards = [usize::from(hands[k].hand[0]), usize::from(hands[k].hand[1])];
+            let wins = below - below_removal[cards[0]] - below_removal[cards[1]];
+            let losses = above - above_removal[cards[0]] - above_removal[cards[1]];
+            utility[k] = win_utility * wins + lose_utility * losses;
+        }
+
+        for k in i..j {
+            let prob = op_reach_prob[k];
+            below += prob;
+            below_removal[usize::from(hands[k].hand[0])] += prob;
+            below_removal[usize::from(hands[k].hand[1])] += prob;
         }
+
+        i = j;
     }
+
+    utility
 }
 
 pub fn showdown(hands: &Range, op_reach_prob: &[f32], win_utility: f32) -> Vec<f32> {
@@ -108,7 +210,10 @@ pub fn showdown(hands: &Range, op_reach_prob: &[f32], win_utility: f32) -> Vec<f
 mod tests {
     use super::ShowdownNode;
     use crate::cfr::traversal::build_traversal_from_ranges;
+    use crate::cfr::payoff::TerminalPayoffs;
     use crate::nodes::showdown_node::{showdown, unsafe_showdown};
+    use crate::nodes::showdown_node::{showdown_utility, split_showdown};
+    use crate::nodes::terminal_node::terminal_utility;
     use crate::{
         cfr::traversal::Traversal,
         nodes::node::CfrNode,
@@ -120,10 +225,52 @@ mod tests {
 
     extern crate test;
 
+    fn ranked_range(range: &str, board: &[u8; 5]) -> Vec<Combination> {
+        let mut hands = construct_starting_range_from_string(range.to_string(), board).unwrap();
+        let board_hand = board
+            .iter()
+            .fold(Hand::default(), |hand, card| hand + CARDS[usize::from(*card)]);
+        hands.iter_mut().for_each(|h| {
+            h.rank = evaluate(&(board_hand + CARDS[usize::from(h.hand[0])] + CARDS[usize::from(h.hand[1])]));
+        });
+        hands.sort_by(|a, b| a.rank.cmp(&b.rank));
+        hands
+    }
+
+    #[test]
+    fn test_split_showdown_matches_showdown() {
+        let board = [2, 13, 24, 35, 47];
+        let hands = ranked_range("22+,A2s+,K9s+,QTo+,T9s,87s,65s", &board);
+        let probs: Vec<f32> = (0..hands.len()).map(|i| 0.1 + (i % 7) as f32 * 0.13).collect();
+
+        let symmetric = showdown(&hands, &probs, 3.0);
+        let split = split_showdown(&hands, &probs, 3.0, -3.0);
+        symmetric
+            .iter()
+            .zip(split.iter())
+            .for_each(|(a, b)| assert!((a - b).abs() < 1e-3, "{} vs {}", a, b));
+
+        // an uneven payoff with a tie adds the tie value to every unblocked matchup
+        let payoffs = TerminalPayoffs {
+            win: [2.0, 2.0],
+            tie: [0.5, 0.5],
+            lose: [-4.0, -4.0],
+        };
+        let utility = showdown_utility(&hands, &probs, &payoffs, 0);
+        let wins = split_showdown(&hands, &probs, 1.0, 0.0);
+        let losses = split_showdown(&hands, &probs, 0.0, 1.0);
+        let unblocked = terminal_utility(1.0, &probs, &hands);
+        for i in 0..hands.len() {
+            let ties = unblocked[i] - wins[i] - losses[i];
+            let expected = 2.0 * wins[i] + 0.5 * ties - 4.0 * losses[i];
+            assert!((utility[i] - expected).abs() < 1e-3);
+        }
+    }
+
     #[bench]
     fn bench_standard_utility(b: &mut Bencher) {
         let board = [2, 13, 24, 35, 47];
-        let mut traverser_hands = construct_starting_range_from_string("22+,A7s,A6s,K8s,K5s,K4s,K3s,K2s,Q7s,Q6s,Q5s,Q4s,Q3s,Q2s,J6s,J5s,J4s,J3s,J2s,T5s,T4s,T3s,T2s,96s,95s,85s,84s,74s,73s,63s,53s,52s,43s,42s,32s,A9o,A8o,A7o,A6o,A5o,A4o,A3o,KTo,K9o,K8o,K7o,K6o,QTo,Q9o,Q8o,JTo,J9o,J8o,T9o,T8o,98o,87o,76o,65o,A9s@75,A8s@75,A2s@75,K7s@75,K6s@75,Q8s@75,T7s@75,T6s@75,97s@75,86s@75,75s@75,64s@75,QJo@75,88@50,ATs@50,A3s@50,KTs@50,K9s@50,Q9s@50,J7s@50,94s@50,93s@50,54s@50,AJo@50,ATo@50,KQo@50,KJo@50,99@25,A4s@25,KJs@25,QJs@25,QTs@25,98s@25,87s@25,76s@25,65s@25".to_string(), &board);
+        let mut traverser_hands = construct_starting_range_from_string("22+,A7s,A6s,K8s,K5s,K4s,K3s,K2s,Q7s,Q6s,Q5s,Q4s,Q3s,Q2s,J6s,J5s,J4s,J3s,J2s,T5s,T4s,T3s,T2s,96s,95s,85s,84s,74s,73s,63s,53s,52s,43s,42s,32s,A9o,A8o,A7o,A6o,A5o,A4o,A3o,KTo,K9o,K8o,K7o,K6o,QTo,Q9o,Q8o,JTo,J9o,J8o,T9o,T8o,98o,87o,76o,65o,A9s@75,A8s@75,A2s@75,K7s@75,K6s@75,Q8s@75,T7s@75,T6s@75,97s@75,86s@75,75s@75,64s@75,QJo@75,88@50,ATs@50,A3s@50,KTs@50,K9s@50,Q9s@50,J7s@50,94s@50,93s@50,54s@50,AJo@50,ATo@50,KQo@50,KJo@50,99@25,A4s@25,KJs@25,QJs@25,QTs@25,98s@25,87s@25,76s@25,65s@25".to_string(), &board).unwrap();
 
         let mut board_hand = Hand::default();
         for board_card in board.iter() {
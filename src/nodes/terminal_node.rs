@@ -1,12 +1,13 @@
 use crate::{
-    cfr::traversal::Traversal,
+    cfr::{payoff::TerminalPayoffs, traversal::Traversal},
     ranges::combination::{Board, Combination},
 };
-use crate::nodes::node::{CfrNode, NodeResult};
+use crate::error::SolverError;
+use crate::nodes::node::{CfrNode, NodeResult, ResultFilter, ResultSink};
 
 #[derive(Debug)]
 pub struct TerminalNode {
-    win_utility: f32,
+    payoffs: TerminalPayoffs,
     player_node: u8,
 }
 
@@ -29,15 +30,31 @@ impl CfrNode for TerminalNode {
         self.dispatch_utility(traversal, op_reach_prob, board)
     }
 
-    fn output_results(&self) -> Option<NodeResult> {
+    fn output_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+    ) -> Option<NodeResult> {
         None
     }
+
+    fn export_results(
+        &self,
+        _traversal: &Traversal,
+        _board: &Board,
+        _filter: &ResultFilter,
+        _sink: &mut dyn ResultSink,
+    ) -> Result<Option<u32>, SolverError> {
+        Ok(None)
+    }
 }
 
 impl TerminalNode {
-    pub fn new(pot_size: f32, player_node: u8) -> Self {
+    // player_node is the player who wins the pot
+    pub fn with_payoffs(payoffs: TerminalPayoffs, player_node: u8) -> Self {
         Self {
-            win_utility: pot_size / 2.0,
+            payoffs,
             player_node,
         }
     }
@@ -50,17 +67,18 @@ impl TerminalNode {
     ) -> Vec<f32> {
         let opp_hands = traversal.get_range_for_opponent(board);
 
+        let traverser = usize::from(traversal.traverser);
         let util = if traversal.traverser == self.player_node {
-            self.win_utility
+            self.payoffs.win[traverser]
         } else {
-            -self.win_utility
+            self.payoffs.lose[traverser]
         };
 
         terminal_utility(util, op_reach_prob, opp_hands)
     }
 }
 
-fn terminal_utility(
+pub fn terminal_utility(
     win_utility: f32,
     op_reach_prob: &[f32],
     hands: &[Combination],
@@ -112,7 +130,7 @@ mod tests {
     #[bench]
     fn bench_standard_terminal(b: &mut Bencher) {
         let board = [2, 13, 24, 35, 47];
-        let mut traverser_hands = construct_starting_range_from_string("77,66,55,44,33,22,A7s,A6s,K8s,K5s,K4s,K3s,K2s,Q7s,Q6s,Q5s,Q4s,Q3s,Q2s,J6s,J5s,J4s,J3s,J2s,T5s,T4s,T3s,T2s,96s,95s,85s,84s,74s,73s,63s,53s,52s,43s,42s,32s,A9o,A8o,A7o,A6o,A5o,A4o,A3o,KTo,K9o,K8o,K7o,K6o,QTo,Q9o,Q8o,JTo,J9o,J8o,T9o,T8o,98o,87o,76o,65o,A9s@75,A8s@75,A2s@75,K7s@75,K6s@75,Q8s@75,T7s@75,T6s@75,97s@75,86s@75,75s@75,64s@75,QJo@75,88@50,ATs@50,A3s@50,KTs@50,K9s@50,Q9s@50,J7s@50,94s@50,93s@50,54s@50,AJo@50,ATo@50,KQo@50,KJo@50,99@25,A4s@25,KJs@25,QJs@25,QTs@25,98s@25,87s@25,76s@25,65s@25".to_string(), &board);
+        let mut traverser_hands = construct_starting_range_from_string("77,66,55,44,33,22,A7s,A6s,K8s,K5s,K4s,K3s,K2s,Q7s,Q6s,Q5s,Q4s,Q3s,Q2s,J6s,J5s,J4s,J3s,J2s,T5s,T4s,T3s,T2s,96s,95s,85s,84s,74s,73s,63s,53s,52s,43s,42s,32s,A9o,A8o,A7o,A6o,A5o,A4o,A3o,KTo,K9o,K8o,K7o,K6o,QTo,Q9o,Q8o,JTo,J9o,J8o,T9o,T8o,98o,87o,76o,65o,A9s@75,A8s@75,A2s@75,K7s@75,K6s@75,Q8s@75,T7s@75,T6s@75,97s@75,86s@75,75s@75,64s@75,QJo@75,88@50,ATs@50,A3s@50,KTs@50,K9s@50,Q9s@50,J7s@50,94s@50,93s@50,54s@50,AJo@50,ATo@50,KQo@50,KJo@50,99@25,A4s@25,KJs@25,QJs@25,QTs@25,98s@25,87s@25,76s@25,65s@25".to_string(), &board).unwrap();
 
         let op_reach_prob = vec![1.0; traverser_hands.len()];
         b.iter(|| {
@@ -9,14 +9,14 @@ pub type Range = [Combination];
 #[serde(rename_all = "camelCase")]
 pub struct Combination {
     pub hand: Hand,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub rank: u16,
     pub combos: f32,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub weight: i8,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub raw_index: usize,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     pub canon_index: usize,
 }
 
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use super::combination::Board;
+use super::utility::{canonical_positions, combination_label, range_relative_probabilities};
+use crate::cfr::traversal::{build_traversal_from_ranges, Traversal};
+use crate::error::SolverError;
+use crate::nodes::all_in_showdown_node::runout_equity;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComboEquity {
+    pub combination: String,
+    pub weight: f32,
+    pub equity: f32,
+}
+
+// equity is the range's overall equity with every combo weighted by how likely it is once the opponent's range
+// blocks it, the histogram gives the share of the range, weighted the same way, in each equal width equity bucket
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerEquity {
+    pub hands: Vec<ComboEquity>,
+    pub equity: f32,
+    pub histogram: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EquityReport {
+    pub board: Board,
+    pub oop: PlayerEquity,
+    pub ip: PlayerEquity,
+}
+
+// all in equity of both ranges against each other on a flop, turn or river, averaged over every remaining runout
+pub fn range_equity(
+    board: Board,
+    oop_range: &str,
+    ip_range: &str,
+    buckets: usize,
+) -> Result<EquityReport, SolverError> {
+    if buckets == 0 {
+        return Err(SolverError::InvalidMessage(
+            "an equity histogram needs at least one bucket".to_string(),
+        ));
+    }
+
+    let mut traversal = build_traversal_from_ranges(board, oop_range, ip_range)?;
+    let oop = player_equity(&mut traversal, 0, &board, buckets);
+    let ip = player_equity(&mut traversal, 1, &board, buckets);
+
+    Ok(EquityReport { board, oop, ip })
+}
+
+fn player_equity(
+    traversal: &mut Traversal,
+    player: u8,
+    board: &Board,
+    buckets: usize,
+) -> PlayerEquity {
+    traversal.traverser = player;
+    let hands = traversal.get_range_for_active_player(board);
+    let op_hands = traversal.get_range_for_opponent(board);
+    let op_reach: Vec<f32> = op_hands.iter().map(|combo| combo.combos).collect();
+
+    let equities = runout_equity(traversal, &op_reach, board);
+    let relative = range_relative_probabilities(hands, op_hands);
+    let positions = canonical_positions(hands);
+
+    let mut result = PlayerEquity {
+        hands: vec![],
+        equity: 0.0,
+        histogram: vec![0.0; buckets],
+    };
+
+    for (i, hand) in hands.iter().enumerate() {
+        if hand.combos <= 0.0 {
+            continue;
+        }
+        // isomorphic combos have their equity stored on the canonical combo
+        let equity = equities[positions[i]];
+        result.hands.push(ComboEquity {
+            combination: combination_label(&hand.hand),
+            weight: hand.combos,
+            equity,
+        });
+
+        if relative[i].is_finite() {
+            result.equity += relative[i] * equity;
+            let bucket = ((equity * buckets as f32) as usize).min(buckets - 1);
+            result.histogram[bucket] += relative[i];
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::parse_board;
+
+    #[test]
+    fn test_river_equity_is_all_or_nothing() {
+        let board = parse_board("as,ah,ac,kd,2s").unwrap();
+        let report = range_equity(board, "KK", "QQ", 10).unwrap();
+
+        assert_eq!(report.oop.hands.len(), 3);
+        assert!(report
+            .oop
+            .hands
+            .iter()
+            .all(|h| (h.equity - 1.0).abs() < 1e-5));
+        assert!(report.ip.hands.iter().all(|h| h.equity.abs() < 1e-5));
+        assert!((report.oop.equity - 1.0).abs() < 1e-5);
+        assert!((report.oop.histogram[9] - 1.0).abs() < 1e-5);
+        assert!((report.ip.histogram[0] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_turn_equity_counts_river_outs() {
+        let board = parse_board("as,kh,7c,2d").unwrap();
+        let report = range_equity(board, "QQ", "JJ", 20).unwrap();
+
+        // jacks hit one of their two outs among the 44 rivers left
+        let outs = 2.0 / 44.0;
+        assert_eq!(report.ip.hands.len(), 6);
+        assert!(report
+            .ip
+            .hands
+            .iter()
+            .all(|h| (h.equity - outs).abs() < 1e-4));
+        assert!((report.ip.equity - outs).abs() < 1e-4);
+        assert!((report.oop.equity + report.ip.equity - 1.0).abs() < 1e-4);
+        assert!((report.oop.histogram.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+}
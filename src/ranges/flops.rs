@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::combination::Board;
+use super::texture::{classify_board, Connectedness, HighCard, Pairing, Suitedness};
+use super::utility::{get_rank, get_suit};
+
+// weight is the number of raw flops, out of 22100, the board stands in for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WeightedFlop {
+    pub board: Board,
+    pub weight: f32,
+}
+
+// every strategically distinct flop, 1755 of them, highest card first
+pub fn canonical_flops() -> Vec<WeightedFlop> {
+    let mut counts: BTreeMap<[u8; 3], u32> = BTreeMap::new();
+    for a in 0u8..52 {
+        for b in (a + 1)..52 {
+            for c in (b + 1)..52 {
+                *counts.entry(canonical_flop([a, b, c])).or_default() += 1;
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(cards, count)| WeightedFlop {
+            board: [cards[0], cards[1], cards[2], 52, 52],
+            weight: count as f32,
+        })
+        .collect()
+}
+
+// the same flop under every relabelling of the suits, the representative is the one that sorts lowest once its cards
+// are ordered high to low, which gives the top card spades
+pub fn canonical_flop(cards: [u8; 3]) -> [u8; 3] {
+    suit_permutations()
+        .iter()
+        .map(|permutation| {
+            let mut mapped =
+                cards.map(|card| get_rank(card) * 4 + permutation[usize::from(get_suit(card))]);
+            mapped.sort_unstable_by(|a, b| b.cmp(a));
+            mapped
+        })
+        .min()
+        .unwrap_or(cards)
+}
+
+fn suit_permutations() -> Vec<[u8; 4]> {
+    let mut permutations = vec![];
+    for a in 0u8..4 {
+        for b in (0u8..4).filter(|b| *b != a) {
+            for c in (0u8..4).filter(|c| *c != a && *c != b) {
+                permutations.push([a, b, c, 6 - a - b - c]);
+            }
+        }
+    }
+    permutations
+}
+
+// picks n flops that stand in for the full set. flops are split into strata by high card, pairedness, suits and
+// connectedness, each stratum gets a share of the n flops in proportion to its weight, and the flops picked inside a
+// stratum are spread evenly across it. each pick carries an equal part of its stratum's weight, scaled so
+// the subset still adds up to 22100
+pub fn representative_flops(n: usize) -> Vec<WeightedFlop> {
+    let flops = canonical_flops();
+    if n >= flops.len() {
+        return flops;
+    }
+
+    let mut strata: BTreeMap<Stratum, Vec<WeightedFlop>> = BTreeMap::new();
+    for flop in flops.iter() {
+        strata.entry(stratum(&flop.board)).or_default().push(*flop);
+    }
+    let strata: Vec<Vec<WeightedFlop>> = strata.into_values().collect();
+
+    let weights: Vec<f32> = strata
+        .iter()
+        .map(|stratum| stratum.iter().map(|flop| flop.weight).sum())
+        .collect();
+    let allocation = allocate(
+        n,
+        &weights,
+        &strata.iter().map(Vec::len).collect::<Vec<_>>(),
+    );
+
+    let mut selected = vec![];
+    for ((stratum, weight), picks) in strata.iter().zip(weights.iter()).zip(allocation) {
+        if picks == 0 {
+            continue;
+        }
+        // the flop in the middle of each of the equal slices of the stratum
+        for k in 0..picks {
+            let flop = stratum[(2 * k + 1) * stratum.len() / (2 * picks)];
+            selected.push(WeightedFlop {
+                board: flop.board,
+                weight: weight / picks as f32,
+            });
+        }
+    }
+
+    let total: f32 = flops.iter().map(|flop| flop.weight).sum();
+    let selected_total: f32 = selected.iter().map(|flop| flop.weight).sum();
+    selected
+        .iter_mut()
+        .for_each(|flop| flop.weight *= total / selected_total);
+    selected
+}
+
+// largest remainder split of n picks over the strata, never giving a stratum more picks than it has flops
+fn allocate(n: usize, weights: &[f32], sizes: &[usize]) -> Vec<usize> {
+    let total: f32 = weights.iter().sum();
+    let quotas: Vec<f32> = weights.iter().map(|w| w / total * n as f32).collect();
+    let mut allocation: Vec<usize> = quotas
+        .iter()
+        .zip(sizes.iter())
+        .map(|(quota, size)| (*quota as usize).min(*size))
+        .collect();
+
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by(|a, b| {
+        let remainder = |i: usize| quotas[i] - allocation[i] as f32;
+        remainder(*b).partial_cmp(&remainder(*a)).unwrap()
+    });
+
+    let mut left = n - allocation.iter().sum::<usize>();
+    while left > 0 {
+        let before = left;
+        for i in order.iter() {
+            if left > 0 && allocation[*i] < sizes[*i] {
+                allocation[*i] += 1;
+                left -= 1;
+            }
+        }
+        if left == before {
+            break;
+        }
+    }
+    allocation
+}
+
+type Stratum = (HighCard, Pairing, Suitedness, bool);
+
+fn stratum(board: &Board) -> Stratum {
+    let texture = classify_board(board).expect("flops always have three cards");
+    (
+        texture.high_card,
+        texture.pairing,
+        texture.suitedness,
+        texture.connectedness >= Connectedness::Connected,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::{card_to_number, parse_board};
+
+    #[test]
+    fn test_canonical_flops_cover_every_flop() {
+        let flops = canonical_flops();
+        assert_eq!(flops.len(), 1755);
+        assert_eq!(flops.iter().map(|f| f.weight).sum::<f32>(), 22100.0);
+
+        // monotone and rainbow unpaired flops stand for 4 and 24 raw flops
+        let monotone = parse_board("ks,7s,2s").unwrap();
+        let rainbow = parse_board("ks,7h,2c").unwrap();
+        let weight = |board: Board| flops.iter().find(|f| f.board == board).unwrap().weight;
+        assert_eq!(weight(monotone), 4.0);
+        assert_eq!(weight(rainbow), 24.0);
+    }
+
+    #[test]
+    fn test_representative_subset_keeps_the_distribution() {
+        let subset = representative_flops(100);
+        assert_eq!(subset.len(), 100);
+        assert!((subset.iter().map(|f| f.weight).sum::<f32>() - 22100.0).abs() < 0.5);
+
+        let share = |flops: &[WeightedFlop], keep: fn(&Stratum) -> bool| {
+            let total: f32 = flops.iter().map(|f| f.weight).sum();
+            flops
+                .iter()
+                .filter(|f| keep(&stratum(&f.board)))
+                .map(|f| f.weight)
+                .sum::<f32>()
+                / total
+        };
+        let all = canonical_flops();
+        for keep in [
+            (|s: &Stratum| s.2 == Suitedness::Monotone) as fn(&Stratum) -> bool,
+            |s| s.1 != Pairing::Unpaired,
+            |s| s.0 == HighCard::Ace,
+            |s| s.3,
+        ] {
+            assert!((share(&subset, keep) - share(&all, keep)).abs() < 0.02);
+        }
+
+        assert_eq!(representative_flops(5000).len(), 1755);
+    }
+
+    #[test]
+    fn test_isomorphic_flops_share_a_representative() {
+        let cards = |s: &str| {
+            let cards: Vec<u8> = s
+                .split(',')
+                .map(|c| card_to_number(c.to_string()).unwrap())
+                .collect();
+            [cards[0], cards[1], cards[2]]
+        };
+        assert_eq!(
+            canonical_flop(cards("qd,jc,2c")),
+            canonical_flop(cards("2h,qs,jh"))
+        );
+        assert_ne!(
+            canonical_flop(cards("qd,jc,2c")),
+            canonical_flop(cards("qc,jc,2d"))
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use rust_poker::hand_evaluator::{evaluate, Hand as EvalHand, CARDS};
+use serde::{Deserialize, Serialize};
+
+use super::combination::{Board, Hand};
+use super::utility::{get_rank, get_suit, parse_combination};
+use crate::error::SolverError;
+use crate::nodes::node::NodeResult;
+
+// rust_poker ranks keep the hand category in the bits above 12, 1 for high card up to 9 for a straight flush
+const CATEGORY_SHIFT: u16 = 12;
+const PAIR: u16 = 2;
+const TWO_PAIR: u16 = 3;
+const TRIPS: u16 = 4;
+const STRAIGHT: u16 = 5;
+const FLUSH: u16 = 6;
+const FULL_HOUSE: u16 = 7;
+const QUADS: u16 = 8;
+
+// a combo falls in at most one made hand category and any number of draws, air is only used when it has neither.
+// blockers are tagged on top of whichever of those it is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum HandCategory {
+    StraightFlush,
+    Quads,
+    FullHouse,
+    Flush,
+    Straight,
+    Set,
+    Trips,
+    TwoPair,
+    Overpair,
+    TopPairTopKicker,
+    TopPairGoodKicker,
+    TopPairWeakKicker,
+    Underpair,
+    MiddlePair,
+    BottomPair,
+    FlushDraw,
+    Oesd,
+    Gutshot,
+    BackdoorFlushDraw,
+    BackdoorStraightDraw,
+    Air,
+    NutFlushBlocker,
+    NutStraightBlocker,
+}
+
+// how the acting player's range in a category plays at a node. combos is the reach weighted number of combos,
+// frequencies and ev are averaged over those combos
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryResult {
+    pub category: HandCategory,
+    pub combos: f32,
+    pub action_frequencies: Vec<f32>,
+    pub ev: Option<f32>,
+}
+
+pub fn classify(hand: &Hand, board: &Board) -> Vec<HandCategory> {
+    let cards: Vec<u8> = board.iter().copied().filter(|card| *card != 52).collect();
+    let mut categories = vec![];
+
+    let made = made_hand(hand, &cards);
+    if let Some(made) = made {
+        categories.push(made);
+    }
+    // nothing left to draw to on the river, and made flushes or straights don't count their draws
+    if cards.len() < 5 && made.map_or(true, |m| m > HandCategory::Straight) {
+        categories.extend(draws(hand, &cards));
+    }
+
+    if categories.is_empty() {
+        categories.push(HandCategory::Air);
+    }
+    categories.extend(blockers(hand, &cards));
+    categories
+}
+
+// per category frequencies and ev of the acting player at an action node, board being the board at that node
+pub fn category_breakdown(
+    node: &NodeResult,
+    board: &Board,
+) -> Result<Vec<CategoryResult>, SolverError> {
+    let (actions, hand_actions) =
+        match (&node.action_list, &node.hand_actions) {
+            (Some(actions), Some(hand_actions)) => (actions, hand_actions),
+            _ => return Err(SolverError::InvalidMessage(
+                "only action nodes with exported strategies can be broken down by hand category"
+                    .to_string(),
+            )),
+        };
+
+    let values = match node.player {
+        Some(0) => node.oop_values.as_ref(),
+        _ => node.ip_values.as_ref(),
+    };
+    let evs: HashMap<&str, f32> = values
+        .iter()
+        .flat_map(|values| values.hands.iter())
+        .map(|hand| (hand.combination.as_str(), hand.ev))
+        .collect();
+
+    // (combos, weighted action frequencies, weighted ev, combos with an ev)
+    let mut totals: HashMap<HandCategory, (f32, Vec<f32>, f32, f32)> = HashMap::new();
+    for hand in hand_actions.iter() {
+        let cards = parse_combination(&hand.combination)?;
+        let ev = evs.get(hand.combination.as_str());
+
+        for category in classify(&cards, board) {
+            let total = totals
+                .entry(category)
+                .or_insert_with(|| (0.0, vec![0.0; actions.len()], 0.0, 0.0));
+            total.0 += hand.frequency;
+            total
+                .1
+                .iter_mut()
+                .zip(hand.action_frequency.iter())
+                .for_each(|(sum, frequency)| *sum += hand.frequency * frequency);
+            if let Some(ev) = ev {
+                total.2 += hand.frequency * ev;
+                total.3 += hand.frequency;
+            }
+        }
+    }
+
+    let mut results: Vec<CategoryResult> = totals
+        .into_iter()
+        .filter(|(_, (combos, _, _, _))| *combos > 0.0)
+        .map(
+            |(category, (combos, frequencies, ev, ev_combos))| CategoryResult {
+                category,
+                combos,
+                action_frequencies: frequencies.iter().map(|f| f / combos).collect(),
+                ev: if ev_combos > 0.0 {
+                    Some(ev / ev_combos)
+                } else {
+                    None
+                },
+            },
+        )
+        .collect();
+    results.sort_by_key(|result| result.category);
+    Ok(results)
+}
+
+fn category(cards: impl Iterator<Item = u8>) -> u16 {
+    let mut hand = EvalHand::default();
+    for card in cards {
+        hand += CARDS[usize::from(card)];
+    }
+    evaluate(&hand) >> CATEGORY_SHIFT
+}
+
+fn made_hand(hand: &Hand, board: &[u8]) -> Option<HandCategory> {
+    let with_hand = category(board.iter().chain(hand.iter()).copied());
+    let board_only = category(board.iter().copied());
+    let pocket_pair = get_rank(hand[0]) == get_rank(hand[1]);
+
+    // the hole cards have to improve on what the board makes on its own
+    match with_hand {
+        _ if with_hand <= board_only => None,
+        c if c > QUADS => Some(HandCategory::StraightFlush),
+        QUADS => Some(HandCategory::Quads),
+        FULL_HOUSE => Some(HandCategory::FullHouse),
+        FLUSH => Some(HandCategory::Flush),
+        STRAIGHT => Some(HandCategory::Straight),
+        TRIPS if pocket_pair => Some(HandCategory::Set),
+        TRIPS => Some(HandCategory::Trips),
+        TWO_PAIR => Some(HandCategory::TwoPair),
+        PAIR => Some(pair(hand, board)),
+        _ => None,
+    }
+}
+
+// the hand's one pair on an unpaired board, either a pocket pair or one hole card pairing the board
+fn pair(hand: &Hand, board: &[u8]) -> HandCategory {
+    let mut board_ranks: Vec<u8> = board.iter().map(|card| get_rank(*card)).collect();
+    board_ranks.sort_unstable_by(|a, b| b.cmp(a));
+
+    if get_rank(hand[0]) == get_rank(hand[1]) {
+        return if get_rank(hand[0]) > board_ranks[0] {
+            HandCategory::Overpair
+        } else {
+            HandCategory::Underpair
+        };
+    }
+
+    let paired = hand
+        .iter()
+        .find_map(|card| board_ranks.iter().position(|rank| *rank == get_rank(*card)));
+    match paired {
+        Some(0) => {
+            let kicker = hand
+                .iter()
+                .map(|card| get_rank(*card))
+                .find(|rank| *rank != board_ranks[0])
+                .unwrap_or_default();
+            // kickers are ranked among the cards not already on the board, so an A high board's top kicker is a K
+            let better_kickers = (kicker + 1..13)
+                .filter(|rank| !board_ranks.contains(rank))
+                .count();
+            match better_kickers {
+                0 => HandCategory::TopPairTopKicker,
+                1..=3 => HandCategory::TopPairGoodKicker,
+                _ => HandCategory::TopPairWeakKicker,
+            }
+        }
+        Some(1) => HandCategory::MiddlePair,
+        _ => HandCategory::BottomPair,
+    }
+}
+
+fn draws(hand: &Hand, board: &[u8]) -> Vec<HandCategory> {
+    let mut draws = vec![];
+    let flop = board.len() == 3;
+
+    for suit in 0u8..4 {
+        let in_hand = hand.iter().filter(|card| get_suit(**card) == suit).count();
+        let on_board = board.iter().filter(|card| get_suit(**card) == suit).count();
+        match (in_hand, in_hand + on_board) {
+            (0, _) => {}
+            (_, 4) => draws.push(HandCategory::FlushDraw),
+            (_, 3) if flop => draws.push(HandCategory::BackdoorFlushDraw),
+            _ => {}
+        }
+    }
+
+    let board_mask = rank_mask(board.iter());
+    let mask = board_mask | rank_mask(hand.iter());
+    // a card only counts as an out when the straight it makes uses the hole cards
+    let completes = |ranks: u16| {
+        ranks & mask == 0 && has_straight(mask | ranks) && !has_straight(board_mask | ranks)
+    };
+
+    let outs = (0..13).filter(|rank| completes(1 << rank)).count();
+    match outs {
+        0 if flop => {
+            let backdoor =
+                (0..13).any(|high| (0..high).any(|low| completes((1 << high) | (1 << low))));
+            if backdoor {
+                draws.push(HandCategory::BackdoorStraightDraw);
+            }
+        }
+        0 => {}
+        1 => draws.push(HandCategory::Gutshot),
+        _ => draws.push(HandCategory::Oesd),
+    }
+    draws
+}
+
+// holding one card of what the best flush or straight on this board needs, without making it
+fn blockers(hand: &Hand, board: &[u8]) -> Vec<HandCategory> {
+    let mut blockers = vec![];
+
+    for suit in 0u8..4 {
+        let on_board = board.iter().filter(|card| get_suit(**card) == suit).count();
+        let in_hand: Vec<u8> = hand
+            .iter()
+            .copied()
+            .filter(|card| get_suit(*card) == suit)
+            .collect();
+        let nut_card = (0..13)
+            .rev()
+            .map(|rank| rank * 4 + suit)
+            .find(|card| !board.contains(card));
+        if on_board == 3 && in_hand.len() == 1 && nut_card == Some(in_hand[0]) {
+            blockers.push(HandCategory::NutFlushBlocker);
+        }
+    }
+
+    // the highest straight the board leaves open, in the same ace low layout as has_straight. when it needs two hole
+    // cards, holding just one of them blocks it
+    let extend = |mask: u16| (mask << 1) | ((mask >> 12) & 1);
+    let board_mask = extend(rank_mask(board.iter()));
+    let hand_mask = extend(rank_mask(hand.iter()));
+    let nut_straight = (0..10)
+        .rev()
+        .map(|low| (0b11111u16 << low) & !board_mask)
+        .find(|missing| missing.count_ones() <= 2);
+    if let Some(missing) = nut_straight {
+        if missing.count_ones() == 2 && (missing & hand_mask).count_ones() == 1 {
+            blockers.push(HandCategory::NutStraightBlocker);
+        }
+    }
+    blockers
+}
+
+fn rank_mask<'a>(cards: impl Iterator<Item = &'a u8>) -> u16 {
+    cards.fold(0, |mask, card| mask | (1 << get_rank(*card)))
+}
+
+// the ace also plays low, so it's copied below the deuce before looking for five ranks in a row
+fn has_straight(mask: u16) -> bool {
+    let extended = (mask << 1) | ((mask >> 12) & 1);
+    (0..10).any(|low| (extended >> low) & 0b11111 == 0b11111)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::game::trained_game;
+    use crate::nodes::node::ResultFilter;
+    use crate::ranges::utility::parse_board;
+
+    fn categories(hand: &str, board: &str) -> Vec<HandCategory> {
+        classify(
+            &parse_combination(hand).unwrap(),
+            &parse_board(board).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_made_hands() {
+        use HandCategory::*;
+        let board = "ks,7h,2d";
+        assert_eq!(categories("AhKd", board), vec![TopPairTopKicker]);
+        assert_eq!(categories("KhQc", board), vec![TopPairGoodKicker]);
+        assert_eq!(categories("Kc3c", board), vec![TopPairWeakKicker]);
+        assert_eq!(categories("AdAc", board), vec![Overpair]);
+        assert_eq!(categories("7d7c", board), vec![Set]);
+        assert_eq!(categories("7c2c", board), vec![TwoPair]);
+        assert_eq!(categories("7c6c", board), vec![MiddlePair]);
+        assert_eq!(
+            categories("7s6s", board),
+            vec![MiddlePair, BackdoorFlushDraw]
+        );
+        assert_eq!(categories("5d5c", board), vec![Underpair]);
+        assert_eq!(categories("Qc8d", board), vec![Air]);
+
+        // a pair in the hole on a paired board is two pair, not a pair of its own
+        assert_eq!(categories("5d5c", "ks,7h,7d"), vec![TwoPair]);
+        assert_eq!(categories("Kd5c", "ks,7h,7d"), vec![TwoPair]);
+        assert_eq!(categories("Ad5c", "ks,7h,7d"), vec![Air]);
+        assert_eq!(categories("5d5c", "ks,kh,7d,7c"), vec![Air]);
+
+        assert_eq!(categories("AcKc", "as,kd,2c,2h,2s"), vec![FullHouse]);
+        assert_eq!(categories("6h5h", "7h,4s,3d,kc,qs"), vec![Straight]);
+    }
+
+    #[test]
+    fn test_draws() {
+        use HandCategory::*;
+        assert_eq!(categories("9c8c", "ts,7h,2d"), vec![Oesd]);
+        assert_eq!(categories("9c6c", "ts,7h,2d"), vec![Gutshot]);
+        assert_eq!(
+            categories("AhQh", "kh,7h,2d"),
+            vec![FlushDraw, BackdoorStraightDraw]
+        );
+        assert_eq!(
+            categories("QhJh", "ks,7h,2d"),
+            vec![BackdoorFlushDraw, BackdoorStraightDraw]
+        );
+        // no draws are left on the river
+        assert_eq!(categories("9c8c", "ts,7h,2d,3c,kd"), vec![Air]);
+    }
+
+    #[test]
+    fn test_blockers() {
+        use HandCategory::*;
+        assert_eq!(
+            categories("AhQc", "kh,7h,2h,4d,9c"),
+            vec![Air, NutFlushBlocker]
+        );
+        // with the ace on the board the king is the nut flush card
+        assert_eq!(
+            categories("Kh9c", "ah,7h,2h,4d,kd"),
+            vec![MiddlePair, NutFlushBlocker]
+        );
+        // two hearts make the flush, that isn't a blocker any more
+        assert_eq!(categories("AhQh", "kh,7h,2h,4d"), vec![Flush]);
+        // on a four flush board the lone ace already makes the nut flush
+        assert_eq!(categories("Ah9c", "kh,7h,2h,4h"), vec![Flush]);
+
+        // kq makes the nut straight on t j 9 2 5, holding only one of them blocks it
+        assert_eq!(
+            categories("Ks3c", "th,jd,2c,9s,5s"),
+            vec![Air, NutStraightBlocker]
+        );
+        assert_eq!(categories("KsQc", "th,jd,2c,9s,5s"), vec![Straight]);
+    }
+
+    #[test]
+    fn test_breakdown_at_river_root() {
+        let board = parse_board("as,ah,ac,kd,2s").unwrap();
+        let game = trained_game("as,ah,ac,kd,2s", "QQ,KK", "QQ,KK,JJ");
+        let root = game.results(&ResultFilter::default()).unwrap().node_results;
+
+        // every combo makes a full house with the aces
+        let breakdown = category_breakdown(&root, &board).unwrap();
+        assert_eq!(breakdown.len(), 1);
+        assert_eq!(breakdown[0].category, HandCategory::FullHouse);
+        assert!(breakdown[0].ev.is_some());
+        assert!((breakdown[0].action_frequencies.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+}
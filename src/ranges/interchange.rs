@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+
+use rust_poker::constants::RANK_TO_CHAR;
+use serde::{Deserialize, Serialize};
+
+use super::combination::{Board, Hand};
+use super::parser::{parse_player_range, NamedRanges};
+use super::utility::{
+    check_hand_overlap, combination_label, get_rank, get_suit, parse_combination,
+};
+use crate::cfr::aggregation::action_label;
+use crate::error::SolverError;
+use crate::nodes::node::NodeResult;
+
+// pio writes weights as fractions after a colon, AKs:0.45,AhKh:0.5. gto+ wraps hands sharing a weight in
+// percentage tags, [45]AKs,AQs[/45]. a hand without a weight is in the range in full for both
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RangeTextFormat {
+    Pio,
+    GtoPlus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRange {
+    pub action: String,
+    pub range: String,
+}
+
+// weights below this are written as if the hand weren't in the range at all
+const MIN_WEIGHT: f32 = 0.0005;
+
+// the acting player's range for every action at the node, each combo weighted by how often it reaches the node and
+// then takes the action
+pub fn strategy_ranges(
+    node: &NodeResult,
+    board: &Board,
+    format: RangeTextFormat,
+) -> Result<Vec<ActionRange>, SolverError> {
+    let (actions, sizings, hand_actions) =
+        match (&node.action_list, &node.bet_sizings, &node.hand_actions) {
+            (Some(actions), Some(sizings), Some(hand_actions)) => (actions, sizings, hand_actions),
+            _ => {
+                return Err(SolverError::InvalidMessage(
+                    "only action nodes with exported strategies have ranges per action".to_string(),
+                ))
+            }
+        };
+
+    let hands = hand_actions
+        .iter()
+        .map(|hand| parse_combination(&hand.combination))
+        .collect::<Result<Vec<Hand>, SolverError>>()?;
+
+    Ok(actions
+        .iter()
+        .zip(sizings.iter())
+        .enumerate()
+        .map(|(i, (action, sizing))| {
+            let weights: Vec<(Hand, f32)> = hands
+                .iter()
+                .zip(hand_actions.iter())
+                .map(|(hand, actions)| (*hand, actions.frequency * actions.action_frequency[i]))
+                .collect();
+            ActionRange {
+                action: action_label(*action, *sizing),
+                range: write_range(&weights, board, format),
+            }
+        })
+        .collect())
+}
+
+// hands are written as their class, AKs, when every combo of it the board leaves is in the range at the same weight,
+// and combo by combo otherwise
+pub fn write_range(weights: &[(Hand, f32)], board: &Board, format: RangeTextFormat) -> String {
+    let mut classes: BTreeMap<(u8, u8, u8), Vec<(Hand, f32)>> = BTreeMap::new();
+    for (hand, weight) in weights.iter().filter(|(_, w)| *w >= MIN_WEIGHT) {
+        classes
+            .entry(class_key(hand))
+            .or_default()
+            .push((*hand, weight.min(1.0)));
+    }
+
+    let mut entries = vec![];
+    // strongest class first, the way ranges are usually written
+    for (key, combos) in classes.iter().rev() {
+        let first = combos[0].1;
+        let whole_class = combos.len() == class_combos(*key, board)
+            && combos.iter().all(|(_, w)| (w - first).abs() < MIN_WEIGHT);
+
+        if whole_class {
+            entries.push((class_label(*key), first));
+        } else {
+            entries.extend(
+                combos
+                    .iter()
+                    .map(|(hand, weight)| (combination_label(hand), *weight)),
+            );
+        }
+    }
+
+    match format {
+        RangeTextFormat::Pio => entries
+            .iter()
+            .map(|(hand, weight)| {
+                if *weight > 1.0 - MIN_WEIGHT {
+                    hand.clone()
+                } else {
+                    format!("{}:{}", hand, format_number(*weight, 3))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(","),
+        RangeTextFormat::GtoPlus => {
+            let mut groups: Vec<(String, Vec<String>)> = vec![];
+            for (hand, weight) in entries.iter() {
+                let percent = format_number(weight * 100.0, 2);
+                match groups.iter_mut().find(|(p, _)| *p == percent) {
+                    Some((_, hands)) => hands.push(hand.clone()),
+                    None => groups.push((percent, vec![hand.clone()])),
+                }
+            }
+
+            groups
+                .iter()
+                .map(|(percent, hands)| {
+                    if percent == "100" {
+                        hands.join(",")
+                    } else {
+                        format!("[{}]{}[/{}]", percent, hands.join(","), percent)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+    }
+}
+
+// turns pio or gto+ range text into the syntax construct_starting_range_from_string reads, anything already
+// written that way passes through unchanged. gto+ group percentages become fractions on each hand
+pub fn import_range(text: &str) -> Result<String, SolverError> {
+    let mut tokens = vec![];
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        match rest.find('[') {
+            None => {
+                push_tokens(rest, None, &mut tokens)?;
+                break;
+            }
+            Some(open) => {
+                push_tokens(&rest[..open], None, &mut tokens)?;
+                let after = &rest[open + 1..];
+
+                let close = after.find(']').ok_or_else(|| unclosed(text))?;
+                let weight = parse_weight(&after[..close], 100.0)? / 100.0;
+                let tag = format!("[/{}]", &after[..close]);
+                let group = &after[close + 1..];
+                let end = group.find(&tag).ok_or_else(|| unclosed(text))?;
+
+                push_tokens(&group[..end], Some(weight), &mut tokens)?;
+                rest = &group[end + tag.len()..];
+            }
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err(SolverError::InvalidRange(format!(
+            "'{}' has no hands",
+            text
+        )));
+    }
+    Ok(tokens.join(","))
+}
+
+fn push_tokens(
+    text: &str,
+    group_weight: Option<f32>,
+    tokens: &mut Vec<String>,
+) -> Result<(), SolverError> {
+    for token in text.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        let (hands, weight) = match (token.split_once(':'), group_weight) {
+            (Some(_), Some(_)) => {
+                return Err(SolverError::InvalidRange(format!(
+                    "'{}' has a weight inside a weighted group",
+                    token
+                )))
+            }
+            (Some((hands, weight)), None) => (hands.trim(), Some(parse_weight(weight, 1.0)?)),
+            (None, weight) => (token, weight),
+        };
+
+        match weight {
+            None => tokens.push(hands.to_string()),
+            Some(w) if w > 1.0 - MIN_WEIGHT => tokens.push(hands.to_string()),
+            Some(w) if w < MIN_WEIGHT => {}
+            Some(w) => tokens.push(format!("{}:{}", hands, format_number(w, 4))),
+        }
+    }
+    Ok(())
+}
+
+// named ranges are expanded up front against the board, so everything downstream reads plain range text
+pub fn resolve_named_ranges(
+    player: &str,
+    text: &str,
+    names: &NamedRanges,
+    board: &Board,
+) -> Result<String, SolverError> {
+    if !text.contains('$') {
+        return Ok(text.to_string());
+    }
+    let weights: Vec<(Hand, f32)> = parse_player_range(player, text, names)?
+        .hands()
+        .into_iter()
+        .filter(|(hand, _)| !check_hand_overlap(*hand, board))
+        .collect();
+    Ok(write_range(&weights, board, RangeTextFormat::Pio))
+}
+
+fn parse_weight(weight: &str, max: f32) -> Result<f32, SolverError> {
+    match weight.trim().parse::<f32>() {
+        Ok(w) if (0.0..=max).contains(&w) => Ok(w),
+        _ => Err(SolverError::InvalidRange(format!(
+            "weight '{}' should be a number between 0 and {}",
+            weight, max
+        ))),
+    }
+}
+
+fn unclosed(text: &str) -> SolverError {
+    SolverError::InvalidRange(format!(
+        "'{}' has a weight group that is never closed",
+        text
+    ))
+}
+
+// high rank, low rank, then 2 for pairs, 1 for suited and 0 for offsuit so classes sort by strength
+fn class_key(hand: &Hand) -> (u8, u8, u8) {
+    let (high, low) = if get_rank(hand[0]) >= get_rank(hand[1]) {
+        (hand[0], hand[1])
+    } else {
+        (hand[1], hand[0])
+    };
+    let kind = if get_rank(high) == get_rank(low) {
+        2
+    } else if get_suit(high) == get_suit(low) {
+        1
+    } else {
+        0
+    };
+    (get_rank(high), get_rank(low), kind)
+}
+
+fn class_label((high, low, kind): (u8, u8, u8)) -> String {
+    let mut label = format!(
+        "{}{}",
+        RANK_TO_CHAR[usize::from(high)],
+        RANK_TO_CHAR[usize::from(low)]
+    );
+    match kind {
+        1 => label.push('s'),
+        0 => label.push('o'),
+        _ => {}
+    }
+    label
+}
+
+// how many combos of the class are left once the board cards are removed
+fn class_combos(key: (u8, u8, u8), board: &Board) -> usize {
+    (0u8..52)
+        .flat_map(|a| (0..a).map(move |b| [a, b]))
+        .filter(|hand| class_key(hand) == key && !check_hand_overlap(*hand, board))
+        .count()
+}
+
+fn format_number(value: f32, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::{construct_starting_range_from_string, parse_board};
+
+    fn combos(labels: &[&str], weight: f32) -> Vec<(Hand, f32)> {
+        labels
+            .iter()
+            .map(|label| (parse_combination(label).unwrap(), weight))
+            .collect()
+    }
+
+    #[test]
+    fn test_whole_classes_collapse() {
+        let board = parse_board("as,7d,2c").unwrap();
+        // the board leaves three AK suited combos
+        let mut weights = combos(&["AhKh", "AcKc", "AdKd"], 0.45);
+        weights.extend(combos(&["QhQs", "QdQc"], 1.0));
+        weights.extend(combos(&["7h6h"], 0.25));
+
+        assert_eq!(
+            write_range(&weights, &board, RangeTextFormat::Pio),
+            "AKs:0.45,QhQs,QdQc,7h6h:0.25"
+        );
+        assert_eq!(
+            write_range(&weights, &board, RangeTextFormat::GtoPlus),
+            "[45]AKs[/45],QhQs,QdQc,[25]7h6h[/25]"
+        );
+    }
+
+    #[test]
+    fn test_import_formats() {
+        assert_eq!(
+            import_range("AKs:0.45,AhKh:0.5,QQ").unwrap(),
+            "AKs:0.45,AhKh:0.5,QQ"
+        );
+        assert_eq!(
+            import_range("QQ,[37.5]AKs, AQs[/37.5],[0]72o[/0]").unwrap(),
+            "QQ,AKs:0.375,AQs:0.375"
+        );
+        assert_eq!(import_range("22+,AKs@50").unwrap(), "22+,AKs@50");
+
+        assert!(import_range("AKs:1.5").is_err());
+        assert!(import_range("[50]AKs").is_err());
+        assert!(import_range("[50]AKs:0.5[/50]").is_err());
+    }
+
+    #[test]
+    fn test_named_ranges_resolve() {
+        let board = parse_board("as,7d,2c").unwrap();
+        let mut names = NamedRanges::new();
+        names.insert("value".to_string(), "QQ+,AKs".to_string());
+
+        assert_eq!(
+            resolve_named_ranges("OOP", "$value,!KK,76s:0.5", &names, &board).unwrap(),
+            "AA,AKs,QQ,76s:0.5"
+        );
+        assert_eq!(resolve_named_ranges("OOP", "22+", &names, &board).unwrap(), "22+");
+        assert!(resolve_named_ranges("OOP", "$bluffs", &names, &board).is_err());
+    }
+
+    #[test]
+    fn test_export_imports_back() {
+        let board = parse_board("as,7d,2c").unwrap();
+        let mut weights = combos(&["AhKh", "AcKc", "AdKd"], 0.5);
+        weights.extend(combos(
+            &["JsJh", "JcJs", "JdJs", "JcJh", "JdJh", "JdJc"],
+            1.0,
+        ));
+
+        for format in [RangeTextFormat::Pio, RangeTextFormat::GtoPlus] {
+            let text = write_range(&weights, &board, format);
+            let range =
+                construct_starting_range_from_string(import_range(&text).unwrap(), &board).unwrap();
+            assert!(text.contains("JJ") && !text.contains("Jh"));
+            assert_eq!(range.len(), 9);
+        }
+    }
+}
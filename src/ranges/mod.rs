@@ -1,3 +1,11 @@
 pub mod combination;
+pub mod equity;
+pub mod flops;
+pub mod hand_category;
+pub mod interchange;
+pub mod parser;
+pub mod preflop_equity;
 pub mod range_manager;
+pub mod texture;
 pub mod utility;
+pub mod validation;
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use rust_poker::constants::{RANK_TO_CHAR, SUIT_TO_CHAR};
+
+use super::combination::Hand;
+use crate::error::SolverError;
+
+// range text is a comma separated list of entries, a later entry overrides the weight an earlier one gave a combo
+//   AA, AKs, KQo, T9           whole classes, without s or o a class is both suited and offsuit
+//   22+, A2s+, QQ-88, K9o-K6o  classes up to the top, or every class in a span
+//   AhKh, AsKx, AxKx           combos, x standing for any suit
+//   random                     every combo
+//   $name                      a named range
+// an entry can end in a whole percentage (AKs@50) or a fraction (AKo:0.375), a weight on a named range scales all of
+// it. a leading ! takes the entry's combos back out of everything before it, so 22+,!55 is every pair but fives
+pub type NamedRanges = HashMap<String, String>;
+
+// combos are kept higher card first, any combo not in the map has no weight
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedRange {
+    weights: BTreeMap<Hand, f32>,
+}
+
+impl ParsedRange {
+    // weights taken as they are, e.g. from a solved node, combos without weight are left out
+    pub fn from_weights(weights: impl IntoIterator<Item = (Hand, f32)>) -> Self {
+        let mut range = ParsedRange::default();
+        for (hand, weight) in weights {
+            range.set(hand, weight);
+        }
+        range
+    }
+
+    pub fn hands(&self) -> Vec<(Hand, f32)> {
+        self.weights
+            .iter()
+            .map(|(hand, weight)| (*hand, *weight))
+            .collect()
+    }
+
+    pub fn weight(&self, hand: &Hand) -> f32 {
+        self.weights.get(&ordered(hand)).copied().unwrap_or(0.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    // every combo of either range, at the larger of its two weights
+    pub fn union(&self, other: &ParsedRange) -> ParsedRange {
+        let mut weights = self.weights.clone();
+        for (hand, weight) in other.weights.iter() {
+            let entry = weights.entry(*hand).or_insert(0.0);
+            *entry = entry.max(*weight);
+        }
+        ParsedRange { weights }
+    }
+
+    fn set(&mut self, hand: Hand, weight: f32) {
+        if weight > 0.0 {
+            self.weights.insert(ordered(&hand), weight);
+        } else {
+            self.weights.remove(&ordered(&hand));
+        }
+    }
+}
+
+pub fn parse_range(text: &str) -> Result<ParsedRange, SolverError> {
+    parse_range_with(text, &NamedRanges::new())
+}
+
+pub fn parse_range_with(text: &str, names: &NamedRanges) -> Result<ParsedRange, SolverError> {
+    parse_entries(text, names, &mut vec![])
+}
+
+// the same as parse_range, with the player the range belongs to at the front of any error
+pub fn parse_player_range(
+    player: &str,
+    text: &str,
+    names: &NamedRanges,
+) -> Result<ParsedRange, SolverError> {
+    parse_range_with(text, names).map_err(|e| match e {
+        SolverError::InvalidRange(reason) => {
+            SolverError::InvalidRange(format!("{} range: {}", player, reason))
+        }
+        e => e,
+    })
+}
+
+// including holds the named ranges being expanded, so one that ends up including itself is caught
+fn parse_entries(
+    text: &str,
+    names: &NamedRanges,
+    including: &mut Vec<String>,
+) -> Result<ParsedRange, SolverError> {
+    if text.trim().is_empty() {
+        return Err(invalid(format!("'{}' has no hands", text)));
+    }
+
+    let mut range = ParsedRange::default();
+    for entry in text.split(',').map(|e| e.trim()) {
+        if entry.is_empty() {
+            return Err(invalid(format!(
+                "'{}' has an empty entry, check for doubled or trailing commas",
+                text
+            )));
+        }
+
+        let (remove, body) = match entry.strip_prefix('!') {
+            Some(body) => (true, body.trim()),
+            None => (false, entry),
+        };
+        let (body, weight) = split_weight(body, entry)?;
+        if remove && weight.is_some() {
+            return Err(invalid(format!(
+                "'{}' takes hands out, it can't have a weight as well",
+                entry
+            )));
+        }
+        let weight = weight.unwrap_or(1.0);
+
+        let hands = match body.strip_prefix('$') {
+            Some(name) => named_range(name, names, including)?.hands(),
+            None => expand_entry(body)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "unknown entry '{}', expected hands like AA, AKs, KQo, 22+, A2s+, K9o-K6o, AhKh, AxKx or $name",
+                        body
+                    ))
+                })?
+                .into_iter()
+                .map(|hand| (hand, 1.0))
+                .collect(),
+        };
+
+        for (hand, hand_weight) in hands {
+            if remove {
+                range.set(hand, 0.0);
+            } else {
+                range.set(hand, hand_weight * weight);
+            }
+        }
+    }
+    Ok(range)
+}
+
+fn named_range(
+    name: &str,
+    names: &NamedRanges,
+    including: &mut Vec<String>,
+) -> Result<ParsedRange, SolverError> {
+    let text = names
+        .get(name)
+        .ok_or_else(|| invalid(format!("'${}' isn't a named range", name)))?;
+    if including.iter().any(|n| n == name) {
+        return Err(invalid(format!("named range '{}' includes itself", name)));
+    }
+
+    including.push(name.to_string());
+    let range = parse_entries(text, names, including).map_err(|e| match e {
+        SolverError::InvalidRange(reason) => invalid(format!("in '${}', {}", name, reason)),
+        e => e,
+    });
+    including.pop();
+    range
+}
+
+fn split_weight<'a>(body: &'a str, entry: &str) -> Result<(&'a str, Option<f32>), SolverError> {
+    if let Some((hands, percent)) = body.split_once('@') {
+        return match percent.trim().parse::<u32>() {
+            Ok(p) if p <= 100 => Ok((hands.trim(), Some(p as f32 / 100.0))),
+            Ok(p) => Err(invalid(format!(
+                "'{}' has weight {}, weights are percentages between 0 and 100",
+                entry, p
+            ))),
+            Err(_) => Err(invalid(format!(
+                "'{}' has weight '{}', expected a whole percentage between 0 and 100",
+                entry, percent
+            ))),
+        };
+    }
+
+    if let Some((hands, fraction)) = body.split_once(':') {
+        return match fraction.trim().parse::<f32>() {
+            Ok(w) if (0.0..=1.0).contains(&w) => Ok((hands.trim(), Some(w))),
+            _ => Err(invalid(format!(
+                "'{}' has weight '{}', expected a fraction between 0 and 1",
+                entry, fraction
+            ))),
+        };
+    }
+
+    Ok((body, None))
+}
+
+fn expand_entry(body: &str) -> Option<Vec<Hand>> {
+    if body.eq_ignore_ascii_case("random") {
+        return Some(
+            (0u8..52)
+                .flat_map(|a| (0..a).map(move |b| [a, b]))
+                .collect(),
+        );
+    }
+
+    let classes = if let Some((from, to)) = body.split_once('-') {
+        span(&parse_class(from)?, &parse_class(to)?)?
+    } else if let Some(class) = body.strip_suffix('+') {
+        up_to_top(&parse_class(class)?)
+    } else if let Some(class) = parse_class(body) {
+        vec![class]
+    } else {
+        return specific_combos(body);
+    };
+
+    Some(classes.iter().flat_map(|class| class.combos()).collect())
+}
+
+struct HandClass {
+    high: u8,
+    low: u8,
+    suitedness: Option<char>,
+}
+
+impl HandClass {
+    fn combos(&self) -> Vec<Hand> {
+        let mut combos = vec![];
+        for high_suit in 0u8..4 {
+            for low_suit in 0u8..4 {
+                let keep = if self.high == self.low {
+                    high_suit > low_suit
+                } else {
+                    match self.suitedness {
+                        Some('s') => high_suit == low_suit,
+                        Some(_) => high_suit != low_suit,
+                        None => true,
+                    }
+                };
+                if keep {
+                    combos.push([self.high * 4 + high_suit, self.low * 4 + low_suit]);
+                }
+            }
+        }
+        combos
+    }
+}
+
+fn rank_of(c: char) -> Option<u8> {
+    RANK_TO_CHAR
+        .iter()
+        .position(|r| r.eq_ignore_ascii_case(&c))
+        .map(|r| r as u8)
+}
+
+fn suit_of(c: char) -> Option<u8> {
+    SUIT_TO_CHAR
+        .iter()
+        .position(|s| s.eq_ignore_ascii_case(&c))
+        .map(|s| s as u8)
+}
+
+fn parse_class(hands: &str) -> Option<HandClass> {
+    let chars: Vec<char> = hands.trim().chars().collect();
+    if chars.len() != 2 && chars.len() != 3 {
+        return None;
+    }
+
+    let first = rank_of(chars[0])?;
+    let second = rank_of(chars[1])?;
+    let suitedness = match chars.get(2) {
+        None => None,
+        Some('s') => Some('s'),
+        Some('o') => Some('o'),
+        Some(_) => return None,
+    };
+
+    // pairs can't be suited or offsuit
+    if first == second && suitedness.is_some() {
+        return None;
+    }
+
+    Some(HandClass {
+        high: first.max(second),
+        low: first.min(second),
+        suitedness,
+    })
+}
+
+// 22+ is every pair, A2s+ runs the low card up to just under the high one
+fn up_to_top(class: &HandClass) -> Vec<HandClass> {
+    if class.high == class.low {
+        return (class.high..13)
+            .map(|rank| HandClass {
+                high: rank,
+                low: rank,
+                suitedness: None,
+            })
+            .collect();
+    }
+    (class.low..class.high)
+        .map(|low| HandClass {
+            high: class.high,
+            low,
+            suitedness: class.suitedness,
+        })
+        .collect()
+}
+
+// pair spans (QQ-88) move both ranks, other spans (K9o-K6o) keep the top card and suitedness fixed
+fn span(from: &HandClass, to: &HandClass) -> Option<Vec<HandClass>> {
+    let from_pair = from.high == from.low;
+    let to_pair = to.high == to.low;
+
+    if from_pair && to_pair {
+        let (bottom, top) = (from.high.min(to.high), from.high.max(to.high));
+        return Some(
+            (bottom..=top)
+                .map(|rank| HandClass {
+                    high: rank,
+                    low: rank,
+                    suitedness: None,
+                })
+                .collect(),
+        );
+    }
+    if from_pair || to_pair || from.high != to.high || from.suitedness != to.suitedness {
+        return None;
+    }
+
+    let (bottom, top) = (from.low.min(to.low), from.low.max(to.low));
+    Some(
+        (bottom..=top)
+            .map(|low| HandClass {
+                high: from.high,
+                low,
+                suitedness: from.suitedness,
+            })
+            .collect(),
+    )
+}
+
+// AhKh, or with x for any suit, AsKx is the four kings with the ace of spades
+fn specific_combos(hands: &str) -> Option<Vec<Hand>> {
+    let chars: Vec<char> = hands.chars().collect();
+    if chars.len() != 4 {
+        return None;
+    }
+
+    let suits = |c: char| -> Option<Vec<u8>> {
+        if c.eq_ignore_ascii_case(&'x') {
+            Some((0..4).collect())
+        } else {
+            suit_of(c).map(|s| vec![s])
+        }
+    };
+    let (first_rank, second_rank) = (rank_of(chars[0])?, rank_of(chars[2])?);
+    let (first_suits, second_suits) = (suits(chars[1])?, suits(chars[3])?);
+
+    let mut combos = BTreeSet::new();
+    for first in first_suits.iter() {
+        for second in second_suits.iter() {
+            let hand = [first_rank * 4 + first, second_rank * 4 + second];
+            if hand[0] != hand[1] {
+                combos.insert(ordered(&hand));
+            }
+        }
+    }
+
+    if combos.is_empty() {
+        None
+    } else {
+        Some(combos.into_iter().collect())
+    }
+}
+
+// combos are keyed with the higher card first
+pub(crate) fn ordered(hand: &Hand) -> Hand {
+    if hand[0] > hand[1] {
+        *hand
+    } else {
+        [hand[1], hand[0]]
+    }
+}
+
+fn invalid(reason: String) -> SolverError {
+    SolverError::InvalidRange(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::parse_combination;
+
+    fn weight(range: &ParsedRange, label: &str) -> f32 {
+        range.weight(&parse_combination(label).unwrap())
+    }
+
+    #[test]
+    fn test_classes_and_spans() {
+        assert_eq!(parse_range("AA").unwrap().len(), 6);
+        assert_eq!(parse_range("AKs").unwrap().len(), 4);
+        assert_eq!(parse_range("KQo").unwrap().len(), 12);
+        assert_eq!(parse_range("T9").unwrap().len(), 16);
+        assert_eq!(parse_range("22+").unwrap().len(), 13 * 6);
+        assert_eq!(parse_range("A2s+").unwrap().len(), 12 * 4);
+        assert_eq!(parse_range("QQ-88").unwrap().len(), 5 * 6);
+        assert_eq!(parse_range("K9o-K6o").unwrap().len(), 4 * 12);
+        assert_eq!(parse_range("random").unwrap().len(), 1326);
+    }
+
+    #[test]
+    fn test_specific_combos_and_wildcards() {
+        let range = parse_range("AhKh").unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(weight(&range, "KhAh"), 1.0);
+
+        assert_eq!(parse_range("AxKx").unwrap().len(), 16);
+        assert_eq!(parse_range("AsKx").unwrap().len(), 4);
+        assert_eq!(parse_range("AsAx").unwrap().len(), 3);
+        assert_eq!(parse_range("AxAx").unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_weights_and_removal() {
+        let range = parse_range("AK:0.375,AKs@50,AhKh:1").unwrap();
+        assert_eq!(weight(&range, "AsKd"), 0.375);
+        assert_eq!(weight(&range, "AsKs"), 0.5);
+        assert_eq!(weight(&range, "AhKh"), 1.0);
+
+        let range = parse_range("22+,!55,A2s+,!AsKx").unwrap();
+        assert_eq!(range.len(), 12 * 6 + 12 * 4 - 1);
+        assert_eq!(weight(&range, "5h5s"), 0.0);
+        assert_eq!(weight(&range, "AsKs"), 0.0);
+        assert_eq!(weight(&range, "AhKh"), 1.0);
+
+        assert!(parse_range("77@0").unwrap().is_empty());
+
+        let union = parse_range("AK:0.5")
+            .unwrap()
+            .union(&parse_range("AKs,QQ").unwrap());
+        assert_eq!(weight(&union, "AsKs"), 1.0);
+        assert_eq!(weight(&union, "AsKd"), 0.5);
+        assert_eq!(union.len(), 16 + 6);
+    }
+
+    #[test]
+    fn test_named_ranges() {
+        let mut names = NamedRanges::new();
+        names.insert("value".to_string(), "QQ+,AKs".to_string());
+        names.insert("polar".to_string(), "$value,76s:0.5".to_string());
+        names.insert("loop".to_string(), "AA,$loop".to_string());
+
+        let range = parse_range_with("$polar:0.5,!AhKh", &names).unwrap();
+        assert_eq!(range.len(), 18 + 4 + 4 - 1);
+        assert_eq!(weight(&range, "QdQc"), 0.5);
+        assert_eq!(weight(&range, "7s6s"), 0.25);
+
+        for text in ["$missing", "$loop"].iter() {
+            assert!(
+                parse_range_with(text, &names).is_err(),
+                "{} should be invalid",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for text in [
+            "", "AA,,KK", "AAs", "AX", "A", "AKx", "K9o-Q6o", "QQ-AKs", "AhAh", "AK@101", "AK@-5",
+            "AK@0.5", "AK@", "AK:1.5", "AK:", "!AK:0.5", "AyKy",
+        ]
+        .iter()
+        {
+            assert!(parse_range(text).is_err(), "{} should be invalid", text);
+        }
+
+        match parse_player_range("OOP", "AA,AKx", &NamedRanges::new()) {
+            Err(SolverError::InvalidRange(reason)) => {
+                assert!(
+                    reason.starts_with("OOP range: unknown entry 'AKx'"),
+                    "{}",
+                    reason
+                )
+            }
+            _ => panic!("AKx should be invalid"),
+        }
+    }
+}
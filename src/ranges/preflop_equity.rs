@@ -0,0 +1,220 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use rust_poker::constants::RANK_TO_CHAR;
+use rust_poker::hand_evaluator::{evaluate, Hand as EvalHand, CARDS};
+
+use super::combination::Hand;
+use super::utility::check_hands_overlap;
+
+pub const STARTING_HANDS: usize = 169;
+
+// one of the 169 preflop hand classes, ranks run 0 for a deuce up to 12 for an ace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StartingHand {
+    pub high: u8,
+    pub low: u8,
+    pub suited: bool,
+}
+
+impl StartingHand {
+    pub fn label(&self) -> String {
+        let high = RANK_TO_CHAR[usize::from(self.high)];
+        let low = RANK_TO_CHAR[usize::from(self.low)];
+        if self.high == self.low {
+            format!("{}{}", high, low)
+        } else if self.suited {
+            format!("{}{}s", high, low)
+        } else {
+            format!("{}{}o", high, low)
+        }
+    }
+
+    // 6 combos for a pair, 4 suited, 12 offsuit
+    pub fn combos(&self) -> Vec<Hand> {
+        let mut combos = vec![];
+        for high_suit in 0u8..4 {
+            for low_suit in 0u8..4 {
+                let keep = if self.high == self.low {
+                    high_suit < low_suit
+                } else {
+                    self.suited == (high_suit == low_suit)
+                };
+                if keep {
+                    combos.push([self.high * 4 + high_suit, self.low * 4 + low_suit]);
+                }
+            }
+        }
+        combos
+    }
+}
+
+// in the usual grid order, AA, AKs, AQs .. 22 row by row with suited hands above the diagonal
+pub fn starting_hands() -> Vec<StartingHand> {
+    let mut hands = vec![];
+    for row in (0u8..13).rev() {
+        for column in (0u8..13).rev() {
+            hands.push(StartingHand {
+                high: row.max(column),
+                low: row.min(column),
+                suited: column < row,
+            });
+        }
+    }
+    hands
+}
+
+// class against class all in equity, estimated by dealing random non overlapping combos of each class and a random
+// board. more samples get closer to enumerating every runout, which is far too slow to do for all 169 x 169 matchups.
+// compatibility is the share of combo pairs that don't share a card, the card removal between two classes
+pub struct PreflopEquity {
+    pub hands: Vec<StartingHand>,
+    equity: Vec<f32>,
+    compatibility: Vec<f32>,
+}
+
+impl PreflopEquity {
+    pub fn sampled(samples: u32, seed: u64) -> Self {
+        let hands = starting_hands();
+        let n = hands.len();
+
+        let rows: Vec<Vec<f32>> = (0..n)
+            .into_par_iter()
+            .map(|hero| {
+                (0..n)
+                    .map(|villain| {
+                        if hero == villain {
+                            0.5
+                        } else {
+                            // every matchup gets its own stream so the table doesn't depend on the thread count
+                            let mut rng = StdRng::seed_from_u64(seed ^ (hero * n + villain) as u64);
+                            matchup_equity(&hands[hero], &hands[villain], samples, &mut rng)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // only one side of each matchup is kept so the table is exactly zero sum
+        let mut equity = vec![0.0; n * n];
+        for hero in 0..n {
+            for villain in 0..n {
+                equity[hero * n + villain] = if hero <= villain {
+                    rows[hero][villain]
+                } else {
+                    1.0 - rows[villain][hero]
+                };
+            }
+        }
+
+        let compatibility = hands
+            .iter()
+            .flat_map(|hero| {
+                hands
+                    .iter()
+                    .map(move |villain| compatibility(hero, villain))
+            })
+            .collect();
+
+        Self {
+            hands,
+            equity,
+            compatibility,
+        }
+    }
+
+    pub fn equity(&self, hero: usize, villain: usize) -> f32 {
+        self.equity[hero * self.hands.len() + villain]
+    }
+
+    pub fn compatibility(&self, hero: usize, villain: usize) -> f32 {
+        self.compatibility[hero * self.hands.len() + villain]
+    }
+}
+
+pub fn matchup_equity(
+    hero: &StartingHand,
+    villain: &StartingHand,
+    samples: u32,
+    rng: &mut impl Rng,
+) -> f32 {
+    let pairs: Vec<(Hand, Hand)> = hero
+        .combos()
+        .into_iter()
+        .flat_map(|h| villain.combos().into_iter().map(move |v| (h, v)))
+        .filter(|(h, v)| !check_hands_overlap(h, v))
+        .collect();
+    if pairs.is_empty() || samples == 0 {
+        return 0.5;
+    }
+
+    let mut won = 0.0;
+    for _ in 0..samples {
+        let (h, v) = pairs[rng.gen_range(0..pairs.len())];
+        let deck: Vec<u8> = (0u8..52)
+            .filter(|card| !h.contains(card) && !v.contains(card))
+            .collect();
+        let board = deck
+            .choose_multiple(rng, 5)
+            .fold(EvalHand::default(), |board, card| {
+                board + CARDS[usize::from(*card)]
+            });
+
+        let hero_rank = evaluate(&(board + CARDS[usize::from(h[0])] + CARDS[usize::from(h[1])]));
+        let villain_rank = evaluate(&(board + CARDS[usize::from(v[0])] + CARDS[usize::from(v[1])]));
+        won += match hero_rank.cmp(&villain_rank) {
+            std::cmp::Ordering::Greater => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Less => 0.0,
+        };
+    }
+    won / samples as f32
+}
+
+fn compatibility(hero: &StartingHand, villain: &StartingHand) -> f32 {
+    let hero_combos = hero.combos();
+    let villain_combos = villain.combos();
+    let disjoint = hero_combos
+        .iter()
+        .flat_map(|h| villain_combos.iter().map(move |v| (h, v)))
+        .filter(|(h, v)| !check_hands_overlap(h, v))
+        .count();
+    disjoint as f32 / (hero_combos.len() * villain_combos.len()) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(label: &str) -> StartingHand {
+        *starting_hands()
+            .iter()
+            .find(|h| h.label() == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_starting_hand_classes() {
+        let hands = starting_hands();
+        assert_eq!(hands.len(), STARTING_HANDS);
+        assert_eq!(hands[0].label(), "AA");
+        assert_eq!(hands[1].label(), "AKs");
+        assert_eq!(hands[13].label(), "AKo");
+        assert_eq!(hands[168].label(), "22");
+        assert_eq!(hands.iter().map(|h| h.combos().len()).sum::<usize>(), 1326);
+
+        assert!((compatibility(&hand("AA"), &hand("AA")) - 1.0 / 6.0).abs() < 1e-6);
+        assert!((compatibility(&hand("AKs"), &hand("AA")) - 0.5).abs() < 1e-6);
+        assert_eq!(compatibility(&hand("KQo"), &hand("72s")), 1.0);
+    }
+
+    #[test]
+    fn test_sampled_matchups() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let aa_kk = matchup_equity(&hand("AA"), &hand("KK"), 4000, &mut rng);
+        assert!((aa_kk - 0.82).abs() < 0.03, "AA vs KK {}", aa_kk);
+        let ak_22 = matchup_equity(&hand("AKo"), &hand("22"), 4000, &mut rng);
+        assert!((ak_22 - 0.47).abs() < 0.03, "AKo vs 22 {}", ak_22);
+    }
+}
@@ -8,6 +8,7 @@ use super::{
     combination::{Board, Combination},
     utility::{board_has_river, board_has_turn, check_card_overlap, check_hand_overlap},
 };
+use crate::error::SolverError;
 
 #[inline(always)]
 fn get_key(board: &Board) -> u64 {
@@ -40,6 +41,7 @@ pub trait RangeManager {
     );
     fn get_next_reach_probs(&self, new_board: &Board, opp_reach_probs: &[f32]) -> Vec<f32>;
     fn get_range_for_board(&self, board: &Board) -> &Vec<Combination>;
+    fn try_get_range_for_board(&self, board: &Board) -> Result<&Vec<Combination>, SolverError>;
     fn get_reach_probs_mapping(&self, board: &Board) -> &Vec<usize>;
     fn get_starting_combinations(&self) -> Vec<Combination>;
 }
@@ -453,6 +455,12 @@ impl RangeManager for IsomorphicRangeManager {
         self.ranges.get(&board_key).unwrap()
     }
 
+    fn try_get_range_for_board(&self, board: &Board) -> Result<&Vec<Combination>, SolverError> {
+        self.ranges
+            .get(&get_key(board))
+            .ok_or(SolverError::UnknownBoard(*board))
+    }
+
     fn get_reach_probs_mapping(&self, board: &Board) -> &Vec<usize> {
         &self.reach_probs_mapping[&get_key(board)]
     }
@@ -733,6 +741,12 @@ impl RangeManager for DefaultRangeManager {
         self.ranges.get(&board_key).unwrap()
     }
 
+    fn try_get_range_for_board(&self, board: &Board) -> Result<&Vec<Combination>, SolverError> {
+        self.ranges
+            .get(&get_key(board))
+            .ok_or(SolverError::UnknownBoard(*board))
+    }
+
     fn get_reach_probs_mapping(&self, board: &Board) -> &Vec<usize> {
         &self.reach_probs_mapping[&get_key(board)]
     }
@@ -771,7 +785,7 @@ mod tests {
     fn test_rm_from_river() {
         let board: Board = [2, 6, 20, 12, 40];
         let starting_combinations =
-            construct_starting_range_from_string("random".to_string(), &board);
+            construct_starting_range_from_string("random".to_string(), &board).unwrap();
 
         let rm = RangeManager::new(starting_combinations, board);
         assert_eq!(rm.starting_combinations.len(), 1081);
@@ -791,7 +805,7 @@ mod tests {
         let board: Board = [2, 6, 20, 12, 52];
         let _board_key = get_key(&board);
         let starting_combinations =
-            construct_starting_range_from_string("random".to_string(), &board);
+            construct_starting_range_from_string("random".to_string(), &board).unwrap();
 
         let rm = RangeManager::new(starting_combinations, board);
         // TODO: check this
@@ -832,7 +846,7 @@ mod tests {
     fn test_rm_from_flop() {
         let board: Board = [2, 6, 20, 52, 52];
         let starting_combinations =
-            construct_starting_range_from_string("random".to_string(), &board);
+            construct_starting_range_from_string("random".to_string(), &board).unwrap();
 
         let rm = RangeManager::new(starting_combinations, board);
         // TODO: check this
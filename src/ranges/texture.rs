@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+
+use super::combination::Board;
+use super::utility::{get_rank, get_suit};
+use crate::error::SolverError;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum HighCard {
+    Low,
+    Middle,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Pairing {
+    Unpaired,
+    Paired,
+    TwoPaired,
+    Trips,
+    FullHouse,
+    Quads,
+}
+
+// flush possible means three or more cards of a suit on a turn or river that isn't monotone
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Suitedness {
+    Rainbow,
+    TwoTone,
+    FlushPossible,
+    Monotone,
+}
+
+// by the most board ranks that fit inside one straight, three is enough for two hole cards to make one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Connectedness {
+    Disconnected,
+    Connected,
+    FourToStraight,
+    StraightOnBoard,
+}
+
+// completed is about the last card dealt, so it's always false on a flop
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardTexture {
+    pub high_card: HighCard,
+    pub pairing: Pairing,
+    pub suitedness: Suitedness,
+    pub connectedness: Connectedness,
+    pub flush_completed: bool,
+    pub straight_completed: bool,
+}
+
+// names match the way the enums serialize
+impl HighCard {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HighCard::Low => "low",
+            HighCard::Middle => "middle",
+            HighCard::Ten => "ten",
+            HighCard::Jack => "jack",
+            HighCard::Queen => "queen",
+            HighCard::King => "king",
+            HighCard::Ace => "ace",
+        }
+    }
+}
+
+impl Pairing {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pairing::Unpaired => "unpaired",
+            Pairing::Paired => "paired",
+            Pairing::TwoPaired => "twoPaired",
+            Pairing::Trips => "trips",
+            Pairing::FullHouse => "fullHouse",
+            Pairing::Quads => "quads",
+        }
+    }
+}
+
+impl Suitedness {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Suitedness::Rainbow => "rainbow",
+            Suitedness::TwoTone => "twoTone",
+            Suitedness::FlushPossible => "flushPossible",
+            Suitedness::Monotone => "monotone",
+        }
+    }
+}
+
+impl Connectedness {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Connectedness::Disconnected => "disconnected",
+            Connectedness::Connected => "connected",
+            Connectedness::FourToStraight => "fourToStraight",
+            Connectedness::StraightOnBoard => "straightOnBoard",
+        }
+    }
+}
+
+impl BoardTexture {
+    // one label per dimension, e.g. aceHigh, unpaired, rainbow, connected
+    pub fn labels(&self) -> Vec<String> {
+        vec![
+            format!("{}High", self.high_card.name()),
+            self.pairing.name().to_string(),
+            self.suitedness.name().to_string(),
+            self.connectedness.name().to_string(),
+        ]
+    }
+}
+
+pub fn classify_board(board: &Board) -> Result<BoardTexture, SolverError> {
+    let cards: Vec<u8> = board.iter().copied().filter(|card| *card != 52).collect();
+    if cards.len() < 3 {
+        return Err(SolverError::InvalidBoard(format!(
+            "a texture needs at least a flop, got {} cards",
+            cards.len()
+        )));
+    }
+    let previous = &cards[..cards.len().saturating_sub(1).max(3)];
+
+    let suits_before = max_suit_count(previous);
+    let suits_now = max_suit_count(&cards);
+    let straight_before = connectedness(previous);
+    let straight_now = connectedness(&cards);
+
+    Ok(BoardTexture {
+        high_card: high_card(&cards),
+        pairing: pairing(&cards),
+        suitedness: if suits_now == cards.len() {
+            Suitedness::Monotone
+        } else {
+            match suits_now {
+                1 => Suitedness::Rainbow,
+                2 => Suitedness::TwoTone,
+                _ => Suitedness::FlushPossible,
+            }
+        },
+        connectedness: straight_now,
+        flush_completed: suits_before < 3 && suits_now >= 3,
+        straight_completed: straight_before < Connectedness::Connected
+            && straight_now >= Connectedness::Connected,
+    })
+}
+
+fn high_card(cards: &[u8]) -> HighCard {
+    match cards
+        .iter()
+        .map(|card| get_rank(*card))
+        .max()
+        .unwrap_or_default()
+    {
+        12 => HighCard::Ace,
+        11 => HighCard::King,
+        10 => HighCard::Queen,
+        9 => HighCard::Jack,
+        8 => HighCard::Ten,
+        5..=7 => HighCard::Middle,
+        _ => HighCard::Low,
+    }
+}
+
+fn pairing(cards: &[u8]) -> Pairing {
+    let mut counts = [0u8; 13];
+    cards
+        .iter()
+        .for_each(|card| counts[usize::from(get_rank(*card))] += 1);
+    let mut counts: Vec<u8> = counts.iter().copied().filter(|c| *c > 1).collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+
+    match counts.as_slice() {
+        [] => Pairing::Unpaired,
+        [4, ..] => Pairing::Quads,
+        [3, 2, ..] | [3, 3, ..] => Pairing::FullHouse,
+        [3, ..] => Pairing::Trips,
+        [2, 2, ..] => Pairing::TwoPaired,
+        _ => Pairing::Paired,
+    }
+}
+
+fn max_suit_count(cards: &[u8]) -> usize {
+    (0u8..4)
+        .map(|suit| cards.iter().filter(|card| get_suit(**card) == suit).count())
+        .max()
+        .unwrap_or_default()
+}
+
+fn connectedness(cards: &[u8]) -> Connectedness {
+    // the ace also plays low, bit 0 is the low ace and bit 13 the high one
+    let mask = cards.iter().fold(0u16, |mask, card| {
+        let rank = get_rank(*card);
+        let mask = mask | (1 << (rank + 1));
+        if rank == 12 {
+            mask | 1
+        } else {
+            mask
+        }
+    });
+
+    let most_in_window = (0..10)
+        .map(|low| ((mask >> low) & 0b11111).count_ones())
+        .max()
+        .unwrap_or_default();
+    match most_in_window {
+        0..=2 => Connectedness::Disconnected,
+        3 => Connectedness::Connected,
+        4 => Connectedness::FourToStraight,
+        _ => Connectedness::StraightOnBoard,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::utility::parse_board;
+
+    fn texture(board: &str) -> BoardTexture {
+        classify_board(&parse_board(board).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_flop_textures() {
+        let qj2 = texture("qs,jh,2h");
+        assert_eq!(qj2.high_card, HighCard::Queen);
+        assert_eq!(qj2.pairing, Pairing::Unpaired);
+        assert_eq!(qj2.suitedness, Suitedness::TwoTone);
+        assert_eq!(qj2.connectedness, Connectedness::Disconnected);
+        assert!(!qj2.flush_completed && !qj2.straight_completed);
+
+        let wheel = texture("as,3h,4c");
+        assert_eq!(wheel.connectedness, Connectedness::Connected);
+        assert_eq!(wheel.suitedness, Suitedness::Rainbow);
+
+        assert_eq!(texture("7s,7h,7c").pairing, Pairing::Trips);
+        assert_eq!(texture("9s,8s,6s").suitedness, Suitedness::Monotone);
+        assert_eq!(texture("9s,8s,6s").high_card, HighCard::Middle);
+    }
+
+    #[test]
+    fn test_completion_on_later_streets() {
+        let turn = texture("qs,jh,2h,th");
+        assert_eq!(turn.suitedness, Suitedness::FlushPossible);
+        assert!(turn.flush_completed);
+        assert!(turn.straight_completed);
+        assert_eq!(turn.connectedness, Connectedness::Connected);
+
+        let river = texture("qs,jh,2h,th,9c");
+        assert!(!river.flush_completed);
+        assert!(!river.straight_completed);
+        assert_eq!(river.connectedness, Connectedness::FourToStraight);
+
+        assert_eq!(texture("ks,kh,2c,2d,2s").pairing, Pairing::FullHouse);
+        assert_eq!(texture("ks,kh,2c,2d").pairing, Pairing::TwoPaired);
+        assert_eq!(
+            texture("qs,jh,2h,th").labels(),
+            vec!["queenHigh", "unpaired", "flushPossible", "connected"]
+        );
+    }
+
+    #[test]
+    fn test_names_match_serialization() {
+        let texture = texture("as,ah,5c,4d,3s");
+        for (name, value) in [
+            (
+                texture.high_card.name(),
+                serde_json::json!(texture.high_card),
+            ),
+            (texture.pairing.name(), serde_json::json!(texture.pairing)),
+            (
+                texture.suitedness.name(),
+                serde_json::json!(texture.suitedness),
+            ),
+            (
+                texture.connectedness.name(),
+                serde_json::json!(texture.connectedness),
+            ),
+        ] {
+            assert_eq!(value, name);
+        }
+    }
+
+    #[test]
+    fn test_boards_without_a_flop_are_rejected() {
+        assert!(classify_board(&[52; 5]).is_err());
+        assert!(classify_board(&[51, 44, 52, 52, 52]).is_err());
+    }
+}
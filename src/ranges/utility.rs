@@ -1,9 +1,10 @@
 use futures_lite::StreamExt;
 use rust_poker::constants::{RANK_TO_CHAR, SUIT_TO_CHAR};
-use rust_poker::hand_range::HandRange;
-use rust_poker::hand_range::{char_to_rank, char_to_suit};
+use std::collections::HashMap;
 
 use super::combination::{Board, Combination, Hand, Range};
+use super::parser::{parse_range, ParsedRange};
+use crate::error::SolverError;
 
 pub fn build_initial_suit_groups(board: &Board) -> Vec<u8> {
     let mut ranks_used = vec![0u16; 4];
@@ -71,6 +72,17 @@ pub fn board_has_river(board: &Board) -> bool {
     board[4] != 52
 }
 
+// 1 for the flop, 2 for the turn and 3 for the river
+pub fn board_street(board: &Board) -> u8 {
+    if board_has_river(board) {
+        3
+    } else if board_has_turn(board) {
+        2
+    } else {
+        1
+    }
+}
+
 pub fn check_card_overlap(card: u8, board: &Board) -> bool {
     board.iter().any(|&c| c == card)
 }
@@ -86,42 +98,33 @@ pub fn check_hands_overlap(hand1: &Hand, hand2: &Hand) -> bool {
 pub fn construct_starting_range_from_string(
     range_string: String,
     board: &Board,
-) -> Vec<Combination> {
-    let starting_range = HandRange::from_strings([range_string].to_vec());
-    let mut starting_combinations = vec![];
-    for hand in starting_range[0].hands.iter() {
-        let combo = Combination::new([hand.0, hand.1], 0, 0.0);
-        if !check_hand_overlap(combo.hand, board) {
-            starting_combinations.push(combo)
-        }
-    }
+) -> Result<Vec<Combination>, SolverError> {
+    Ok(starting_combinations(&parse_range(&range_string)?, board))
+}
 
-    starting_combinations
+pub fn starting_combinations(range: &ParsedRange, board: &Board) -> Vec<Combination> {
+    range
+        .hands()
+        .into_iter()
+        .filter(|(hand, _)| !check_hand_overlap(*hand, board))
+        .map(|(hand, _)| Combination::new(hand, 0, 0.0))
+        .collect()
 }
 
 // currently invariant is held that ip_hands[i] == oop_hands[i], need to test if this is faster than
 // fewer hands w/ maintaining reference into opponent hands for where equivalent hand is (bad locality?)
 pub fn build_player_specific_merged_range(
-    range_string: String,
+    range: &ParsedRange,
     merged_range: &[Combination],
 ) -> Vec<Combination> {
-    let starting_range = HandRange::from_strings([range_string].to_vec());
-    let mut starting_combinations = vec![];
-    let mut final_range = vec![];
-    for hand in starting_range[0].hands.iter() {
-        let combo = Combination::new([hand.0, hand.1], 0, f32::from(hand.2) / 100.0);
-        starting_combinations.push(combo)
-    }
-
-    for &hand in merged_range.iter() {
-        let to_add = match starting_combinations.iter().find(|&h| hand.eq(h)) {
-            None => hand,
-            Some(&matching_hand) => matching_hand
-        };
-        final_range.push(to_add)
-    }
-
-    final_range
+    merged_range
+        .iter()
+        .map(|&hand| {
+            let mut combo = hand;
+            combo.combos = range.weight(&hand.hand);
+            combo
+        })
+        .collect()
 }
 
 pub fn range_relative_probabilities(rng: &Range, opp_range: &Range) -> Vec<f32> {
@@ -167,12 +170,97 @@ pub fn hand_to_string(h: &Hand) -> String {
     format!("{}{}", number_to_card(h[0]), number_to_card(h[1]))
 }
 
-pub fn card_to_number(card: String) -> u8 {
-    let chars: Vec<char> = card.chars().collect();
-    let rank = char_to_rank(chars[0]);
-    let suit = char_to_suit(chars[1]);
+// labels a combo the way it is usually written, higher card first, e.g. AhKh
+pub fn combination_label(h: &Hand) -> String {
+    if h[0] >= h[1] {
+        hand_to_string(h)
+    } else {
+        hand_to_string(&[h[1], h[0]])
+    }
+}
+
+// reads a combo label such as AhKh back into its two cards, in the order they're written
+pub fn parse_combination(label: &str) -> Result<Hand, SolverError> {
+    match (label.get(..2), label.get(2..)) {
+        (Some(first), Some(second)) => Ok([
+            card_to_number(first.to_string())?,
+            card_to_number(second.to_string())?,
+        ]),
+        _ => Err(SolverError::InvalidCard(label.to_string())),
+    }
+}
+
+// suit isomorphic hands only carry weight on their canonical combo, for every hand this gives the index of the combo
+// whose results stand in for it
+pub fn canonical_positions(hands: &Range) -> Vec<usize> {
+    let positions: HashMap<usize, usize> = hands
+        .iter()
+        .enumerate()
+        .filter(|(_, hand)| hand.weight != 0)
+        .map(|(i, hand)| (hand.raw_index, i))
+        .collect();
+
+    hands
+        .iter()
+        .enumerate()
+        .map(|(i, hand)| {
+            if hand.weight == 0 {
+                positions.get(&hand.canon_index).copied().unwrap_or(i)
+            } else {
+                i
+            }
+        })
+        .collect()
+}
+
+pub fn card_to_number(card: String) -> Result<u8, SolverError> {
+    let chars: Vec<char> = card.trim().chars().collect();
+    if chars.len() != 2 {
+        return Err(SolverError::InvalidCard(card));
+    }
+
+    let rank = RANK_TO_CHAR
+        .iter()
+        .position(|c| c.eq_ignore_ascii_case(&chars[0]));
+    let suit = SUIT_TO_CHAR
+        .iter()
+        .position(|c| c.eq_ignore_ascii_case(&chars[1]));
+
+    match (rank, suit) {
+        (Some(rank), Some(suit)) => Ok(4 * rank as u8 + suit as u8),
+        _ => Err(SolverError::InvalidCard(card)),
+    }
+}
+
+// accepts comma separated cards, "qs,jh,2h", and fills any missing turn or river with 52
+pub fn parse_board(board: &str) -> Result<Board, SolverError> {
+    let cards = board
+        .split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| card_to_number(c.to_string()))
+        .collect::<Result<Vec<u8>, SolverError>>()?;
+
+    if cards.len() < 3 || cards.len() > 5 {
+        return Err(SolverError::InvalidBoard(format!(
+            "'{}' has {} cards, expected between 3 and 5",
+            board,
+            cards.len()
+        )));
+    }
 
-    4 * rank + suit
+    for (i, card) in cards.iter().enumerate() {
+        if cards[..i].contains(card) {
+            return Err(SolverError::InvalidBoard(format!(
+                "'{}' contains {} more than once",
+                board,
+                number_to_card(*card)
+            )));
+        }
+    }
+
+    let mut parsed: Board = [52; 5];
+    parsed[..cards.len()].copy_from_slice(&cards);
+    Ok(parsed)
 }
 
 pub fn number_to_card(card: u8) -> String {
@@ -193,9 +281,9 @@ mod tests {
     #[test]
     fn test_build_suit_groups() {
         let board: Board = [
-            card_to_number("7c".to_string()),
-            card_to_number("7h".to_string()),
-            card_to_number("7d".to_string()),
+            card_to_number("7c".to_string()).unwrap(),
+            card_to_number("7h".to_string()).unwrap(),
+            card_to_number("7d".to_string()).unwrap(),
             52, //card_to_number("3d".to_string()),
             52, //card_to_number("2c".to_string()),
         ];
@@ -203,4 +291,58 @@ mod tests {
 
         println!("{:?}", sg);
     }
+
+    #[test]
+    fn test_card_to_number_round_trip() {
+        for card in 0u8..52 {
+            assert_eq!(card_to_number(number_to_card(card)).unwrap(), card);
+        }
+        assert_eq!(
+            card_to_number("QS".to_string()).unwrap(),
+            card_to_number("qs".to_string()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_card_to_number_rejects_invalid_cards() {
+        assert!(card_to_number("".to_string()).is_err());
+        assert!(card_to_number("q".to_string()).is_err());
+        assert!(card_to_number("1s".to_string()).is_err());
+        assert!(card_to_number("qx".to_string()).is_err());
+        assert!(card_to_number("qsj".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_board() {
+        let board = parse_board("qs,jh,2h").unwrap();
+        assert_eq!(board[3], 52);
+        assert_eq!(board[4], 52);
+        assert_eq!(board[0], card_to_number("qs".to_string()).unwrap());
+
+        let board = parse_board("qs,jh,2h,3c,4d").unwrap();
+        assert_eq!(board[4], card_to_number("4d".to_string()).unwrap());
+
+        assert!(parse_board("qs,jh").is_err());
+        assert!(parse_board("qs,jh,2h,3c,4d,5d").is_err());
+        assert!(parse_board("qs,jh,zz").is_err());
+        assert!(parse_board("qs,jh,qs").is_err());
+    }
+
+    #[test]
+    fn test_canonical_positions() {
+        let ah = card_to_number("ah".to_string()).unwrap();
+        let kh = card_to_number("kh".to_string()).unwrap();
+        let ad = card_to_number("ad".to_string()).unwrap();
+        let kd = card_to_number("kd".to_string()).unwrap();
+
+        let canonical = Combination::new([kh, ah], 0, 1.0);
+        let mut isomorphic = Combination::new([kd, ad], 0, 1.0);
+        isomorphic.weight = 0;
+        isomorphic.canon_index = canonical.raw_index;
+
+        let hands = vec![isomorphic, canonical];
+        assert_eq!(canonical_positions(&hands), vec![1, 1]);
+        assert_eq!(combination_label(&hands[0].hand), "AdKd");
+        assert_eq!(combination_label(&hands[1].hand), "AhKh");
+    }
 }
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use super::combination::Board;
+use super::parser::{parse_player_range, NamedRanges};
+use super::utility::{check_hand_overlap, parse_board};
+use crate::error::SolverError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeReport {
+    pub player: String,
+    pub combos: usize,
+    pub weighted_combos: f32,
+    pub removed_by_board: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputReport {
+    pub board: Board,
+    pub oop: RangeReport,
+    pub ip: RangeReport,
+}
+
+pub fn validate_inputs(
+    board: &str,
+    oop_range: &str,
+    ip_range: &str,
+) -> Result<InputReport, SolverError> {
+    let board = parse_board(board)?;
+    let oop = validate_range("OOP", oop_range, &board)?;
+    let ip = validate_range("IP", ip_range, &board)?;
+
+    Ok(InputReport { board, oop, ip })
+}
+
+pub fn validate_range(
+    player: &str,
+    range: &str,
+    board: &Board,
+) -> Result<RangeReport, SolverError> {
+    if range.trim().is_empty() {
+        return Err(SolverError::InvalidRange(format!("{} range is empty", player)));
+    }
+    let parsed = parse_player_range(player, range, &NamedRanges::new())?;
+
+    let mut report = RangeReport {
+        player: player.to_string(),
+        combos: 0,
+        weighted_combos: 0.0,
+        removed_by_board: 0,
+    };
+
+    for (hand, weight) in parsed.hands() {
+        if check_hand_overlap(hand, board) {
+            report.removed_by_board += 1;
+        } else {
+            report.combos += 1;
+            report.weighted_combos += weight;
+        }
+    }
+
+    if report.combos == 0 {
+        return Err(SolverError::EmptyRange(player.to_string()));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranges::parser::parse_range;
+
+    #[test]
+    fn test_accepts_common_range_syntax() {
+        for token in [
+            "random", "AA", "AKs", "KQo", "T9", "22+", "A2s+", "K9o-K6o", "QQ-88", "AhKh", "AKs@50",
+            "77@0", "JTs@100",
+        ]
+        .iter()
+        {
+            assert!(parse_range(token).is_ok(), "{} should be valid", token);
+        }
+    }
+
+    #[test]
+    fn test_range_emptied_by_board() {
+        let board = parse_board("as,ah,ac").unwrap();
+        match validate_range("OOP", "AA", &board) {
+            Err(SolverError::EmptyRange(player)) => assert_eq!(player, "OOP"),
+            _ => panic!("AA should be empty on a board with three aces"),
+        }
+    }
+
+    #[test]
+    fn test_reports_combos() {
+        let report = validate_inputs("as,kd,2c", "AA,KK@50", "QQ").unwrap();
+
+        assert_eq!(report.oop.combos, 3 + 3);
+        assert_eq!(report.oop.removed_by_board, 6);
+        assert!((report.oop.weighted_combos - 4.5).abs() < 1e-6);
+        assert_eq!(report.ip.combos, 6);
+        assert_eq!(report.ip.removed_by_board, 0);
+    }
+
+    #[test]
+    fn test_rejects_empty_entries() {
+        let board = parse_board("as,kd,2c").unwrap();
+        assert!(validate_range("IP", "", &board).is_err());
+        assert!(validate_range("IP", "AA,,KK", &board).is_err());
+    }
+}